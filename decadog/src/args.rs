@@ -1,6 +1,11 @@
 use std::path::PathBuf;
 use structopt::StructOpt;
 
+use crate::command::board::Command as BoardCommand;
+use crate::command::cache::Command as CacheCommand;
+use crate::command::epic::Command as EpicCommand;
+use crate::command::release::Command as ReleaseCommand;
+use crate::command::report::Command as ReportCommand;
 use crate::command::sprint::Command as SprintCommand;
 
 /// Github and Zenhub toolkit. Octocat++.
@@ -16,6 +21,18 @@ pub struct Args {
     /// Defaults to ./decadog.yml
     pub config: Option<PathBuf>,
 
+    #[structopt(short = "v", long = "verbose")]
+    /// Show more information, such as remaining API quota.
+    pub verbose: bool,
+
+    #[structopt(long = "no-cache")]
+    /// Always fetch fresh data, ignoring and not updating the cache.
+    pub no_cache: bool,
+
+    #[structopt(long = "workspace")]
+    /// Name or id of the Zenhub workspace to use, overriding settings.
+    pub workspace: Option<String>,
+
     /// Subcommand selected.
     #[structopt(subcommand)]
     pub command: Command,
@@ -29,4 +46,55 @@ pub enum Command {
         #[structopt(subcommand)]
         command: SprintCommand,
     },
+
+    #[structopt(name = "board")]
+    /// Manage Zenhub boards.
+    Board {
+        #[structopt(subcommand)]
+        command: BoardCommand,
+    },
+
+    #[structopt(name = "epic")]
+    /// Track Zenhub epics.
+    Epic {
+        #[structopt(subcommand)]
+        command: EpicCommand,
+    },
+
+    #[structopt(name = "release")]
+    /// Track Zenhub releases.
+    Release {
+        #[structopt(subcommand)]
+        command: ReleaseCommand,
+    },
+
+    #[structopt(name = "report")]
+    /// Report on how work flows through the board.
+    Report {
+        #[structopt(subcommand)]
+        command: ReportCommand,
+    },
+
+    #[structopt(name = "deps")]
+    /// Show dependencies between issues in a sprint.
+    Deps {
+        #[structopt(long = "milestone")]
+        /// Title of the sprint. Prompts if not given.
+        milestone: Option<String>,
+    },
+
+    #[structopt(name = "cache")]
+    /// Manage cached API responses.
+    Cache {
+        #[structopt(subcommand)]
+        command: CacheCommand,
+    },
+
+    #[structopt(name = "serve-webhooks")]
+    /// Receive Github webhooks, keeping a local store of issues up to date.
+    ServeWebhooks {
+        #[structopt(long = "address")]
+        /// Address to listen on. Defaults to 127.0.0.1:8080
+        address: Option<String>,
+    },
 }
@@ -0,0 +1,115 @@
+use std::fmt;
+
+use decadog_core::PipelineOrder;
+use structopt::StructOpt;
+
+use crate::command::{client, workspace};
+use crate::interact::Confirm;
+use crate::{error::Error, Settings};
+
+/// What to order the issues in a pipeline by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderBy {
+    Estimate,
+    Labels,
+}
+
+impl fmt::Display for OrderBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Estimate => write!(f, "estimate"),
+            Self::Labels => write!(f, "labels"),
+        }
+    }
+}
+
+/// Parse what to order a pipeline by: `estimate` or `labels`.
+fn parse_order_by(value: &str) -> Result<OrderBy, String> {
+    match value {
+        "estimate" => Ok(OrderBy::Estimate),
+        "labels" => Ok(OrderBy::Labels),
+        _ => Err(format!(
+            "Invalid order {}; expected estimate or labels",
+            value
+        )),
+    }
+}
+
+fn reorder_pipeline(
+    pipeline_name: &str,
+    by: OrderBy,
+    labels: &[String],
+    settings: &Settings,
+) -> Result<(), Error> {
+    let order = match by {
+        OrderBy::Estimate => PipelineOrder::Estimate,
+        OrderBy::Labels if labels.is_empty() => {
+            return Err(Error::User {
+                description: "Ordering by labels requires --labels.".to_owned(),
+            })
+        }
+        OrderBy::Labels => PipelineOrder::Labels(labels.to_vec()),
+    };
+
+    let github = settings.github_client()?;
+    let zenhub = settings.zenhub_client("reorder pipelines")?;
+    let store = settings.store()?;
+    let client = client(settings, &github, &zenhub, store.as_ref())?;
+
+    let repositories = client.get_repositories()?;
+    let workspace = workspace(&client, settings, repositories.primary())?;
+    let board = client.get_workspace_board(&repositories, &workspace)?;
+    let pipeline = board
+        .pipelines
+        .iter()
+        .find(|pipeline| pipeline.name.eq_ignore_ascii_case(pipeline_name))
+        .ok_or_else(|| Error::User {
+            description: format!("No pipeline {} in workspace {}.", pipeline_name, workspace),
+        })?;
+
+    if !Confirm::new(&format!(
+        "Reorder {} issues in {} by {}?",
+        pipeline.issues.len(),
+        pipeline.name,
+        by
+    ))
+    .interact()?
+    {
+        return Ok(());
+    }
+    let moved = client.reorder_pipeline(&repositories, &workspace, pipeline, &order)?;
+    println!("Moved {} issues.", moved);
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    #[structopt(name = "reorder")]
+    /// Order the issues in a pipeline.
+    Reorder {
+        /// Name of the pipeline.
+        pipeline: String,
+
+        #[structopt(
+            long = "by",
+            possible_values = &["estimate", "labels"],
+            parse(try_from_str = parse_order_by)
+        )]
+        /// Largest estimate first, or by the first of --labels each issue has.
+        by: OrderBy,
+
+        #[structopt(long = "labels", use_delimiter = true)]
+        /// Labels in priority order, such as `P0,P1,P2`.
+        labels: Vec<String>,
+    },
+}
+
+pub fn run(command: &Command, settings: &Settings) -> Result<(), Error> {
+    match command {
+        Command::Reorder {
+            ref pipeline,
+            by,
+            ref labels,
+        } => reorder_pipeline(pipeline, *by, labels, settings),
+    }
+}
@@ -0,0 +1,28 @@
+use decadog_core::cache::Cache;
+use structopt::StructOpt;
+
+use crate::{error::Error, Settings};
+
+fn clear_cache(settings: &Settings) -> Result<(), Error> {
+    match settings.cache_dir() {
+        Some(cache_dir) => {
+            Cache::new(&cache_dir).clear()?;
+            eprintln!("Cleared cache at {}", cache_dir.display());
+        }
+        None => eprintln!("No cache directory found."),
+    }
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    #[structopt(name = "clear")]
+    /// Remove all cached API responses.
+    Clear,
+}
+
+pub fn run(command: &Command, settings: &Settings) -> Result<(), Error> {
+    match command {
+        Command::Clear => clear_cache(settings),
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use colored::Colorize;
+use decadog_core::github::{Issue, State};
+use decadog_core::zenhub::IssueReference;
+use decadog_core::{Client, Repositories, Sprint};
+
+use crate::command::{client, find_sprint_backend, select_sprint};
+use crate::{error::Error, Settings};
+
+/// Describe an issue related to one in the sprint.
+fn describe(
+    client: &Client,
+    repositories: &Repositories,
+    sprint_issues: &HashMap<IssueReference, Issue>,
+    sprint: &Sprint,
+    reference: &IssueReference,
+) -> Result<String, Error> {
+    let issue = match sprint_issues.get(reference) {
+        Some(issue) => issue.clone(),
+        None => match client.get_referenced_issue(repositories, reference)? {
+            Some(issue) => issue,
+            None => {
+                return Ok(format!(
+                    "#{} in unknown repository {}",
+                    reference.issue_number, reference.repo_id
+                ))
+            }
+        },
+    };
+    let description = if issue.state == State::Closed {
+        format!("{} (closed)", issue).dimmed().to_string()
+    } else if !sprint.contains(repositories, &issue) {
+        format!("{} (not in sprint)", issue).yellow().to_string()
+    } else {
+        issue.to_string()
+    };
+    Ok(description)
+}
+
+/// Print the dependencies of issues in a sprint.
+pub fn run(sprint_title: Option<&str>, settings: &Settings) -> Result<(), Error> {
+    let github = settings.github_client()?;
+    let zenhub = settings.zenhub_report_client("show dependencies")?;
+    let store = settings.store()?;
+    let client = client(settings, &github, &zenhub, store.as_ref())?;
+
+    let repositories = client.get_repositories()?;
+    let backend = find_sprint_backend(&client, settings, &repositories)?;
+    let sprint = match select_sprint(
+        &client,
+        &repositories,
+        &backend,
+        sprint_title,
+        false,
+        "Sprint",
+    )? {
+        Some(sprint) => sprint,
+        None => return Ok(()),
+    };
+
+    let dependencies = client.get_dependencies(&repositories)?;
+    let mut sprint_issues = HashMap::new();
+    for issue in client.get_sprint_issues(&repositories, &sprint, None)? {
+        sprint_issues.insert(repositories.reference(&issue)?, issue);
+    }
+
+    let mut references: Vec<&IssueReference> = sprint_issues.keys().collect();
+    references.sort_by_key(|reference| (reference.repo_id, reference.issue_number));
+    let mut any_dependencies = false;
+    for reference in references {
+        let blocking: Vec<_> = dependencies.blocking(reference).collect();
+        let blocked: Vec<_> = dependencies.blocked(reference).collect();
+        if blocking.is_empty() && blocked.is_empty() {
+            continue;
+        }
+        any_dependencies = true;
+
+        println!();
+        println!("{}", sprint_issues[reference].to_string().bold());
+        for (relation, related) in blocking
+            .into_iter()
+            .map(|related| ("blocked by", related))
+            .chain(blocked.into_iter().map(|related| ("blocks", related)))
+        {
+            let description = describe(&client, &repositories, &sprint_issues, &sprint, related)?;
+            println!("  {} {}", relation, description);
+        }
+    }
+    if !any_dependencies {
+        eprintln!("No dependencies in {}.", sprint);
+    }
+    Ok(())
+}
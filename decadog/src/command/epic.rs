@@ -0,0 +1,67 @@
+use colored::Colorize;
+use structopt::StructOpt;
+
+use crate::command::{client, find_sprint_backend, select_sprint};
+use crate::{error::Error, Settings};
+
+fn epic_status(settings: &Settings) -> Result<(), Error> {
+    let github = settings.github_client()?;
+    let zenhub = settings.zenhub_report_client("show epic status")?;
+    let store = settings.store()?;
+    let client = client(settings, &github, &zenhub, store.as_ref())?;
+
+    // Select the current sprint, if there is one
+    let repositories = client.get_repositories()?;
+    let backend = find_sprint_backend(&client, settings, &repositories)?;
+    let sprint = select_sprint(
+        &client,
+        &repositories,
+        &backend,
+        None,
+        false,
+        "Current sprint",
+    )?;
+
+    let statuses = client.get_epic_statuses(&repositories, sprint.as_ref())?;
+    if statuses.is_empty() {
+        eprintln!("No epics.");
+        return Ok(());
+    }
+
+    for status in statuses.iter() {
+        println!();
+        println!("{}", status.epic.to_string().bold());
+        println!(
+            "{} points: {} done, {} remaining",
+            status.total_points,
+            status.done_points,
+            status.remaining_points()
+        );
+        if status.unknown_issues > 0 {
+            println!(
+                "{} issues in other repositories not counted",
+                status.unknown_issues
+            );
+        }
+        if !status.in_sprint.is_empty() {
+            println!("In sprint:");
+            for issue in status.in_sprint.iter() {
+                println!("  {}", issue);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    #[structopt(name = "status")]
+    /// Show the progress of each epic.
+    Status,
+}
+
+pub fn run(command: &Command, settings: &Settings) -> Result<(), Error> {
+    match command {
+        Command::Status => epic_status(settings),
+    }
+}
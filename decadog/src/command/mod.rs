@@ -1 +1,201 @@
+pub mod board;
+pub mod cache;
+pub mod deps;
+pub mod epic;
+pub mod release;
+pub mod report;
 pub mod sprint;
+pub mod webhooks;
+
+use std::fmt::Display;
+use std::io::{self, IsTerminal};
+
+use decadog_core::github::Repository;
+use decadog_core::store::Store;
+use decadog_core::zenhub::{SprintState, Workspace};
+use decadog_core::{
+    describe_workspaces, github, zenhub, Client, Placement, Repositories, Sprint, SprintBackend,
+};
+
+use crate::interact::Select;
+use crate::{error::Error, Settings};
+
+/// Create a client for all repositories in `settings`, reading issues from `store` if given.
+pub fn client<'a>(
+    settings: &'a Settings,
+    github: &'a github::Client,
+    zenhub: &'a zenhub::Client,
+    store: Option<&'a Store>,
+) -> Result<Client<'a>, Error> {
+    let mut client = Client::new(&settings.owner, &settings.repo, github, zenhub)?;
+    if let Some(store) = store {
+        client = client.with_store(store);
+    }
+    if let Some(repositories) = &settings.repositories {
+        client = client.with_repositories(repositories)?;
+    }
+    if let Some(concurrency) = settings.concurrency {
+        client = client.with_concurrency(concurrency);
+    }
+    Ok(client)
+}
+
+/// Parse a placement in a pipeline: `top`, `bottom`, an index, or `above:<issue>` or
+/// `below:<issue>`, where `<issue>` is a reference such as `12` or `repo#12`.
+pub fn parse_placement(value: &str) -> Result<Placement, String> {
+    let placement = match value {
+        "top" => Placement::Top,
+        "bottom" => Placement::Bottom,
+        _ => {
+            if let Some(reference) = value.strip_prefix("above:") {
+                Placement::Above(reference.to_owned())
+            } else if let Some(reference) = value.strip_prefix("below:") {
+                Placement::Below(reference.to_owned())
+            } else {
+                Placement::Index(value.parse().map_err(|_| {
+                    format!(
+                        "Invalid position {}; expected top, bottom, an index, \
+                         above:<issue> or below:<issue>",
+                        value
+                    )
+                })?)
+            }
+        }
+    };
+    Ok(placement)
+}
+
+/// Choose the Zenhub workspace for `repository`.
+///
+/// Uses the workspace in `settings` if given, otherwise prompts when there is more than one.
+pub fn workspace(
+    client: &Client,
+    settings: &Settings,
+    repository: &Repository,
+) -> Result<Workspace, Error> {
+    if let Some(name_or_id) = &settings.zenhub_workspace {
+        return Ok(client.get_workspace(repository, name_or_id)?);
+    }
+
+    let mut workspaces = client.get_workspaces(repository)?;
+    match workspaces.len() {
+        0 => Err(Error::Settings {
+            description: format!("No Zenhub workspace found for {}.", repository.name),
+        }),
+        1 => Ok(workspaces.remove(0)),
+        _ if io::stdin().is_terminal() => {
+            let select = Select::new("Zenhub workspace", &workspaces)
+                .expect("At least one workspace is required.");
+            Ok(select.interact()?.to_owned())
+        }
+        _ => Err(Error::Settings {
+            description: format!(
+                "{} is in several Zenhub workspaces. Set zenhub_workspace or pass --workspace \
+                 to choose one of: {}",
+                repository.name,
+                describe_workspaces(&workspaces)
+            ),
+        }),
+    }
+}
+
+/// Where sprints are kept, as configured in `settings`.
+///
+/// Zenhub native sprints are those of `workspace`.
+pub fn sprint_backend(settings: &Settings, workspace: &Workspace) -> SprintBackend {
+    if settings.native_sprints() {
+        SprintBackend::Zenhub(workspace.id.clone())
+    } else {
+        SprintBackend::Milestones
+    }
+}
+
+/// Where sprints are kept, as configured in `settings`.
+///
+/// Only looks up the Zenhub workspace if native sprints are used.
+pub fn find_sprint_backend(
+    client: &Client,
+    settings: &Settings,
+    repositories: &Repositories,
+) -> Result<SprintBackend, Error> {
+    if settings.native_sprints() {
+        let workspace = workspace(client, settings, repositories.primary())?;
+        Ok(sprint_backend(settings, &workspace))
+    } else {
+        Ok(SprintBackend::Milestones)
+    }
+}
+
+/// Choose the item titled `title` if given, otherwise prompt for one.
+///
+/// Returns `None` if there are no items to choose from.
+fn choose<T, F>(
+    items: &[T],
+    title: Option<&str>,
+    item_title: F,
+    prompt: &str,
+) -> Result<Option<T>, Error>
+where
+    T: Clone + Display,
+    F: Fn(&T) -> &str,
+{
+    if items.is_empty() {
+        return Ok(None);
+    }
+    match title {
+        Some(title) => items
+            .iter()
+            .find(|item| item_title(item) == title)
+            .map(|item| Some(item.clone()))
+            .ok_or_else(|| Error::User {
+                description: format!("No sprint {}.", title),
+            }),
+        None => {
+            let select = Select::new(prompt, items).expect("At least one sprint is required.");
+            Ok(Some(select.interact()?.to_owned()))
+        }
+    }
+}
+
+/// Choose a sprint from `backend`, by `title` if given, otherwise at a prompt.
+///
+/// Only open sprints are candidates, unless `closed` is set. Returns `None` if there
+/// are no candidates.
+pub fn select_sprint(
+    client: &Client,
+    repositories: &Repositories,
+    backend: &SprintBackend,
+    title: Option<&str>,
+    closed: bool,
+    prompt: &str,
+) -> Result<Option<Sprint>, Error> {
+    match backend {
+        SprintBackend::Milestones => {
+            let milestones = if closed {
+                client.get_all_milestones()?
+            } else {
+                client.get_milestones()?
+            };
+            match choose(&milestones, title, |milestone| &milestone.title, prompt)? {
+                Some(milestone) => Ok(Some(client.get_sprint(repositories, milestone)?)),
+                None => {
+                    eprintln!("No {}milestones.", if closed { "" } else { "open " });
+                    Ok(None)
+                }
+            }
+        }
+        SprintBackend::Zenhub(workspace_id) => {
+            let mut sprints = client.get_native_sprints(workspace_id, SprintState::Open)?;
+            if closed {
+                sprints.extend(client.get_native_sprints(workspace_id, SprintState::Closed)?);
+            }
+            match choose(&sprints, title, |sprint| sprint.title(), prompt)? {
+                Some(sprint) => Ok(Some(client.get_native_sprint(sprint)?)),
+                None => {
+                    eprintln!("No {}Zenhub sprints.", if closed { "" } else { "open " });
+                    Ok(None)
+                }
+            }
+        }
+    }
+}
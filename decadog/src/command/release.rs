@@ -0,0 +1,258 @@
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate};
+use colored::Colorize;
+use decadog_core::github::Issue;
+use decadog_core::zenhub::{Release, ReleaseState};
+use decadog_core::{Client, Repositories};
+use structopt::StructOpt;
+
+use crate::command::{client, workspace};
+use crate::interact::Confirm;
+use crate::{error::Error, Settings};
+
+/// Parse a date such as `2020-01-31`, as midday UTC like the Zenhub UI.
+fn parse_date(value: &str) -> Result<DateTime<FixedOffset>, String> {
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date {}; expected YYYY-MM-DD", value))?;
+    Ok(DateTime::from_utc(
+        date.and_hms(12, 00, 00),
+        FixedOffset::east(0),
+    ))
+}
+
+/// Find a release by title or id, open or closed.
+fn find_release(
+    client: &Client,
+    repositories: &Repositories,
+    title_or_id: &str,
+) -> Result<Release, Error> {
+    client
+        .get_releases(repositories)?
+        .into_iter()
+        .find(|release| release.matches(title_or_id))
+        .ok_or_else(|| Error::User {
+            description: format!("No release {}.", title_or_id),
+        })
+}
+
+fn release_status(all: bool, weeks: u32, settings: &Settings) -> Result<(), Error> {
+    let github = settings.github_client()?;
+    let zenhub = settings.zenhub_report_client("show release status")?;
+    let store = settings.store()?;
+    let client = client(settings, &github, &zenhub, store.as_ref())?;
+
+    let repositories = client.get_repositories()?;
+    let releases: Vec<Release> = client
+        .get_releases(&repositories)?
+        .into_iter()
+        .filter(|release| all || release.state == ReleaseState::Open)
+        .collect();
+    if releases.is_empty() {
+        eprintln!("No releases.");
+        return Ok(());
+    }
+    let workspace = workspace(&client, settings, repositories.primary())?;
+    let board_index = client.get_board_index(&repositories, &workspace)?;
+
+    for release in releases.into_iter() {
+        let progress = client.get_release_progress(
+            &repositories,
+            Some(&board_index),
+            release,
+            Duration::weeks(weeks.into()),
+        )?;
+        let release = &progress.release;
+
+        println!();
+        match release.state {
+            ReleaseState::Open => println!("{}", release.title.bold()),
+            ReleaseState::Closed => println!("{} {}", release.title.bold(), "(closed)".dimmed()),
+        }
+        println!(
+            "  {} to {}",
+            release.start_date.format("%Y-%m-%d"),
+            release.desired_end_date.format("%Y-%m-%d")
+        );
+        println!(
+            "  scope: {} issues, {} points",
+            progress.issues, progress.total_points
+        );
+        println!(
+            "  done: {} issues, {} points",
+            progress.closed_issues, progress.done_points
+        );
+        println!("  remaining: {} points", progress.remaining_points());
+        if progress.unestimated_issues > 0 {
+            println!(
+                "  {}",
+                format!(
+                    "{} open issues without an estimate",
+                    progress.unestimated_issues
+                )
+                .yellow()
+            );
+        }
+        println!(
+            "  velocity: {:.1} points a week over the last {} weeks",
+            progress.velocity, weeks
+        );
+        let projection = match progress.projected_date {
+            Some(_) if progress.remaining_points() == 0 => "done".to_owned(),
+            Some(projected_date) => projected_date.format("%Y-%m-%d").to_string(),
+            None => "unknown, no recent progress".to_owned(),
+        };
+        if progress.is_late() && progress.remaining_points() > 0 {
+            println!("  projected: {}", projection.red());
+        } else {
+            println!("  projected: {}", projection.green());
+        }
+    }
+    Ok(())
+}
+
+fn create_release(
+    title: &str,
+    description: Option<&str>,
+    start: DateTime<FixedOffset>,
+    due: DateTime<FixedOffset>,
+    settings: &Settings,
+) -> Result<(), Error> {
+    let github = settings.github_client()?;
+    let zenhub = settings.zenhub_client("create release")?;
+    let store = settings.store()?;
+    let client = client(settings, &github, &zenhub, store.as_ref())?;
+
+    let repositories = client.get_repositories()?;
+    let release = client.create_release(&repositories, title, description, start, due)?;
+    eprintln!(
+        "Created release '{}' ({}) from {} to {}",
+        release.title,
+        release.release_id,
+        release.start_date.format("%Y-%m-%d"),
+        release.desired_end_date.format("%Y-%m-%d")
+    );
+    Ok(())
+}
+
+fn update_release_issues(
+    title_or_id: &str,
+    references: &[String],
+    add: bool,
+    settings: &Settings,
+) -> Result<(), Error> {
+    let github = settings.github_client()?;
+    let zenhub = settings.zenhub_client("update release")?;
+    let store = settings.store()?;
+    let client = client(settings, &github, &zenhub, store.as_ref())?;
+
+    let repositories = client.get_repositories()?;
+    let release = find_release(&client, &repositories, title_or_id)?;
+    let issues = references
+        .iter()
+        .map(|reference| {
+            client
+                .get_issue_by_reference(reference)
+                .map_err(|_| Error::User {
+                    description: format!("Invalid issue {}.", reference),
+                })
+        })
+        .collect::<Result<Vec<Issue>, _>>()?;
+    for issue in issues.iter() {
+        eprintln!("{}", issue);
+    }
+
+    let action = if add { "Add" } else { "Remove" };
+    let preposition = if add { "to" } else { "from" };
+    if !Confirm::new(&format!(
+        "{} {} issues {} {}?",
+        action,
+        issues.len(),
+        preposition,
+        release
+    ))
+    .interact()?
+    {
+        return Ok(());
+    }
+    if add {
+        client.update_release_issues(&repositories, &release, &issues, &[])?;
+    } else {
+        client.update_release_issues(&repositories, &release, &[], &issues)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    #[structopt(name = "status")]
+    /// Show the progress of each release, and when it is projected to be done.
+    Status {
+        #[structopt(long = "all")]
+        /// Include closed releases.
+        all: bool,
+
+        #[structopt(long = "weeks", default_value = "4")]
+        /// Weeks of recent progress to work out velocity from.
+        weeks: u32,
+    },
+
+    #[structopt(name = "create")]
+    /// Create a release spanning every repository.
+    Create {
+        /// Title of the release.
+        title: String,
+
+        #[structopt(long = "start", parse(try_from_str = parse_date))]
+        /// Start date, such as 2020-01-31.
+        start: DateTime<FixedOffset>,
+
+        #[structopt(long = "due", parse(try_from_str = parse_date))]
+        /// Desired end date, such as 2020-03-31.
+        due: DateTime<FixedOffset>,
+
+        #[structopt(long = "description")]
+        /// Description of the release.
+        description: Option<String>,
+    },
+
+    #[structopt(name = "add")]
+    /// Add issues to a release.
+    Add {
+        /// Title or id of the release.
+        release: String,
+
+        #[structopt(required = true)]
+        /// Issues to add, such as `12` or `repo#12`.
+        issues: Vec<String>,
+    },
+
+    #[structopt(name = "remove")]
+    /// Remove issues from a release.
+    Remove {
+        /// Title or id of the release.
+        release: String,
+
+        #[structopt(required = true)]
+        /// Issues to remove, such as `12` or `repo#12`.
+        issues: Vec<String>,
+    },
+}
+
+pub fn run(command: &Command, settings: &Settings) -> Result<(), Error> {
+    match command {
+        Command::Status { all, weeks } => release_status(*all, *weeks, settings),
+        Command::Create {
+            ref title,
+            start,
+            due,
+            ref description,
+        } => create_release(title, description.as_deref(), *start, *due, settings),
+        Command::Add {
+            ref release,
+            ref issues,
+        } => update_release_issues(release, issues, true, settings),
+        Command::Remove {
+            ref release,
+            ref issues,
+        } => update_release_issues(release, issues, false, settings),
+    }
+}
@@ -0,0 +1,164 @@
+use chrono::Duration;
+use colored::Colorize;
+use decadog_core::flow::{FlowSummary, IssueFlow, Percentiles};
+use decadog_core::{Client, Repositories, Sprint};
+use structopt::StructOpt;
+
+use crate::command::{client, find_sprint_backend, select_sprint};
+use crate::{error::Error, Settings};
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1}d", duration.num_minutes() as f64 / (24.0 * 60.0))
+}
+
+fn format_percentiles(percentiles: Option<&Percentiles>) -> String {
+    match percentiles {
+        Some(percentiles) => format!(
+            "p50 {}, p85 {}, p95 {} (of {})",
+            format_duration(percentiles.p50),
+            format_duration(percentiles.p85),
+            format_duration(percentiles.p95),
+            percentiles.count
+        ),
+        None => "-".to_owned(),
+    }
+}
+
+fn print_summary(summary: &FlowSummary, indent: &str) {
+    println!(
+        "{}cycle time: {}",
+        indent,
+        format_percentiles(summary.cycle_time.as_ref())
+    );
+    println!(
+        "{}lead time: {}",
+        indent,
+        format_percentiles(summary.lead_time.as_ref())
+    );
+}
+
+/// Get the flow of every issue in `sprint`.
+fn sprint_flows(
+    client: &Client,
+    repositories: &Repositories,
+    sprint: &Sprint,
+    in_progress: &str,
+) -> Result<Vec<IssueFlow>, Error> {
+    let issues = client.get_sprint_issues(repositories, sprint, None)?;
+    Ok(client.get_issue_flows(repositories, issues, in_progress)?)
+}
+
+fn report_flow(
+    sprint_titles: &[String],
+    in_progress: &str,
+    settings: &Settings,
+) -> Result<(), Error> {
+    let github = settings.github_client()?;
+    let zenhub = settings.zenhub_report_client("report flow")?;
+    let store = settings.store()?;
+    let client = client(settings, &github, &zenhub, store.as_ref())?;
+
+    let repositories = client.get_repositories()?;
+    let backend = find_sprint_backend(&client, settings, &repositories)?;
+    let titles: Vec<Option<&str>> = if sprint_titles.is_empty() {
+        vec![None]
+    } else {
+        sprint_titles
+            .iter()
+            .map(|title| Some(title.as_ref()))
+            .collect()
+    };
+    let mut selected = Vec::new();
+    for title in titles {
+        match select_sprint(&client, &repositories, &backend, title, true, "Sprint")? {
+            Some(sprint) => selected.push(sprint),
+            None => return Ok(()),
+        }
+    }
+
+    let mut all_flows = Vec::new();
+    for sprint in selected.iter() {
+        let flows = sprint_flows(&client, &repositories, sprint, in_progress)?;
+        println!();
+        println!("{}", sprint.title().bold());
+        for flow in flows.iter() {
+            let estimate = match flow.estimate {
+                Some(estimate) => format!("{} points", estimate),
+                None => "no estimate".to_owned(),
+            };
+            let times = |duration: Option<Duration>| match duration {
+                Some(duration) => format_duration(duration),
+                None => "-".to_owned(),
+            };
+            println!(
+                "  {} ({}): cycle {}, lead {}",
+                flow.issue,
+                estimate,
+                times(flow.cycle_time),
+                times(flow.lead_time)
+            );
+            let pipeline_times: Vec<String> = flow
+                .pipeline_times
+                .iter()
+                .map(|(pipeline, duration)| format!("{} {}", pipeline, format_duration(*duration)))
+                .collect();
+            if !pipeline_times.is_empty() {
+                println!("    {}", pipeline_times.join(", ").dimmed());
+            }
+        }
+
+        let summary = FlowSummary::new(flows.iter());
+        println!("{} issues", summary.issues);
+        print_summary(&summary, "  ");
+        for (pipeline, percentiles) in summary.pipeline_times.iter() {
+            println!(
+                "  in {}: {}",
+                pipeline,
+                format_percentiles(Some(percentiles))
+            );
+        }
+        all_flows.extend(flows);
+    }
+
+    println!();
+    println!("{}", "By estimate".bold());
+    for (estimate, summary) in FlowSummary::by_estimate(&all_flows).iter() {
+        match estimate {
+            Some(estimate) => println!("{} points ({} issues)", estimate, summary.issues),
+            None => println!("No estimate ({} issues)", summary.issues),
+        }
+        print_summary(summary, "  ");
+    }
+
+    println!();
+    println!("{}", "By label".bold());
+    for (label, summary) in FlowSummary::by_label(&all_flows).iter() {
+        println!("{} ({} issues)", label, summary.issues);
+        print_summary(summary, "  ");
+    }
+    Ok(())
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    #[structopt(name = "flow")]
+    /// Show time in each pipeline, cycle time and lead time of sprint issues.
+    Flow {
+        #[structopt(long = "milestone")]
+        /// Title of a sprint, which may be given several times. Prompts if not given.
+        milestones: Vec<String>,
+
+        #[structopt(long = "in-progress", default_value = "In Progress")]
+        /// Pipeline in which cycle time starts.
+        in_progress: String,
+    },
+}
+
+pub fn run(command: &Command, settings: &Settings) -> Result<(), Error> {
+    match command {
+        Command::Flow {
+            ref milestones,
+            ref in_progress,
+        } => report_flow(milestones, in_progress, settings),
+    }
+}
@@ -1,14 +1,15 @@
 use chrono::{DateTime, Duration, FixedOffset, Local};
 use colored::Colorize;
 use decadog_core::github::{
-    self, Milestone, OrganisationMember, Repository, SearchQueryBuilder, State,
+    DateQualifier, DateRange, OrganisationMember, SearchQueryBuilder, State,
 };
-use decadog_core::zenhub::{self, Estimate, Pipeline, Workspace};
-use decadog_core::{AssignedTo, Client};
+use decadog_core::zenhub::{Estimate, NativeSprint, Pipeline, SprintState, Workspace};
+use decadog_core::{AssignedTo, Client, Dependencies, Placement, Repositories, Sprint};
 use lazy_static::lazy_static;
 use log::error;
 use structopt::StructOpt;
 
+use crate::command::{client, parse_placement, select_sprint, sprint_backend, workspace};
 use crate::interact::{Confirm, FuzzySelect, Input, Select};
 use crate::{error::Error, Settings};
 
@@ -57,12 +58,14 @@ impl SprintPoints {
     }
 }
 
-struct MilestoneManager<'a> {
+struct SprintManager<'a> {
     client: &'a Client<'a>,
-    milestone: &'a Milestone,
+    sprint: Sprint,
 
-    repository: Repository,
+    repositories: Repositories,
+    dependencies: Dependencies,
     workspace: Workspace,
+    placement: &'a Placement,
     pipeline_options: FuzzySelect<Pipeline>,
     member_options: FuzzySelect<OrganisationMember>,
 }
@@ -73,18 +76,24 @@ enum LoopStatus {
     NextPipeline,
 }
 
-impl<'a> MilestoneManager<'a> {
-    fn new(client: &'a Client<'a>, milestone: &'a Milestone) -> Result<Self, Error> {
-        let organisation_members = client.get_members()?;
+impl<'a> SprintManager<'a> {
+    fn new(
+        client: &'a Client<'a>,
+        sprint: Sprint,
+        repositories: Repositories,
+        workspace: Workspace,
+        settings: &Settings,
+        placement: &'a Placement,
+    ) -> Result<Self, Error> {
+        let organisation_members = client.get_members(&settings.member_source()?)?;
         let member_options: FuzzySelect<OrganisationMember> = organisation_members
             .into_iter()
             .map(|member| (member.login.clone(), member))
             .collect();
 
-        let repository = client.get_repository()?;
-        let workspace = client.get_first_workspace(&repository)?;
+        let dependencies = client.get_dependencies(&repositories)?;
 
-        let board = client.get_board(&repository, &workspace)?;
+        let board = client.get_workspace_board(&repositories, &workspace)?;
         let pipeline_options: FuzzySelect<Pipeline> = board
             .pipelines
             .into_iter()
@@ -93,19 +102,21 @@ impl<'a> MilestoneManager<'a> {
 
         Ok(Self {
             client,
-            milestone,
-            repository,
+            sprint,
+            repositories,
+            dependencies,
             workspace,
+            placement,
             member_options,
             pipeline_options,
         })
     }
 
-    fn manage(&self) -> Result<(), Error> {
+    fn manage(&mut self) -> Result<(), Error> {
         loop {
-            let pipeline = self.pipeline_options.interact()?;
+            let pipeline = self.pipeline_options.interact()?.clone();
             loop {
-                match self.manage_issue(pipeline) {
+                match self.manage_issue(&pipeline) {
                     Ok(LoopStatus::Success) => continue,
                     Ok(LoopStatus::NextPipeline) => break,
                     Ok(LoopStatus::Quit) => return Ok(()),
@@ -115,46 +126,64 @@ impl<'a> MilestoneManager<'a> {
         }
     }
 
-    fn manage_issue(&self, pipeline: &Pipeline) -> Result<LoopStatus, Error> {
-        // Input an issue number
-        let issue_number_str = Input::<String>::new()
-            .with_prompt("Issue number (n: next pipeline, q: quit)")
+    fn manage_issue(&mut self, pipeline: &Pipeline) -> Result<LoopStatus, Error> {
+        // Input an issue number, optionally with a repository
+        let issue_reference = Input::<String>::new()
+            .with_prompt("Issue number or repo#number (n: next pipeline, q: quit)")
             .interact()?;
 
-        // Fetch the issue and parse the number
-        if issue_number_str == "q" {
+        // Fetch the issue by reference
+        if issue_reference == "q" {
             return Ok(LoopStatus::Quit);
-        } else if issue_number_str == "n" {
+        } else if issue_reference == "n" {
             return Ok(LoopStatus::NextPipeline);
         }
-        let issue_number = issue_number_str.parse().map_err(|_| Error::User {
-            description: format!("Invalid issue number {}.", &issue_number_str),
-        })?;
-
-        let issue = self.client.get_issue(issue_number)?;
+        let issue = self
+            .client
+            .get_issue_by_reference(&issue_reference)
+            .map_err(|_| Error::User {
+                description: format!("Invalid issue {}.", &issue_reference),
+            })?;
         eprintln!("{}", issue);
 
-        // If already assigned to the target milestone, no-op
-        if issue.assigned_to(self.milestone) {
-            eprintln!("Already in milestone.");
+        let repository = self.repositories.for_issue(&issue)?;
+
+        // If already in the sprint, no-op
+        if self.sprint.contains(&self.repositories, &issue) {
+            eprintln!("Already in sprint.");
         } else {
-            // Otherwise, confirm the assignment
-            if Confirm::new("Assign to milestone?").interact()? {
+            // Otherwise, confirm adding it
+            if Confirm::new("Add to sprint?").interact()? {
                 self.client
-                    .assign_issue_to_milestone(&issue, Some(&self.milestone))?;
+                    .add_issue_to_sprint(&self.repositories, &mut self.sprint, &issue)?;
             } else {
                 return Ok(LoopStatus::Success);
             }
         }
 
-        if issue.assigned_to(pipeline) {
+        // Warn if the issue cannot be finished in this sprint
+        for blocker in
+            self.client
+                .get_open_blockers(&self.repositories, &self.dependencies, &issue)?
+        {
+            if !self.sprint.contains(&self.repositories, &blocker) {
+                eprintln!(
+                    "{} blocked by {}, which is not in the sprint.",
+                    "Warning:".yellow(),
+                    blocker
+                );
+            }
+        }
+
+        if pipeline.contains(repository, &issue) {
             eprintln!("Already in pipeline.");
         } else {
             self.client.move_issue_to_pipeline(
-                &self.repository,
+                &self.repositories,
                 &self.workspace,
                 &issue,
                 &pipeline,
+                self.placement,
             )?;
         }
 
@@ -187,60 +216,72 @@ impl<'a> MilestoneManager<'a> {
     }
 }
 
-fn sync_sprint(settings: &Settings) -> Result<(), Error> {
-    let github = github::Client::new(&settings.github_url, &settings.github_token.value())?;
-    let zenhub = zenhub::Client::new(
-        settings
-            .zenhub_url
-            .as_ref()
-            .ok_or(Error::Settings {
-                description: "Zenhub url required to sync sprint.".to_owned(),
-            })?
-            .as_ref(),
-        settings
-            .zenhub_token
-            .as_ref()
-            .ok_or(Error::Settings {
-                description: "Zenhub token required to sync sprint.".to_owned(),
-            })?
-            .as_ref(),
+fn sync_sprint(placement: &Placement, settings: &Settings) -> Result<(), Error> {
+    let github = settings.github_client()?;
+    let zenhub = settings.zenhub_client("sync sprint")?;
+    let store = settings.store()?;
+    let client = client(settings, &github, &zenhub, store.as_ref())?;
+
+    // Select sprint to move tickets to
+    let repositories = client.get_repositories()?;
+    let workspace = workspace(&client, settings, repositories.primary())?;
+    let backend = sprint_backend(settings, &workspace);
+    let sprint = match select_sprint(
+        &client,
+        &repositories,
+        &backend,
+        None,
+        false,
+        "Sprint to sync",
+    )? {
+        Some(sprint) => sprint,
+        None => return Ok(()),
+    };
+
+    let mut sprint_manager = SprintManager::new(
+        &client,
+        sprint,
+        repositories,
+        workspace,
+        settings,
+        placement,
     )?;
-    let client = Client::new(&settings.owner, &settings.repo, &github, &zenhub)?;
-
-    // Select milestone to move tickets to
-    let milestones = client.get_milestones()?;
-    if milestones.is_empty() {
-        eprintln!("No open milestones.");
-        return Ok(());
-    }
-
-    let select_milestone =
-        Select::new("Sprint to sync", &milestones).expect("At least one milestone is required.");
-    let open_milestone = select_milestone.interact()?;
-
-    let milestone_manager = MilestoneManager::new(&client, open_milestone)?;
-    milestone_manager.manage()
+    sprint_manager.manage()
 }
 
 fn create_sprint(settings: &Settings) -> Result<(), Error> {
-    let github = github::Client::new(&settings.github_url, &settings.github_token.value())?;
-    let zenhub = zenhub::Client::new(
-        settings
-            .zenhub_url
-            .as_ref()
-            .ok_or(Error::Settings {
-                description: "Zenhub url required to create sprint.".to_owned(),
-            })?
-            .as_ref(),
-        settings
-            .zenhub_token
-            .as_ref()
-            .ok_or(Error::Settings {
-                description: "Zenhub token required to create sprint.".to_owned(),
-            })?
-            .as_ref(),
-    )?;
-    let client = Client::new(&settings.owner, &settings.repo, &github, &zenhub)?;
+    let github = settings.github_client()?;
+    let zenhub = settings.zenhub_client("create sprint")?;
+    let store = settings.store()?;
+    let client = client(settings, &github, &zenhub, store.as_ref())?;
+
+    if settings.native_sprints() {
+        // Zenhub creates native sprints from the workspace sprint settings
+        let repositories = client.get_repositories()?;
+        let workspace = workspace(&client, settings, repositories.primary())?;
+        let now = Local::now();
+        let upcoming: Vec<NativeSprint> = client
+            .get_native_sprints(&workspace.id, SprintState::Open)?
+            .into_iter()
+            .filter(|sprint| sprint.start_at > now)
+            .collect();
+        eprintln!(
+            "Zenhub creates sprints from the sprint settings of {}.",
+            workspace
+        );
+        if upcoming.is_empty() {
+            eprintln!("No upcoming sprints; check sprints are enabled for the workspace.");
+        }
+        for sprint in upcoming.iter() {
+            eprintln!(
+                "Upcoming '{}' from {} to {}",
+                sprint,
+                sprint.start_at.format("%Y-%m-%d"),
+                sprint.end_at.format("%Y-%m-%d")
+            );
+        }
+        return Ok(());
+    }
 
     // Select milestone to move tickets to
     if Confirm::new("Create sprint from today for two weeks?").interact()? {
@@ -248,96 +289,168 @@ fn create_sprint(settings: &Settings) -> Result<(), Error> {
             .with_prompt("Sprint number")
             .interact()?;
 
-        let repository = client.get_repository()?;
+        let repositories = client.get_repositories()?;
         // Zenhub UI uses dates with midday, so copy that here
         let start_date = DateTime::from_utc(
             Local::today().naive_local().and_hms(12, 00, 00),
             FixedOffset::east(0),
         );
         let due_on = start_date + Duration::days(13);
-        let sprint = client.create_sprint(&repository, &sprint_number, start_date, due_on)?;
+        let sprint = client.create_sprint(&repositories, &sprint_number, start_date, due_on)?;
 
-        eprintln!("Created '{}'", sprint.milestone.title);
+        if let Some(milestones) = sprint.milestones() {
+            for (full_name, milestone) in milestones.iter() {
+                eprintln!("Created '{}' in {}", milestone.title, full_name);
+            }
+        }
     }
     Ok(())
 }
 
+fn sprint_status(settings: &Settings) -> Result<(), Error> {
+    let github = settings.github_client()?;
+    let zenhub = settings.zenhub_report_client("show sprint status")?;
+    let store = settings.store()?;
+    let client = client(settings, &github, &zenhub, store.as_ref())?;
+
+    let repositories = client.get_repositories()?;
+    let workspace = workspace(&client, settings, repositories.primary())?;
+    let backend = sprint_backend(settings, &workspace);
+    let sprint = match select_sprint(&client, &repositories, &backend, None, false, "Sprint")? {
+        Some(sprint) => sprint,
+        None => return Ok(()),
+    };
+    let board_index = client.get_board_index(&repositories, &workspace)?;
+    let issues = client.get_sprint_issues(&repositories, &sprint, None)?;
+    let zenhub_issues = client.get_zenhub_issues(&repositories, Some(&board_index), &issues)?;
+
+    println!();
+    println!("{}", sprint.title().bold());
+    match sprint.end_date() {
+        Some(end_date) => println!(
+            "{} to {}",
+            sprint.start_date().format("%Y-%m-%d"),
+            end_date.format("%Y-%m-%d")
+        ),
+        None => println!("From {}", sprint.start_date().format("%Y-%m-%d")),
+    }
+
+    let mut points_total: u32 = 0;
+    let mut points_done: u32 = 0;
+    let mut issues_done: usize = 0;
+    for issue in issues.iter() {
+        let estimate = zenhub_issues[&issue.id]
+            .estimate
+            .as_ref()
+            .map(|estimate| estimate.value);
+        let place = if issue.state == State::Closed {
+            issues_done += 1;
+            points_done += estimate.unwrap_or(0);
+            "Closed".to_owned()
+        } else {
+            board_index
+                .get(&repositories.reference(issue)?)
+                .map(|entry| entry.pipeline_name.clone())
+                .unwrap_or_else(|| "Not on board".to_owned())
+        };
+        points_total += estimate.unwrap_or(0);
+        println!(
+            "{} [{}, {}]",
+            issue,
+            place,
+            match estimate {
+                Some(estimate) => format!("{} points", estimate),
+                None => "no estimate".yellow().to_string(),
+            }
+        );
+    }
+
+    println!();
+    println!(
+        "{} of {} issues closed, {} of {} points done.",
+        issues_done,
+        issues.len(),
+        points_done,
+        points_total
+    );
+    Ok(())
+}
+
 fn finish_sprint(settings: &Settings) -> Result<(), Error> {
     // To count as points in the sprint, the ticket must have been
     // - closed in the sprint period
     // - have points assigned
     //
     // For each ticket *closed* in the sprint time range (start to *now*)
-    // - if it has no milestone attached, prompt to attach to open milestone
+    // - if it is not in the sprint, prompt to add it
     // - if it has no points, prompt to assign estimate
     //
     // For each non-closed ticket in the sprint
     // - print status, ask if correct
 
-    let github = github::Client::new(&settings.github_url, &settings.github_token.value())?;
-    let zenhub = zenhub::Client::new(
-        settings
-            .zenhub_url
-            .as_ref()
-            .ok_or(Error::Settings {
-                description: "Zenhub url required to finish sprint.".to_owned(),
-            })?
-            .as_ref(),
-        settings
-            .zenhub_token
-            .as_ref()
-            .ok_or(Error::Settings {
-                description: "Zenhub token required to finish sprint.".to_owned(),
-            })?
-            .as_ref(),
-    )?;
-    let client = Client::new(&settings.owner, &settings.repo, &github, &zenhub)?;
+    let github = settings.github_client()?;
+    let zenhub = settings.zenhub_client("finish sprint")?;
+    let store = settings.store()?;
+    let client = client(settings, &github, &zenhub, store.as_ref())?;
 
     let select_estimate =
         Select::new("Estimate", ESTIMATES.iter()).expect("At least one estimate is required.");
 
-    // Select milestone to close
-    let milestones = client.get_milestones()?;
-    if milestones.is_empty() {
-        eprintln!("No open milestones.");
-        return Ok(());
-    }
-
-    let select_milestone =
-        Select::new("Sprint to finish", &milestones).expect("At least one milestone is required.");
-    let open_milestone = select_milestone.interact()?.to_owned();
-
-    let repository = client.get_repository()?;
-    let sprint = client.get_sprint(&repository, open_milestone)?;
+    // Select sprint to finish
+    let repositories = client.get_repositories()?;
+    let workspace = workspace(&client, settings, repositories.primary())?;
+    let backend = sprint_backend(settings, &workspace);
+    let mut sprint = match select_sprint(
+        &client,
+        &repositories,
+        &backend,
+        None,
+        false,
+        "Sprint to finish",
+    )? {
+        Some(sprint) => sprint,
+        None => return Ok(()),
+    };
+    // Read estimates from the board where possible, rather than issue by issue
+    let mut board_index = client.get_board_index(&repositories, &workspace)?;
 
     println!();
     println!("{}", "Issues for review:".bold());
-    let out_of_sprint_issues = client
-        .search_issues(
-            SearchQueryBuilder::new()
-                .no_milestone()
-                .closed_on_or_after(&sprint.start_date.start_date)
-                .not_label("Z-obsolete"),
-        )?
-        .collect::<Result<Vec<_>, _>>()?;
-    let milestone_issues = client
-        .search_issues(
-            SearchQueryBuilder::new()
-                .milestone(&sprint.milestone.title)
-                .state(&State::Closed)
-                .not_label("Z-obsolete"),
-        )?
-        .collect::<Result<Vec<_>, _>>()?;
-
-    for issue in out_of_sprint_issues.into_iter().chain(milestone_issues) {
-        // If assigned to a different milestone, ignore
-        if let Some(milestone) = &issue.milestone {
-            if milestone.id != sprint.milestone.id {
-                continue;
-            };
-        };
-
-        let zenhub_issue = client.get_zenhub_issue(&repository, &issue)?;
+    let mut out_of_sprint_query = SearchQueryBuilder::new();
+    if sprint.milestones().is_some() {
+        // Issues in a different milestone are in a different sprint, so ignore them
+        out_of_sprint_query.no_milestone();
+    }
+    out_of_sprint_query
+        .state(&State::Closed)
+        .not_label("Z-obsolete");
+    let out_of_sprint_issues = client.search_issues_windowed(
+        &out_of_sprint_query,
+        DateQualifier::Closed,
+        &DateRange::since(sprint.start_date().naive_local().date()),
+    )?;
+    let out_of_sprint_issues: Vec<_> = out_of_sprint_issues
+        .into_iter()
+        .filter(|issue| !sprint.contains(&repositories, issue))
+        .collect();
+    // Issues in a different sprint, open or closed, count towards that sprint
+    let other_sprints = client.get_other_sprints(&repositories, &sprint, &out_of_sprint_issues)?;
+    let sprint_issues = client.get_sprint_issues(&repositories, &sprint, Some(&State::Closed))?;
+
+    let review_issues: Vec<_> = out_of_sprint_issues
+        .into_iter()
+        .filter(|issue| !other_sprints.contains_key(&issue.id))
+        .chain(
+            sprint_issues
+                .into_iter()
+                .filter(|issue| !issue.labels.iter().any(|label| label.name == "Z-obsolete")),
+        )
+        .collect();
+    let zenhub_issues =
+        client.get_zenhub_issues(&repositories, Some(&board_index), &review_issues)?;
+
+    for issue in review_issues {
+        let zenhub_issue = &zenhub_issues[&issue.id];
         // If it's an epic, ignore
         if zenhub_issue.is_epic {
             continue;
@@ -352,12 +465,18 @@ fn finish_sprint(settings: &Settings) -> Result<(), Error> {
             }
         };
 
-        // If no milestone, ask to assign to open milestone and if applicable mark as not planned
+        // If not in the sprint, ask to add it
         // If answer is no, ignore
-        if issue.milestone.is_none() {
+        if !sprint.contains(&repositories, &issue) {
             show_description_once();
-            if Confirm::new("Assign to milestone?").interact()? {
-                client.assign_issue_to_milestone(&issue, Some(&sprint.milestone))?;
+            if let Some(milestones) = sprint.milestones() {
+                if milestones.for_issue(&issue).is_none() {
+                    eprintln!("No sprint milestone in this repository.");
+                    continue;
+                }
+            }
+            if Confirm::new("Add to sprint?").interact()? {
+                client.add_issue_to_sprint(&repositories, &mut sprint, &issue)?;
             } else {
                 continue;
             }
@@ -366,21 +485,29 @@ fn finish_sprint(settings: &Settings) -> Result<(), Error> {
         if zenhub_issue.estimate == None {
             show_description_once();
             let new_estimate = select_estimate.interact()?;
-            client.set_estimate(&repository, &issue, new_estimate.value)?;
+            client.set_estimate(repositories.for_issue(&issue)?, &issue, new_estimate.value)?;
+            board_index.set_estimate(&repositories.reference(&issue)?, Some(new_estimate.clone()));
         };
     }
 
     println!();
     println!("{}", "Issues open in sprint:".bold());
-    let open_milestone_issues = client
-        .search_issues(
-            SearchQueryBuilder::new()
-                .state(&State::Open)
-                .milestone(&sprint.milestone.title),
-        )?
-        .collect::<Result<Vec<_>, _>>()?;
-    for issue in open_milestone_issues.iter() {
-        println!("{}", issue);
+    let open_sprint_issues =
+        client.get_sprint_issues(&repositories, &sprint, Some(&State::Open))?;
+    let dependencies = client.get_dependencies(&repositories)?;
+    for issue in open_sprint_issues.iter() {
+        let blockers = client.get_open_blockers(&repositories, &dependencies, issue)?;
+        if blockers.is_empty() {
+            println!("{}", issue);
+        } else {
+            let blockers: Vec<String> =
+                blockers.iter().map(|blocker| blocker.reference()).collect();
+            println!(
+                "{} {}",
+                issue,
+                format!("[blocked by {}]", blockers.join(", ")).red()
+            );
+        }
     }
 
     println!();
@@ -399,12 +526,11 @@ fn finish_sprint(settings: &Settings) -> Result<(), Error> {
     println!("Calucating points summary...");
     let mut points_in_milestone: u32 = 0;
     let mut points_in_milestone_open: u32 = 0;
-    let milestone_issues = client
-        .search_issues(SearchQueryBuilder::new().milestone(&sprint.milestone.title))?
-        .collect::<Result<Vec<_>, _>>()?;
-    for issue in milestone_issues.into_iter() {
-        let zenhub_issue = client.get_zenhub_issue(&repository, &issue)?;
-        let issue_estimate = match zenhub_issue.estimate {
+    let sprint_issues = client.get_sprint_issues(&repositories, &sprint, None)?;
+    let zenhub_issues =
+        client.get_zenhub_issues(&repositories, Some(&board_index), &sprint_issues)?;
+    for issue in sprint_issues.into_iter() {
+        let issue_estimate = match &zenhub_issues[&issue.id].estimate {
             Some(estimate) => estimate.value,
             None => 0,
         };
@@ -426,7 +552,7 @@ fn finish_sprint(settings: &Settings) -> Result<(), Error> {
 We completed *{}* planned points out of *{}* ({} remaining).
 We also did {} out of sprint points.
 In total, we finished *{} points* of work."#,
-        sprint.milestone.title,
+        sprint.title(),
         sprint_points.done_in_sprint,
         sprint_points.planned,
         sprint_points.planned - sprint_points.done_in_sprint,
@@ -435,23 +561,33 @@ In total, we finished *{} points* of work."#,
     );
     eprintln!();
 
+    if sprint.milestones().is_none() {
+        if let Some(end_date) = sprint.end_date() {
+            eprintln!(
+                "Zenhub closes this sprint on {}, and moves open issues as set for the workspace.",
+                end_date.format("%Y-%m-%d")
+            );
+        }
+        return Ok(());
+    }
+
     if Confirm::new("Close sprint?").interact()? {
         // New title: Sprint <milestone_number> [<points done in sprint>/<points planned> + <points
         // done out of sprint>]
         let new_title = format!(
             "{} [{}/{} + {}]",
-            sprint.milestone.title,
+            sprint.title(),
             sprint_points.done_in_sprint,
             sprint_points.planned,
             sprint_points.done_out_of_sprint
         );
-        client.update_milestone_title(&sprint.milestone, new_title)?;
+        client.rename_sprint(&sprint, &new_title)?;
 
-        println!("Closing milestone.");
-        client.close_milestone(&sprint.milestone)?;
+        println!("Closing milestones.");
+        client.close_sprint(&sprint)?;
         println!("Removing open issues from milestone...");
-        for issue in open_milestone_issues.iter() {
-            client.assign_issue_to_milestone(&issue, None)?;
+        for issue in open_sprint_issues.iter() {
+            client.remove_issue_from_sprint(&repositories, &sprint, issue)?;
         }
     } else {
         return Ok(());
@@ -468,7 +604,20 @@ pub enum Command {
 
     #[structopt(name = "sync")]
     /// Sync a physical board to the digital board.
-    Sync,
+    Sync {
+        #[structopt(
+            long = "position",
+            default_value = "top",
+            parse(try_from_str = parse_placement)
+        )]
+        /// Where to put issues moved to a pipeline: top, bottom, an index, above:<issue>
+        /// or below:<issue>.
+        position: Placement,
+    },
+
+    #[structopt(name = "status")]
+    /// Show the issues and points in an open sprint.
+    Status,
 
     #[structopt(name = "finish")]
     /// Finish an open sprint.
@@ -478,7 +627,8 @@ pub enum Command {
 pub fn run(command: &Command, settings: &Settings) -> Result<(), Error> {
     match command {
         Command::Create => create_sprint(settings),
-        Command::Sync => sync_sprint(settings),
+        Command::Sync { ref position } => sync_sprint(position, settings),
+        Command::Status => sprint_status(settings),
         Command::Finish => finish_sprint(settings),
     }
 }
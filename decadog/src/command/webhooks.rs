@@ -0,0 +1,87 @@
+use decadog_core::store::Store;
+use decadog_core::webhook::{self, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER};
+use log::{debug, error, info, warn};
+use tiny_http::{Method, Request, Response, Server};
+
+use crate::{error::Error, Settings};
+
+/// Value of the header `name` in a request, if present.
+fn header<'r>(request: &'r Request, name: &'static str) -> Option<&'r str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+/// Handle a single delivery, returning the status code to respond with.
+fn handle(request: &mut Request, secret: &[u8], store: &mut Store) -> u16 {
+    if *request.method() != Method::Post {
+        return 405;
+    }
+    let mut body = Vec::new();
+    if let Err(error) = request.as_reader().read_to_end(&mut body) {
+        warn!("Failed to read delivery: {}", error);
+        return 400;
+    }
+
+    let delivery = header(request, DELIVERY_HEADER).unwrap_or("unknown");
+    let event = match webhook::receive(
+        secret,
+        header(request, EVENT_HEADER),
+        header(request, SIGNATURE_HEADER),
+        &body,
+    ) {
+        Ok(Some(event)) => event,
+        Ok(None) => {
+            debug!("Ignoring delivery {}", delivery);
+            return 204;
+        }
+        Err(error @ decadog_core::Error::WebhookSignature { .. }) => {
+            warn!("Rejecting delivery {}: {}", delivery, error);
+            return 401;
+        }
+        Err(error) => {
+            warn!("Rejecting delivery {}: {}", delivery, error);
+            return 400;
+        }
+    };
+
+    info!("Received delivery {}", delivery);
+    if store.apply(&event) {
+        if let Err(error) = store.save() {
+            error!("Failed to save store: {}", error);
+            return 500;
+        }
+    }
+    204
+}
+
+/// Receive webhook deliveries until interrupted, keeping the store up to date.
+pub fn serve(address: Option<&str>, settings: &Settings) -> Result<(), Error> {
+    let secret = settings
+        .webhook_secret
+        .as_ref()
+        .ok_or_else(|| Error::Settings {
+            description: "Webhook secret required to serve webhooks.".to_owned(),
+        })?;
+    let mut store = settings.store()?.ok_or_else(|| Error::Settings {
+        description: "Store path required to serve webhooks.".to_owned(),
+    })?;
+    let address = address
+        .or(settings.webhook_address.as_deref())
+        .unwrap_or("127.0.0.1:8080");
+
+    let server = Server::http(address).map_err(|error| Error::User {
+        description: format!("Failed to listen on {}: {}", address, error),
+    })?;
+    eprintln!("Listening for webhooks on {}", address);
+
+    for mut request in server.incoming_requests() {
+        let status = handle(&mut request, secret.value().as_bytes(), &mut store);
+        if let Err(error) = request.respond(Response::empty(status)) {
+            warn!("Failed to respond to delivery: {}", error);
+        }
+    }
+    Ok(())
+}
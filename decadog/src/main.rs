@@ -1,11 +1,17 @@
 #![deny(clippy::all)]
 
+use std::env;
 use std::path::PathBuf;
+use std::time::Duration;
 
+use decadog_core::cache::Cache;
+use decadog_core::retry::RetryPolicy;
 use decadog_core::secret::Secret;
+use decadog_core::store::Store;
+use decadog_core::{github, zenhub, MemberSource};
 #[cfg(feature = "config_keyring")]
 use keyring::Keyring;
-use log::{debug, error};
+use log::{debug, error, LevelFilter};
 use serde_derive::{Deserialize, Serialize};
 use structopt::StructOpt;
 
@@ -15,18 +21,86 @@ mod error;
 mod interact;
 
 use args::{Args, Command};
-use command::sprint;
+use command::{board, cache, deps, epic, release, report, sprint, webhooks};
 pub use error::Error;
 
+/// Where to find people to assign issues to, as configured.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum MemberSourceSetting {
+    Organisation,
+    Assignees,
+    Collaborators,
+    /// The team `member_team`.
+    Team,
+    /// The logins `members`.
+    List,
+}
+
+/// Where sprints are kept, as configured.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum SprintBackendSetting {
+    Milestones,
+    Zenhub,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Settings {
     version: Option<u32>,
     owner: String,
     repo: String,
+    /// Other repositories sprints span, as `owner/repo`, or `repo` for the same owner.
+    repositories: Option<Vec<String>>,
     github_url: String,
-    github_token: Secret,
+    github_token: Option<Secret>,
+    /// Github App to authenticate as, instead of using a token.
+    github_app_id: Option<u64>,
+    github_app_installation_id: Option<u64>,
+    github_app_private_key: Option<Secret>,
+    github_per_page: Option<u32>,
+    /// Maximum time to wait for a Github rate limit to reset, in seconds.
+    github_max_rate_limit_wait: Option<u64>,
     zenhub_url: Option<String>,
     zenhub_token: Option<Secret>,
+    /// Maximum time to wait for the Zenhub rate limit to reset, in seconds.
+    zenhub_max_rate_limit_wait: Option<u64>,
+    /// Name or id of the Zenhub workspace to use.
+    zenhub_workspace: Option<String>,
+    /// Url of the Zenhub GraphQL API, used for native sprints.
+    zenhub_graphql_url: Option<String>,
+    /// Zenhub API key for the GraphQL API, used for native sprints.
+    zenhub_graphql_token: Option<Secret>,
+    /// Where sprints are kept.
+    sprint_backend: Option<SprintBackendSetting>,
+    /// Maximum number of attempts for requests that fail transiently.
+    retry_attempts: Option<u32>,
+    /// Delay before retrying a failed request, in milliseconds. Doubles with each attempt.
+    retry_base_delay_ms: Option<u64>,
+    /// Maximum random delay added to each retry, in milliseconds.
+    retry_jitter_ms: Option<u64>,
+    /// Maximum number of concurrent requests made when fetching many issues.
+    concurrency: Option<usize>,
+    /// Whether to cache API responses on disk.
+    cache: Option<bool>,
+    /// Directory to cache API responses in.
+    cache_dir: Option<PathBuf>,
+    /// Maximum age of cached Zenhub responses reused by reports, in seconds.
+    zenhub_cache_ttl: Option<u64>,
+    /// Where to find people to assign issues to.
+    member_source: Option<MemberSourceSetting>,
+    /// Team slug to use with the `team` member source.
+    member_team: Option<String>,
+    /// Logins to use with the `list` member source.
+    members: Option<Vec<String>>,
+    /// Secret used to sign Github webhook deliveries.
+    webhook_secret: Option<Secret>,
+    /// Address to receive Github webhooks on.
+    webhook_address: Option<String>,
+    /// File to store issues received by webhook in. Other commands read issues from it.
+    store_path: Option<PathBuf>,
+    /// Stop reading an issue from the store once it has had no events for this long, in seconds.
+    store_max_age: Option<u64>,
 }
 
 impl Settings {
@@ -37,6 +111,10 @@ impl Settings {
         let mut settings = config::Config::default();
         settings.set_default("github_url", "https://api.github.com/")?;
         settings.set_default("zenhub_url", "https://api.zenhub.io/")?;
+        settings.set_default(
+            "zenhub_graphql_url",
+            "https://api.zenhub.com/public/graphql",
+        )?;
         if let Some(config_path) = config_path {
             settings.merge(config::File::from(config_path).required(true))?;
         } else {
@@ -48,17 +126,33 @@ impl Settings {
         {
             const KEYRING_USERNAME: &str = "decadog";
             const KEYRING_GITHUB_TOKEN: &str = "decadog_github_token";
+            const KEYRING_GITHUB_APP_PRIVATE_KEY: &str = "decadog_github_app_private_key";
             const KEYRING_ZENHUB_TOKEN: &str = "decadog_zenhub_token";
+            const KEYRING_ZENHUB_GRAPHQL_TOKEN: &str = "decadog_zenhub_graphql_token";
+            const KEYRING_WEBHOOK_SECRET: &str = "decadog_webhook_secret";
 
             debug!("Loading credentials from keyring");
             let github_keyring = Keyring::new(KEYRING_GITHUB_TOKEN, KEYRING_USERNAME);
             if let Ok(token) = github_keyring.get_password() {
                 settings.set("github_token", token)?;
             };
+            let github_app_keyring = Keyring::new(KEYRING_GITHUB_APP_PRIVATE_KEY, KEYRING_USERNAME);
+            if let Ok(private_key) = github_app_keyring.get_password() {
+                settings.set("github_app_private_key", private_key)?;
+            };
             let zenhub_keyring = Keyring::new(KEYRING_ZENHUB_TOKEN, KEYRING_USERNAME);
             if let Ok(token) = zenhub_keyring.get_password() {
                 settings.set("zenhub_token", token)?;
             };
+            let zenhub_graphql_keyring =
+                Keyring::new(KEYRING_ZENHUB_GRAPHQL_TOKEN, KEYRING_USERNAME);
+            if let Ok(token) = zenhub_graphql_keyring.get_password() {
+                settings.set("zenhub_graphql_token", token)?;
+            };
+            let webhook_keyring = Keyring::new(KEYRING_WEBHOOK_SECRET, KEYRING_USERNAME);
+            if let Ok(secret) = webhook_keyring.get_password() {
+                settings.set("webhook_secret", secret)?;
+            };
         }
 
         // Print out our settings (as a HashMap)
@@ -66,21 +160,196 @@ impl Settings {
         debug!("Loaded settings: {:?}", settings);
         Ok(settings)
     }
+
+    /// Directory to cache API responses in, if any.
+    ///
+    /// Defaults to `decadog` in the user cache directory.
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        if let Some(cache_dir) = &self.cache_dir {
+            return Some(cache_dir.clone());
+        }
+        env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .map(|cache_home| cache_home.join("decadog"))
+    }
+
+    /// Cache for API responses, with entries for the `api` given, if enabled.
+    fn api_cache(&self, api: &str) -> Option<Cache> {
+        if self.cache == Some(false) {
+            return None;
+        }
+        self.cache_dir()
+            .map(|cache_dir| Cache::new(cache_dir.join(api)))
+    }
+
+    /// Local store of issues received by webhook, if configured.
+    pub fn store(&self) -> Result<Option<Store>, Error> {
+        match &self.store_path {
+            Some(store_path) => Ok(Some(
+                Store::open(store_path)?
+                    .with_max_age(Duration::from_secs(self.store_max_age.unwrap_or(3600))),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Where to find people to assign issues to.
+    ///
+    /// If no source is given, `members` or `member_team` are used if set, otherwise
+    /// organisation members.
+    pub fn member_source(&self) -> Result<MemberSource, Error> {
+        let team = || {
+            self.member_team.clone().ok_or_else(|| Error::Settings {
+                description: "member_team required for team member source.".to_owned(),
+            })
+        };
+        let logins = || {
+            self.members.clone().ok_or_else(|| Error::Settings {
+                description: "members required for list member source.".to_owned(),
+            })
+        };
+        match self.member_source {
+            Some(MemberSourceSetting::Organisation) => Ok(MemberSource::Organisation),
+            Some(MemberSourceSetting::Assignees) => Ok(MemberSource::Assignees),
+            Some(MemberSourceSetting::Collaborators) => Ok(MemberSource::Collaborators),
+            Some(MemberSourceSetting::Team) => Ok(MemberSource::Team(team()?)),
+            Some(MemberSourceSetting::List) => Ok(MemberSource::Logins(logins()?)),
+            None if self.members.is_some() => Ok(MemberSource::Logins(logins()?)),
+            None if self.member_team.is_some() => Ok(MemberSource::Team(team()?)),
+            None => Ok(MemberSource::Organisation),
+        }
+    }
+
+    /// Whether sprints are Zenhub native sprints, rather than milestones.
+    pub fn native_sprints(&self) -> bool {
+        self.sprint_backend == Some(SprintBackendSetting::Zenhub)
+    }
+
+    /// Policy for retrying requests that fail transiently.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        let default = RetryPolicy::default();
+        RetryPolicy {
+            attempts: self.retry_attempts.unwrap_or(default.attempts),
+            base_delay: self
+                .retry_base_delay_ms
+                .map(Duration::from_millis)
+                .unwrap_or(default.base_delay),
+            jitter: self
+                .retry_jitter_ms
+                .map(Duration::from_millis)
+                .unwrap_or(default.jitter),
+        }
+    }
+
+    /// Create a Github client from these settings.
+    ///
+    /// Authenticates as a Github App installation if configured, otherwise with a token.
+    pub fn github_client(&self) -> Result<github::Client, Error> {
+        let client = match (
+            self.github_app_id,
+            self.github_app_installation_id,
+            &self.github_app_private_key,
+            &self.github_token,
+        ) {
+            (Some(app_id), Some(installation_id), Some(private_key), _) => {
+                let credentials =
+                    github::auth::AppCredentials::new(app_id, installation_id, private_key)?;
+                github::Client::new_app(&self.github_url, credentials)?
+            }
+            (_, _, _, Some(token)) => github::Client::new(&self.github_url, token.value())?,
+            _ => {
+                return Err(Error::Settings {
+                    description: "Github token or Github App credentials required.".to_owned(),
+                })
+            }
+        };
+        let mut client = client.with_retry_policy(self.retry_policy());
+        if let Some(per_page) = self.github_per_page {
+            client = client.with_per_page(per_page)?;
+        }
+        if let Some(max_wait) = self.github_max_rate_limit_wait {
+            client = client.with_max_rate_limit_wait(Duration::from_secs(max_wait));
+        }
+        if let Some(cache) = self.api_cache("github") {
+            client = client.with_cache(cache);
+        }
+        Ok(client)
+    }
+
+    /// Create a Zenhub client from these settings. `action` describes why it is required.
+    ///
+    /// Cached responses are not reused, as changes are worked out from them.
+    pub fn zenhub_client(&self, action: &str) -> Result<zenhub::Client, Error> {
+        self.build_zenhub_client(action, false)
+    }
+
+    /// Create a Zenhub client for a command that only reads, reusing cached responses.
+    pub fn zenhub_report_client(&self, action: &str) -> Result<zenhub::Client, Error> {
+        self.build_zenhub_client(action, true)
+    }
+
+    fn build_zenhub_client(&self, action: &str, cached: bool) -> Result<zenhub::Client, Error> {
+        let url = self.zenhub_url.as_ref().ok_or_else(|| Error::Settings {
+            description: format!("Zenhub url required to {}.", action),
+        })?;
+        let token = self.zenhub_token.as_ref().ok_or_else(|| Error::Settings {
+            description: format!("Zenhub token required to {}.", action),
+        })?;
+        let mut client =
+            zenhub::Client::new(url, token.value())?.with_retry_policy(self.retry_policy());
+        if let Some(max_wait) = self.zenhub_max_rate_limit_wait {
+            client = client.with_max_rate_limit_wait(Duration::from_secs(max_wait));
+        }
+        if let Some(cache) = self.api_cache("zenhub") {
+            // Without reusing responses, changes still refresh and clear the cache for reports
+            let ttl = if cached {
+                Duration::from_secs(self.zenhub_cache_ttl.unwrap_or(300))
+            } else {
+                Duration::from_secs(0)
+            };
+            client = client.with_cache(cache, ttl);
+        }
+        if let (Some(url), Some(token)) = (&self.zenhub_graphql_url, &self.zenhub_graphql_token) {
+            client = client.with_graphql(url, token.value())?;
+        }
+        Ok(client)
+    }
 }
 
 fn run(args: Args) -> Result<(), Error> {
-    let settings = Settings::load(args.config)?;
+    let mut settings = Settings::load(args.config)?;
+    if args.no_cache {
+        settings.cache = Some(false);
+    }
+    if args.workspace.is_some() {
+        settings.zenhub_workspace = args.workspace;
+    }
 
     match args.command {
         Command::Sprint { ref command } => sprint::run(command, &settings),
+        Command::Board { ref command } => board::run(command, &settings),
+        Command::Epic { ref command } => epic::run(command, &settings),
+        Command::Release { ref command } => release::run(command, &settings),
+        Command::Report { ref command } => report::run(command, &settings),
+        Command::Deps { ref milestone } => deps::run(milestone.as_deref(), &settings),
+        Command::Cache { ref command } => cache::run(command, &settings),
+        Command::ServeWebhooks { ref address } => webhooks::serve(address.as_deref(), &settings),
     }
 }
 
 pub fn main() {
-    env_logger::init();
+    let args = Args::from_args();
+
+    let mut logger = env_logger::Builder::from_default_env();
+    if args.verbose {
+        logger
+            .filter_module("decadog", LevelFilter::Info)
+            .filter_module("decadog_core", LevelFilter::Info);
+    }
+    logger.init();
     debug!("Initialised logger.");
 
-    let args = Args::from_args();
     if let Err(error) = run(args) {
         error!("{}", error);
     }
@@ -0,0 +1,106 @@
+/// Base urls for APIs, which may be served under a path prefix.
+use std::fmt;
+
+use reqwest::Url;
+
+use crate::error::Error;
+
+/// The root of an API, against which endpoint paths are resolved.
+///
+/// Any path prefix is preserved, so that APIs not served from the root of their host
+/// (such as Github Enterprise Server, under `/api/v3/`) work as expected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaseUrl(Url);
+
+impl BaseUrl {
+    /// Parse a base url for the API named `api`.
+    pub fn parse(url: &str, api: &str) -> Result<Self, Error> {
+        let mut base_url = Url::parse(url).map_err(|_| Error::Config {
+            description: format!("Invalid {} base url {}", api, url),
+        })?;
+        if base_url.cannot_be_a_base() {
+            return Err(Error::Config {
+                description: format!("Invalid {} base url {}", api, url),
+            });
+        }
+
+        // Without a trailing slash, the last segment of the prefix would be replaced on join
+        if !base_url.path().ends_with('/') {
+            let path = format!("{}/", base_url.path());
+            base_url.set_path(&path);
+        }
+        base_url.set_query(None);
+        base_url.set_fragment(None);
+        Ok(Self(base_url))
+    }
+
+    /// Resolve an endpoint path, such as `repos/owner/repo`, under this base url.
+    ///
+    /// Leading slashes are ignored, so the path is always relative to the base url.
+    pub fn join(&self, path: &str) -> Result<Url, Error> {
+        Ok(self.0.join(path.trim_start_matches('/'))?)
+    }
+
+    /// The base url itself.
+    pub fn as_url(&self) -> &Url {
+        &self.0
+    }
+}
+
+impl fmt::Display for BaseUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_join_root() {
+        let base_url = BaseUrl::parse("https://api.github.com", "Github").unwrap();
+        assert_eq!(
+            base_url.join("repos/owner/repo").unwrap().as_str(),
+            "https://api.github.com/repos/owner/repo"
+        );
+        assert_eq!(
+            base_url.join("/repos/owner/repo").unwrap().as_str(),
+            "https://api.github.com/repos/owner/repo"
+        );
+    }
+
+    #[test]
+    fn test_join_preserves_prefix() {
+        for url in &[
+            "https://ghe.example.com/api/v3",
+            "https://ghe.example.com/api/v3/",
+            "https://ghe.example.com/api/v3/?ignored=true",
+        ] {
+            let base_url = BaseUrl::parse(url, "Github").unwrap();
+            assert_eq!(base_url.to_string(), "https://ghe.example.com/api/v3/");
+            assert_eq!(
+                base_url.join("/repos/owner/repo").unwrap().as_str(),
+                "https://ghe.example.com/api/v3/repos/owner/repo"
+            );
+            assert_eq!(
+                base_url.join("search/issues").unwrap().as_str(),
+                "https://ghe.example.com/api/v3/search/issues"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        for url in &["not a url", "mailto:someone@example.com"] {
+            match BaseUrl::parse(url, "Zenhub").unwrap_err() {
+                Error::Config { description } => {
+                    assert_eq!(description, format!("Invalid Zenhub base url {}", url))
+                }
+                _ => panic!("Unexpected error"),
+            }
+        }
+    }
+}
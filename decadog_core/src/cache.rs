@@ -0,0 +1,219 @@
+/// On-disk cache of API responses.
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::Hasher;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use reqwest::blocking::Response;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{ResponseBuilderExt, StatusCode};
+use serde_derive::{Deserialize, Serialize};
+use url::Url;
+
+use crate::error::Error;
+
+/// A cached response from an API.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct CacheEntry {
+    pub stored_at: DateTime<Utc>,
+    /// Url of the original response, which paginated requests rely on.
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl CacheEntry {
+    /// Read a successful response in full, returning the entry for it.
+    pub fn from_response(response: Response) -> Result<Self, Error> {
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((name.as_str().to_owned(), value.to_str().ok()?.to_owned()))
+            })
+            .collect();
+        Ok(Self {
+            stored_at: Utc::now(),
+            url: response.url().to_string(),
+            headers,
+            body: response.text()?,
+        })
+    }
+
+    /// Get the value of a stored header.
+    pub fn header(&self, name: &HeaderName) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name.as_str()))
+            .map(|(_, value)| value.as_ref())
+    }
+
+    /// Whether this entry was stored less than `ttl` ago.
+    pub fn is_fresh(&self, ttl: Duration) -> bool {
+        match chrono::Duration::from_std(ttl) {
+            Ok(ttl) => self.stored_at + ttl > Utc::now(),
+            Err(_) => true,
+        }
+    }
+
+    /// Recreate the original successful response.
+    pub fn to_response(&self) -> Response {
+        let mut headers = HeaderMap::new();
+        for (name, value) in self.headers.iter() {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        let mut builder = http::Response::builder().status(StatusCode::OK);
+        if let Ok(url) = Url::parse(&self.url) {
+            builder = builder.url(url);
+        }
+        if let Some(builder_headers) = builder.headers_mut() {
+            *builder_headers = headers;
+        }
+        builder
+            .body(self.body.clone())
+            .expect("Response parts were already valid")
+            .into()
+    }
+}
+
+/// An on-disk cache of API responses, keyed by request.
+///
+/// Failing to read or write the cache is never fatal; the request is made as normal.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    directory: PathBuf,
+}
+
+impl Cache {
+    /// Create a cache storing entries in `directory`, which is created as required.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(key.as_bytes());
+        self.directory
+            .join(format!("{:016x}.json", hasher.finish()))
+    }
+
+    /// Get the entry stored for `key`, if any.
+    pub fn get(&self, key: &str) -> Option<CacheEntry> {
+        let path = self.path(key);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                if error.kind() != ErrorKind::NotFound {
+                    warn!("Failed to read cache entry {}: {}", path.display(), error);
+                }
+                return None;
+            }
+        };
+        match serde_json::from_str(&contents) {
+            Ok(entry) => {
+                debug!("Cache hit for {}", key);
+                Some(entry)
+            }
+            Err(error) => {
+                warn!("Ignoring invalid cache entry {}: {}", path.display(), error);
+                None
+            }
+        }
+    }
+
+    /// Store an entry for `key`, replacing any existing entry.
+    pub fn put(&self, key: &str, entry: &CacheEntry) {
+        let path = self.path(key);
+        let result = fs::create_dir_all(&self.directory)
+            .and_then(|_| fs::write(&path, serde_json::to_string(entry)?));
+        if let Err(error) = result {
+            warn!("Failed to write cache entry {}: {}", path.display(), error);
+        }
+    }
+
+    /// Remove all entries from the cache.
+    pub fn clear(&self) -> Result<(), Error> {
+        match fs::remove_dir_all(&self.directory) {
+            Err(error) if error.kind() != ErrorKind::NotFound => Err(Error::Io { source: error }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use pretty_assertions::assert_eq;
+    use reqwest::header::ETAG;
+
+    use super::*;
+
+    /// An empty cache in a temporary directory, unique to `name`.
+    pub fn temporary_cache(name: &str) -> Cache {
+        let cache = Cache::new(std::env::temp_dir().join(format!(
+            "decadog-test-{}-{}",
+            name,
+            std::process::id()
+        )));
+        cache.clear().unwrap();
+        cache
+    }
+
+    #[test]
+    fn test_put_get_clear() {
+        let cache = temporary_cache("put-get-clear");
+        assert_eq!(cache.get("key"), None);
+
+        let entry = CacheEntry {
+            stored_at: Utc::now(),
+            url: "https://api.github.com/repos/tommilligan/decadog".to_owned(),
+            headers: vec![("etag".to_owned(), r#""abc""#.to_owned())],
+            body: "{}".to_owned(),
+        };
+        cache.put("key", &entry);
+        assert_eq!(cache.get("key"), Some(entry.clone()));
+        assert_eq!(cache.get("other key"), None);
+        assert_eq!(entry.header(&ETAG), Some(r#""abc""#));
+
+        cache.clear().unwrap();
+        assert_eq!(cache.get("key"), None);
+    }
+
+    #[test]
+    fn test_entry_freshness() {
+        let mut entry = CacheEntry {
+            stored_at: Utc::now(),
+            url: "https://api.github.com/repos/tommilligan/decadog".to_owned(),
+            headers: vec![],
+            body: "{}".to_owned(),
+        };
+        assert!(entry.is_fresh(Duration::from_secs(60)));
+        entry.stored_at = entry.stored_at - chrono::Duration::seconds(120);
+        assert!(!entry.is_fresh(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_entry_to_response() {
+        let entry = CacheEntry {
+            stored_at: Utc::now(),
+            url: "https://api.github.com/repos/tommilligan/decadog/issues?page=2".to_owned(),
+            headers: vec![("link".to_owned(), "<https://example.com>".to_owned())],
+            body: "[]".to_owned(),
+        };
+        let response = entry.to_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.url().as_str(), entry.url);
+        assert_eq!(response.headers()["link"], "<https://example.com>");
+        assert_eq!(response.text().unwrap(), "[]");
+    }
+}
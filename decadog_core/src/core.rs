@@ -1,5 +1,13 @@
-use crate::github::{Issue, Milestone, OrganisationMember};
-use crate::zenhub::{Pipeline, StartDate};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::error::Error;
+use crate::github::{Issue, Milestone, OrganisationMember, Repository};
+use crate::zenhub::{
+    Board, Dependency, Estimate, IssueReference, NativeSprint, Pipeline, PipelineIssue, StartDate,
+};
 
 /// Represents objects in the Github ontology that can be assigned to one another.
 ///
@@ -8,11 +16,433 @@ pub trait AssignedTo<T> {
     fn assigned_to(&self, assignable: &T) -> bool;
 }
 
-/// A sprint.
+/// Where to find the people who can be assigned to issues.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemberSource {
+    /// Members of the organisation owning the repository.
+    ///
+    /// If the owner is a user rather than an organisation, repository assignees are used.
+    Organisation,
+    /// Users that can be assigned to issues in the repository.
+    Assignees,
+    /// Collaborators on the repository.
+    Collaborators,
+    /// Members of the team with this slug, in the organisation owning the repository.
+    Team(String),
+    /// Users with these logins.
+    Logins(Vec<String>),
+}
+
+/// Where to place an issue when moving it to a pipeline.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Placement {
+    #[default]
+    Top,
+    Bottom,
+    /// Index from the top of the pipeline, starting at 0.
+    Index(u32),
+    /// Directly above another issue, by reference such as `12` or `repo#12`.
+    Above(String),
+    /// Directly below another issue, by reference such as `12` or `repo#12`.
+    Below(String),
+}
+
+/// How to order the issues in a pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PipelineOrder {
+    /// Largest estimate first, then issues without an estimate.
+    Estimate,
+    /// Issues with a label earlier in the list first, then issues with none of them.
+    ///
+    /// Labels are compared case insensitively.
+    Labels(Vec<String>),
+}
+
+/// The moves needed to reorder issues from `current` to `desired`, as the issue to
+/// move and the index to move it to, applied in order.
+///
+/// Issues only in `current` are left in place, after those in `desired`.
+pub fn reorder_moves(
+    current: &[IssueReference],
+    desired: &[IssueReference],
+) -> Vec<(IssueReference, u32)> {
+    let mut current = current.to_vec();
+    let mut moves = Vec::new();
+    for (index, reference) in desired.iter().enumerate() {
+        match current.iter().position(|issue| issue == reference) {
+            Some(current_index) if current_index == index => (),
+            Some(current_index) => {
+                let issue = current.remove(current_index);
+                current.insert(index, issue);
+                moves.push((reference.clone(), index as u32));
+            }
+            None => (),
+        }
+    }
+    moves
+}
+
+/// The repositories a client works with, keyed by full name (`owner/repo`).
+///
+/// Full names are compared case insensitively, as on Github.
+#[derive(Debug, Clone)]
+pub struct Repositories {
+    repositories: Vec<(String, Repository)>,
+}
+
+impl Repositories {
+    /// Create from repositories and their full names. The first is the primary repository.
+    pub fn new(repositories: Vec<(String, Repository)>) -> Result<Self, Error> {
+        if repositories.is_empty() {
+            return Err(Error::Config {
+                description: "At least one repository is required.".to_owned(),
+            });
+        }
+        Ok(Self {
+            repositories: repositories
+                .into_iter()
+                .map(|(full_name, repository)| (full_name.to_lowercase(), repository))
+                .collect(),
+        })
+    }
+
+    /// The primary repository.
+    pub fn primary(&self) -> &Repository {
+        &self.repositories[0].1
+    }
+
+    /// Get a repository by full name.
+    pub fn get(&self, full_name: &str) -> Option<&Repository> {
+        let full_name = full_name.to_lowercase();
+        self.repositories
+            .iter()
+            .find(|(name, _)| *name == full_name)
+            .map(|(_, repository)| repository)
+    }
+
+    /// Get the repository an issue belongs to.
+    pub fn for_issue(&self, issue: &Issue) -> Result<&Repository, Error> {
+        issue
+            .full_name()
+            .and_then(|full_name| self.get(&full_name))
+            .ok_or_else(|| Error::Unknown {
+                description: format!("Issue {} is not in a known repository.", issue),
+            })
+    }
+
+    /// All repositories, with their full names, starting with the primary.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Repository)> {
+        self.repositories
+            .iter()
+            .map(|(full_name, repository)| (full_name.as_ref(), repository))
+    }
+
+    /// Get a Zenhub reference to an issue.
+    pub fn reference(&self, issue: &Issue) -> Result<IssueReference, Error> {
+        Ok(IssueReference {
+            repo_id: self.for_issue(issue)?.id,
+            issue_number: issue.number,
+        })
+    }
+
+    /// Get a repository, with its full name, by id.
+    pub fn get_by_id(&self, repository_id: u64) -> Option<(&str, &Repository)> {
+        self.iter()
+            .find(|(_, repository)| repository.id == repository_id)
+    }
+}
+
+/// Blocking relationships between issues, across repositories.
+#[derive(Debug, Clone, Default)]
+pub struct Dependencies {
+    dependencies: Vec<Dependency>,
+}
+
+impl Dependencies {
+    /// Create from dependencies, ignoring duplicates.
+    pub fn new<I: IntoIterator<Item = Dependency>>(dependencies: I) -> Self {
+        let mut unique: Vec<Dependency> = Vec::new();
+        for dependency in dependencies {
+            if !unique.contains(&dependency) {
+                unique.push(dependency);
+            }
+        }
+        Self {
+            dependencies: unique,
+        }
+    }
+
+    /// Issues that block `issue`.
+    pub fn blocking<'a>(
+        &'a self,
+        issue: &'a IssueReference,
+    ) -> impl Iterator<Item = &'a IssueReference> {
+        self.dependencies
+            .iter()
+            .filter(move |dependency| dependency.blocked == *issue)
+            .map(|dependency| &dependency.blocking)
+    }
+
+    /// Issues that `issue` blocks.
+    pub fn blocked<'a>(
+        &'a self,
+        issue: &'a IssueReference,
+    ) -> impl Iterator<Item = &'a IssueReference> {
+        self.dependencies
+            .iter()
+            .filter(move |dependency| dependency.blocking == *issue)
+            .map(|dependency| &dependency.blocked)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Dependency> {
+        self.dependencies.iter()
+    }
+}
+
+/// Progress of a Zenhub epic.
 #[derive(Debug, Clone)]
-pub struct Sprint {
-    pub milestone: Milestone,
-    pub start_date: StartDate,
+pub struct EpicStatus {
+    pub epic: Issue,
+    /// Points of all issues in the epic.
+    pub total_points: u32,
+    /// Points of closed issues in the epic.
+    pub done_points: u32,
+    /// Open issues in the epic that are in the sprint.
+    pub in_sprint: Vec<Issue>,
+    /// Issues in the epic from repositories that are not worked with.
+    pub unknown_issues: usize,
+}
+
+impl EpicStatus {
+    /// Points of open issues in the epic.
+    pub fn remaining_points(&self) -> u32 {
+        self.total_points - self.done_points
+    }
+}
+
+/// Milestones sharing a title across repositories, keyed by full name (`owner/repo`).
+#[derive(Debug, Clone, Default)]
+pub struct MilestoneSet {
+    milestones: HashMap<String, Milestone>,
+}
+
+impl MilestoneSet {
+    pub fn insert(&mut self, full_name: &str, milestone: Milestone) {
+        self.milestones.insert(full_name.to_lowercase(), milestone);
+    }
+
+    /// Get the milestone in a repository, by full name.
+    pub fn get(&self, full_name: &str) -> Option<&Milestone> {
+        self.milestones.get(&full_name.to_lowercase())
+    }
+
+    /// Get the milestone in the repository an issue belongs to.
+    pub fn for_issue(&self, issue: &Issue) -> Option<&Milestone> {
+        self.get(&issue.full_name()?)
+    }
+
+    /// Whether an issue is in the milestone for its repository.
+    pub fn contains(&self, issue: &Issue) -> bool {
+        match (&issue.milestone, self.for_issue(issue)) {
+            (Some(milestone), Some(set_milestone)) => milestone.id == set_milestone.id,
+            _ => false,
+        }
+    }
+
+    /// All milestones, with the full names of their repositories.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Milestone)> {
+        self.milestones
+            .iter()
+            .map(|(full_name, milestone)| (full_name.as_ref(), milestone))
+    }
+}
+
+/// Where an issue is on a Zenhub board, and its Zenhub metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoardEntry {
+    pub pipeline_id: String,
+    pub pipeline_name: String,
+    /// Index in the pipeline, from the top.
+    pub position: usize,
+    pub estimate: Option<Estimate>,
+    pub is_epic: bool,
+}
+
+/// The issues on a Zenhub board, by repository and issue number.
+///
+/// Built from a single board fetch, to avoid fetching issues one at a time.
+#[derive(Debug, Clone, Default)]
+pub struct BoardIndex {
+    entries: HashMap<IssueReference, BoardEntry>,
+}
+
+impl BoardIndex {
+    /// Index `board`. Issues without a known repository are in `default_repo_id`.
+    pub fn new(board: &Board, default_repo_id: u64) -> Self {
+        let mut entries = HashMap::new();
+        for pipeline in board.pipelines.iter() {
+            for (position, issue) in pipeline.issues.iter().enumerate() {
+                entries.insert(
+                    issue.reference(default_repo_id),
+                    BoardEntry {
+                        pipeline_id: pipeline.id.clone(),
+                        pipeline_name: pipeline.name.clone(),
+                        position,
+                        estimate: issue.estimate.clone(),
+                        is_epic: issue.is_epic,
+                    },
+                );
+            }
+        }
+        Self { entries }
+    }
+
+    pub fn get(&self, reference: &IssueReference) -> Option<&BoardEntry> {
+        self.entries.get(reference)
+    }
+
+    /// Record a new estimate for an issue, if it is on the board.
+    pub fn set_estimate(&mut self, reference: &IssueReference, estimate: Option<Estimate>) {
+        if let Some(entry) = self.entries.get_mut(reference) {
+            entry.estimate = estimate;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Where sprints are kept.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SprintBackend {
+    /// A Github milestone with the same title in each repository, with a Zenhub start date.
+    Milestones,
+    /// Zenhub native sprints in the workspace with this id.
+    Zenhub(String),
+}
+
+/// A sprint, which may span several repositories.
+#[derive(Debug, Clone)]
+pub enum Sprint {
+    /// A sprint kept as Github milestones.
+    Milestone {
+        /// The sprint milestone in the primary repository.
+        milestone: Milestone,
+        start_date: StartDate,
+        /// The sprint milestone in every repository it spans, including the primary.
+        milestones: MilestoneSet,
+    },
+    /// A Zenhub native sprint.
+    Zenhub {
+        sprint: NativeSprint,
+        /// The issues in the sprint when it was fetched.
+        issues: HashSet<IssueReference>,
+    },
+}
+
+impl Sprint {
+    pub fn title(&self) -> &str {
+        match self {
+            Self::Milestone { milestone, .. } => &milestone.title,
+            Self::Zenhub { sprint, .. } => sprint.title(),
+        }
+    }
+
+    pub fn start_date(&self) -> DateTime<FixedOffset> {
+        match self {
+            Self::Milestone { start_date, .. } => start_date.start_date,
+            Self::Zenhub { sprint, .. } => sprint.start_at,
+        }
+    }
+
+    /// When the sprint is due to end, if known.
+    pub fn end_date(&self) -> Option<DateTime<FixedOffset>> {
+        match self {
+            Self::Milestone { milestone, .. } => milestone.due_on,
+            Self::Zenhub { sprint, .. } => Some(sprint.end_at),
+        }
+    }
+
+    /// The sprint milestones, if the sprint is kept as milestones.
+    pub fn milestones(&self) -> Option<&MilestoneSet> {
+        match self {
+            Self::Milestone { milestones, .. } => Some(milestones),
+            Self::Zenhub { .. } => None,
+        }
+    }
+
+    /// Record that the issue `reference` was added to the sprint.
+    ///
+    /// Milestone sprints are kept on the issues themselves, so there is nothing to record.
+    pub fn insert(&mut self, reference: IssueReference) {
+        if let Self::Zenhub { issues, .. } = self {
+            issues.insert(reference);
+        }
+    }
+
+    /// Whether `issue` is in the sprint.
+    ///
+    /// Issues in a Zenhub sprint are those in it when the sprint was fetched, and
+    /// any inserted since.
+    pub fn contains(&self, repositories: &Repositories, issue: &Issue) -> bool {
+        match self {
+            Self::Milestone { milestones, .. } => milestones.contains(issue),
+            Self::Zenhub { issues, .. } => repositories
+                .reference(issue)
+                .map(|reference| issues.contains(&reference))
+                .unwrap_or(false),
+        }
+    }
+}
+
+impl fmt::Display for Sprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.title())
+    }
+}
+
+impl Pipeline {
+    /// Whether this pipeline contains an issue from `repository`.
+    ///
+    /// Issues without a known repository, such as those on a single repository board,
+    /// are matched by number alone.
+    pub fn contains(&self, repository: &Repository, issue: &Issue) -> bool {
+        self.issues.iter().any(|pipeline_issue| {
+            pipeline_issue.issue_number == issue.number
+                && match pipeline_issue.repo_id {
+                    Some(repo_id) => repo_id == repository.id,
+                    None => true,
+                }
+        })
+    }
+
+    /// Index of an issue in this pipeline.
+    ///
+    /// Issues without a known repository are matched by number alone.
+    pub fn index_of(&self, reference: &IssueReference) -> Option<usize> {
+        self.issues.iter().position(|pipeline_issue| {
+            pipeline_issue.issue_number == reference.issue_number
+                && match pipeline_issue.repo_id {
+                    Some(repo_id) => repo_id == reference.repo_id,
+                    None => true,
+                }
+        })
+    }
+}
+
+impl PipelineIssue {
+    /// Reference to this issue, which is in `default_repo_id` if its repository is unknown.
+    pub fn reference(&self, default_repo_id: u64) -> IssueReference {
+        IssueReference {
+            repo_id: self.repo_id.unwrap_or(default_repo_id),
+            issue_number: self.issue_number,
+        }
+    }
 }
 
 impl AssignedTo<Milestone> for Issue {
@@ -26,15 +456,6 @@ impl AssignedTo<Milestone> for Issue {
     }
 }
 
-impl AssignedTo<Pipeline> for Issue {
-    fn assigned_to(&self, assignable: &Pipeline) -> bool {
-        assignable
-            .issues
-            .iter()
-            .any(|issue| issue.issue_number == self.number)
-    }
-}
-
 impl AssignedTo<Issue> for OrganisationMember {
     fn assigned_to(&self, assignable: &Issue) -> bool {
         assignable
@@ -50,6 +471,7 @@ mod tests {
     use lazy_static::lazy_static;
 
     use super::*;
+    use crate::zenhub::PipelineIssue;
 
     lazy_static! {
         static ref DEFAULT_DATETIME_FIXED: DateTime<FixedOffset> =
@@ -70,6 +492,7 @@ mod tests {
                 updated_at: *DEFAULT_DATETIME_FIXED,
                 closed_at: Some(*DEFAULT_DATETIME_FIXED),
                 html_url: Default::default(),
+                repository_url: "https://api.github.com/repos/tommilligan/decadog".to_owned(),
             }
         }
     }
@@ -80,8 +503,14 @@ mod tests {
                 id: Default::default(),
                 number: Default::default(),
                 title: Default::default(),
+                description: Default::default(),
                 state: Default::default(),
-                due_on: *DEFAULT_DATETIME_FIXED,
+                open_issues: Default::default(),
+                closed_issues: Default::default(),
+                created_at: *DEFAULT_DATETIME_FIXED,
+                due_on: Some(*DEFAULT_DATETIME_FIXED),
+                closed_at: Default::default(),
+                html_url: Default::default(),
             }
         }
     }
@@ -105,4 +534,163 @@ mod tests {
         assert!(!member.assigned_to(&issue));
         assert!(member.assigned_to(&issue_with_assignee));
     }
+
+    #[test]
+    fn issue_in_repositories() {
+        let repositories = Repositories::new(vec![
+            (
+                "tommilligan/decadog".to_owned(),
+                Repository {
+                    id: 1,
+                    name: "decadog".to_owned(),
+                },
+            ),
+            (
+                "TomMilligan/Frontend".to_owned(),
+                Repository {
+                    id: 2,
+                    name: "frontend".to_owned(),
+                },
+            ),
+        ])
+        .unwrap();
+        let mut issue = Issue::default();
+        assert_eq!(repositories.for_issue(&issue).unwrap().id, 1);
+        issue.repository_url = "https://api.github.com/repos/tommilligan/frontend".to_owned();
+        assert_eq!(repositories.for_issue(&issue).unwrap().id, 2);
+        issue.repository_url = "https://api.github.com/repos/tommilligan/backend".to_owned();
+        assert!(repositories.for_issue(&issue).is_err());
+        assert_eq!(repositories.primary().id, 1);
+    }
+
+    #[test]
+    fn pipeline_contains_issue() {
+        let repository = Repository {
+            id: 1,
+            name: "decadog".to_owned(),
+        };
+        let other_repository = Repository {
+            id: 2,
+            name: "frontend".to_owned(),
+        };
+        let issue = Issue {
+            number: 3,
+            ..Default::default()
+        };
+        let mut pipeline = Pipeline::default();
+        pipeline.issues.push(PipelineIssue {
+            issue_number: 3,
+            estimate: None,
+            is_epic: false,
+            repo_id: None,
+        });
+        assert!(pipeline.contains(&repository, &issue));
+        assert!(pipeline.contains(&other_repository, &issue));
+
+        pipeline.issues[0].repo_id = Some(1);
+        assert!(pipeline.contains(&repository, &issue));
+        assert!(!pipeline.contains(&other_repository, &issue));
+    }
+
+    #[test]
+    fn dependencies_between_issues() {
+        let reference = |repo_id, issue_number| IssueReference {
+            repo_id,
+            issue_number,
+        };
+        let dependency = |blocking, blocked| Dependency { blocking, blocked };
+        let dependencies = Dependencies::new(vec![
+            dependency(reference(1, 1), reference(1, 2)),
+            dependency(reference(2, 1), reference(1, 2)),
+            dependency(reference(1, 2), reference(1, 3)),
+            dependency(reference(1, 1), reference(1, 2)),
+        ]);
+        assert_eq!(dependencies.iter().count(), 3);
+        assert_eq!(
+            dependencies.blocking(&reference(1, 2)).collect::<Vec<_>>(),
+            vec![&reference(1, 1), &reference(2, 1)]
+        );
+        assert_eq!(
+            dependencies.blocked(&reference(1, 2)).collect::<Vec<_>>(),
+            vec![&reference(1, 3)]
+        );
+        assert_eq!(dependencies.blocking(&reference(1, 1)).count(), 0);
+    }
+
+    #[test]
+    fn board_index() {
+        let pipeline_issue = |issue_number, estimate: Option<u32>, repo_id| PipelineIssue {
+            issue_number,
+            estimate: estimate.as_ref().map(Estimate::from),
+            is_epic: issue_number == 3,
+            repo_id,
+        };
+        let board = Board {
+            pipelines: vec![
+                Pipeline {
+                    id: "p1".to_owned(),
+                    name: "Backlog".to_owned(),
+                    issues: vec![pipeline_issue(1, Some(2), None)],
+                },
+                Pipeline {
+                    id: "p2".to_owned(),
+                    name: "Done".to_owned(),
+                    issues: vec![
+                        pipeline_issue(2, None, Some(2)),
+                        pipeline_issue(3, None, None),
+                    ],
+                },
+            ],
+        };
+        let reference = |repo_id, issue_number| IssueReference {
+            repo_id,
+            issue_number,
+        };
+
+        let mut index = BoardIndex::new(&board, 1);
+        assert_eq!(index.len(), 3);
+        assert_eq!(
+            index.get(&reference(1, 1)),
+            Some(&BoardEntry {
+                pipeline_id: "p1".to_owned(),
+                pipeline_name: "Backlog".to_owned(),
+                position: 0,
+                estimate: Some(Estimate { value: 2 }),
+                is_epic: false,
+            })
+        );
+        assert_eq!(index.get(&reference(2, 2)).unwrap().position, 0);
+        assert!(index.get(&reference(1, 3)).unwrap().is_epic);
+        assert_eq!(index.get(&reference(1, 2)), None);
+
+        index.set_estimate(&reference(2, 2), Some(Estimate { value: 5 }));
+        index.set_estimate(&reference(1, 4), Some(Estimate { value: 5 }));
+        assert_eq!(
+            index.get(&reference(2, 2)).unwrap().estimate,
+            Some(Estimate { value: 5 })
+        );
+        assert_eq!(index.len(), 3);
+    }
+
+    #[test]
+    fn reorder_pipeline_issues() {
+        let reference = |issue_number| IssueReference {
+            repo_id: 1,
+            issue_number,
+        };
+        let current = [reference(1), reference(2), reference(3), reference(4)];
+
+        assert_eq!(reorder_moves(&current, &current), vec![]);
+        assert_eq!(
+            reorder_moves(&current, &[reference(3), reference(1), reference(2)]),
+            vec![(reference(3), 0)]
+        );
+        assert_eq!(
+            reorder_moves(
+                &current,
+                &[reference(4), reference(3), reference(2), reference(1)]
+            ),
+            vec![(reference(4), 0), (reference(3), 1), (reference(2), 2)]
+        );
+    }
 }
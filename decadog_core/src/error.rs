@@ -1,8 +1,15 @@
-use reqwest::{Error as ReqwestError, StatusCode};
+use std::io::Error as IoError;
+
+use chrono::{DateTime, Utc};
+use reqwest::blocking::Response;
+use reqwest::{Error as ReqwestError, Method, StatusCode};
+use serde::de::DeserializeOwned;
 use snafu::Snafu;
 use url::ParseError as UrlParseError;
 
 use crate::github::GithubClientErrorBody;
+use crate::rate_limit::RateLimitedApi;
+use crate::zenhub::ZenhubErrorKind;
 
 #[derive(Debug, Snafu)]
 #[snafu(visibility = "pub")]
@@ -16,18 +23,130 @@ pub enum Error {
     #[snafu(display("Decadog config error: {}", description))]
     Config { description: String },
 
-    #[snafu(display("Github error [{}]: {:?}", status, error))]
+    #[snafu(display("Github error [{}] for {} {}: {}", status, method, path, error))]
     Github {
-        error: GithubClientErrorBody,
+        method: Method,
+        path: String,
+        error: Box<GithubClientErrorBody>,
         status: StatusCode,
     },
 
+    #[snafu(display(
+        "Github abuse detection triggered by {} {}, wait before retrying: {}",
+        method,
+        path,
+        error
+    ))]
+    GithubAbuseDetected {
+        method: Method,
+        path: String,
+        error: Box<GithubClientErrorBody>,
+    },
+
+    #[snafu(display(
+        "Github rejected credentials for {} {}, check your token is valid: {}",
+        method,
+        path,
+        error
+    ))]
+    GithubBadCredentials {
+        method: Method,
+        path: String,
+        error: Box<GithubClientErrorBody>,
+    },
+
+    #[snafu(display(
+        "Github denied access to {} {}, check your token has the required scopes ({}): {}",
+        method,
+        path,
+        accepted_scopes.as_deref().unwrap_or("unknown"),
+        error
+    ))]
+    GithubMissingScope {
+        method: Method,
+        path: String,
+        accepted_scopes: Option<String>,
+        error: Box<GithubClientErrorBody>,
+    },
+
+    #[snafu(display(
+        "Github could not find {} {}, or your token cannot access it: {}",
+        method,
+        path,
+        error
+    ))]
+    GithubNotFound {
+        method: Method,
+        path: String,
+        error: Box<GithubClientErrorBody>,
+    },
+
+    #[snafu(display("Github validation failed for {} {}: {}", method, path, error))]
+    GithubValidationFailed {
+        method: Method,
+        path: String,
+        error: Box<GithubClientErrorBody>,
+    },
+
+    #[snafu(display(
+        "{}. Sprint milestones were left in {}, delete them before retrying",
+        source,
+        repositories.join(", ")
+    ))]
+    IncompleteSprint {
+        source: Box<Error>,
+        repositories: Vec<String>,
+    },
+
+    #[snafu(display("Incomplete results: {}", description))]
+    IncompleteResults { description: String },
+
+    #[snafu(display("Io error: {}", source))]
+    Io { source: IoError },
+
+    #[snafu(display(
+        "{} rate limit exceeded until {}, try again later",
+        api,
+        reset_at.format("%H:%M:%S UTC")
+    ))]
+    RateLimited {
+        api: RateLimitedApi,
+        reset_at: DateTime<Utc>,
+    },
+
     #[snafu(display("Reqwest error: {}", source))]
     Reqwest { source: ReqwestError },
 
+    #[snafu(display(
+        "{}. Zenhub error [{}] for {} {}: {} (request id {})",
+        kind,
+        status,
+        method,
+        path,
+        message,
+        request_id.as_deref().unwrap_or("unknown")
+    ))]
+    Zenhub {
+        kind: ZenhubErrorKind,
+        method: Method,
+        path: String,
+        message: String,
+        request_id: Option<String>,
+        status: StatusCode,
+    },
+
+    #[snafu(display("Zenhub GraphQL error in {}: {}", operation, message))]
+    ZenhubGraphql { operation: String, message: String },
+
     #[snafu(display("Url parse error: {}", source))]
     Url { source: UrlParseError },
 
+    #[snafu(display("Invalid webhook payload: {}", description))]
+    WebhookPayload { description: String },
+
+    #[snafu(display("Invalid webhook signature: {}", description))]
+    WebhookSignature { description: String },
+
     #[snafu(display("Unknown error: {}", description))]
     Unknown { description: String },
 }
@@ -43,3 +162,13 @@ impl From<UrlParseError> for Error {
         Error::Url { source }
     }
 }
+
+/// Read the body of an error response as JSON, or as trimmed text if it is not.
+///
+/// Not all errors have a JSON body, such as those from proxies.
+pub(crate) fn error_body<T: DeserializeOwned>(
+    response: Response,
+) -> Result<Result<T, String>, Error> {
+    let text = response.text()?;
+    Ok(serde_json::from_str(&text).map_err(|_| text.trim().to_owned()))
+}
@@ -0,0 +1,337 @@
+/// How issues flow through pipelines: time in each pipeline, cycle time and lead time.
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, FixedOffset};
+
+use crate::github::Issue;
+use crate::zenhub::IssueEvent;
+
+fn add_time(pipeline_times: &mut BTreeMap<String, Duration>, pipeline: &str, duration: Duration) {
+    let total = pipeline_times
+        .entry(pipeline.to_owned())
+        .or_insert_with(Duration::zero);
+    *total = *total + duration;
+}
+
+/// How a single issue moved through pipelines.
+#[derive(Debug, Clone)]
+pub struct IssueFlow {
+    pub issue: Issue,
+    /// The latest estimate, if the issue has one.
+    pub estimate: Option<u32>,
+    /// Total time spent in each pipeline, by pipeline name.
+    pub pipeline_times: BTreeMap<String, Duration>,
+    /// Time from first entering the in progress pipeline to being closed.
+    pub cycle_time: Option<Duration>,
+    /// Time from being created to being closed.
+    pub lead_time: Option<Duration>,
+}
+
+impl IssueFlow {
+    /// Work out the flow of `issue` from its Zenhub events.
+    ///
+    /// Time in the current pipeline of an open issue is counted up to `now`.
+    pub fn new(
+        issue: Issue,
+        events: &[IssueEvent],
+        in_progress: &str,
+        now: DateTime<FixedOffset>,
+    ) -> Self {
+        let mut events: Vec<&IssueEvent> = events.iter().collect();
+        events.sort_by_key(|event| match event {
+            IssueEvent::TransferIssue { created_at, .. }
+            | IssueEvent::EstimateIssue { created_at, .. } => Some(*created_at),
+            IssueEvent::Other => None,
+        });
+        let end = issue.closed_at.unwrap_or(now);
+
+        let mut pipeline_times: BTreeMap<String, Duration> = BTreeMap::new();
+        let mut current: Option<&str> = None;
+        let mut since = issue.created_at;
+        let mut started_at = None;
+        let mut estimate = None;
+        for event in events {
+            match event {
+                IssueEvent::TransferIssue {
+                    created_at,
+                    from_pipeline,
+                    to_pipeline,
+                } => {
+                    let moved_at = (*created_at).min(end);
+                    if let Some(pipeline) = current.or_else(|| {
+                        from_pipeline
+                            .as_ref()
+                            .map(|pipeline| pipeline.name.as_ref())
+                    }) {
+                        add_time(&mut pipeline_times, pipeline, moved_at - since);
+                    }
+                    // Moves after the issue was closed, such as tidying the board, are not work
+                    if started_at.is_none()
+                        && to_pipeline.name.eq_ignore_ascii_case(in_progress)
+                        && issue
+                            .closed_at
+                            .is_none_or(|closed_at| *created_at <= closed_at)
+                    {
+                        started_at = Some(*created_at);
+                    }
+                    current = Some(&to_pipeline.name);
+                    since = moved_at;
+                }
+                IssueEvent::EstimateIssue { to_estimate, .. } => {
+                    estimate = to_estimate.as_ref().map(|estimate| estimate.value);
+                }
+                IssueEvent::Other => (),
+            }
+        }
+        if let Some(pipeline) = current {
+            add_time(&mut pipeline_times, pipeline, end - since);
+        }
+
+        let cycle_time = match (started_at, issue.closed_at) {
+            (Some(started_at), Some(closed_at)) => Some(closed_at - started_at),
+            _ => None,
+        };
+        let lead_time = issue
+            .closed_at
+            .map(|closed_at| closed_at - issue.created_at);
+        Self {
+            issue,
+            estimate,
+            pipeline_times,
+            cycle_time,
+            lead_time,
+        }
+    }
+}
+
+/// Percentiles of a set of durations, by nearest rank.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Percentiles {
+    pub count: usize,
+    pub p50: Duration,
+    pub p85: Duration,
+    pub p95: Duration,
+}
+
+impl Percentiles {
+    /// Percentiles of `durations`, or `None` if there are none.
+    pub fn new<I: IntoIterator<Item = Duration>>(durations: I) -> Option<Self> {
+        let mut durations: Vec<Duration> = durations.into_iter().collect();
+        if durations.is_empty() {
+            return None;
+        }
+        durations.sort();
+        let percentile = |percent: usize| {
+            let rank = (percent * durations.len()).div_ceil(100).max(1);
+            durations[rank - 1]
+        };
+        Some(Self {
+            count: durations.len(),
+            p50: percentile(50),
+            p85: percentile(85),
+            p95: percentile(95),
+        })
+    }
+}
+
+/// Flow of a group of issues.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowSummary {
+    pub issues: usize,
+    /// Cycle time of closed issues that were in progress.
+    pub cycle_time: Option<Percentiles>,
+    /// Lead time of closed issues.
+    pub lead_time: Option<Percentiles>,
+    /// Time spent in each pipeline, by issues that were in it.
+    pub pipeline_times: BTreeMap<String, Percentiles>,
+}
+
+impl FlowSummary {
+    pub fn new<'a, I: IntoIterator<Item = &'a IssueFlow>>(flows: I) -> Self {
+        let flows: Vec<&IssueFlow> = flows.into_iter().collect();
+        let mut pipeline_times: BTreeMap<String, Vec<Duration>> = BTreeMap::new();
+        for flow in flows.iter() {
+            for (pipeline, duration) in flow.pipeline_times.iter() {
+                pipeline_times
+                    .entry(pipeline.clone())
+                    .or_default()
+                    .push(*duration);
+            }
+        }
+        Self {
+            issues: flows.len(),
+            cycle_time: Percentiles::new(flows.iter().filter_map(|flow| flow.cycle_time)),
+            lead_time: Percentiles::new(flows.iter().filter_map(|flow| flow.lead_time)),
+            pipeline_times: pipeline_times
+                .into_iter()
+                .filter_map(|(pipeline, durations)| Some((pipeline, Percentiles::new(durations)?)))
+                .collect(),
+        }
+    }
+
+    /// Summaries for each label, of the issues with that label.
+    pub fn by_label(flows: &[IssueFlow]) -> BTreeMap<String, Self> {
+        let mut groups: BTreeMap<String, Vec<&IssueFlow>> = BTreeMap::new();
+        for flow in flows.iter() {
+            for label in flow.issue.labels.iter() {
+                groups.entry(label.name.clone()).or_default().push(flow);
+            }
+        }
+        groups
+            .into_iter()
+            .map(|(label, flows)| (label, Self::new(flows)))
+            .collect()
+    }
+
+    /// Summaries for each estimate, of the issues with that estimate.
+    pub fn by_estimate(flows: &[IssueFlow]) -> BTreeMap<Option<u32>, Self> {
+        let mut groups: BTreeMap<Option<u32>, Vec<&IssueFlow>> = BTreeMap::new();
+        for flow in flows.iter() {
+            groups.entry(flow.estimate).or_default().push(flow);
+        }
+        groups
+            .into_iter()
+            .map(|(estimate, flows)| (estimate, Self::new(flows)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::github::Label;
+    use crate::zenhub::{Estimate, EventPipeline};
+
+    fn date(day: u32) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(&format!("2020-01-{:02}T00:00:00Z", day)).unwrap()
+    }
+
+    fn transfer(day: u32, from: Option<&str>, to: &str) -> IssueEvent {
+        IssueEvent::TransferIssue {
+            created_at: date(day),
+            from_pipeline: from.map(|name| EventPipeline {
+                name: name.to_owned(),
+            }),
+            to_pipeline: EventPipeline {
+                name: to.to_owned(),
+            },
+        }
+    }
+
+    fn issue(closed_day: Option<u32>, label: &str) -> Issue {
+        Issue {
+            created_at: date(1),
+            closed_at: closed_day.map(date),
+            labels: vec![Label {
+                id: 1,
+                name: label.to_owned(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_issue_flow() {
+        // Events arrive newest first
+        let events = [
+            transfer(9, Some("Review"), "Closed"),
+            transfer(7, Some("In Progress"), "Review"),
+            IssueEvent::EstimateIssue {
+                created_at: date(4),
+                from_estimate: Some(Estimate { value: 3 }),
+                to_estimate: Some(Estimate { value: 5 }),
+            },
+            transfer(3, Some("Backlog"), "In Progress"),
+            IssueEvent::Other,
+        ];
+
+        let flow = IssueFlow::new(issue(Some(8), "bug"), &events, "in progress", date(20));
+        assert_eq!(flow.estimate, Some(5));
+        assert_eq!(flow.cycle_time, Some(Duration::days(5)));
+        assert_eq!(flow.lead_time, Some(Duration::days(7)));
+        assert_eq!(
+            flow.pipeline_times.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("Backlog".to_owned(), Duration::days(2)),
+                ("Closed".to_owned(), Duration::zero()),
+                ("In Progress".to_owned(), Duration::days(4)),
+                ("Review".to_owned(), Duration::days(1)),
+            ]
+        );
+
+        let flow = IssueFlow::new(issue(None, "bug"), &events[3..], "In Progress", date(20));
+        assert_eq!(flow.cycle_time, None);
+        assert_eq!(flow.lead_time, None);
+        assert_eq!(flow.pipeline_times["In Progress"], Duration::days(17));
+    }
+
+    #[test]
+    fn test_issue_flow_moved_after_close() {
+        // Closed from the backlog, then moved through In Progress while tidying the board
+        let events = [
+            transfer(2, Some("Backlog"), "In Progress"),
+            transfer(3, Some("In Progress"), "Closed"),
+        ];
+        let flow = IssueFlow::new(issue(Some(1), "bug"), &events, "In Progress", date(20));
+        assert_eq!(flow.cycle_time, None);
+        assert_eq!(flow.lead_time, Some(Duration::zero()));
+    }
+
+    #[test]
+    fn test_percentiles() {
+        assert_eq!(Percentiles::new(vec![]), None);
+        let percentiles = Percentiles::new((1..=20).rev().map(Duration::days)).unwrap();
+        assert_eq!(
+            percentiles,
+            Percentiles {
+                count: 20,
+                p50: Duration::days(10),
+                p85: Duration::days(17),
+                p95: Duration::days(19),
+            }
+        );
+        let percentiles = Percentiles::new(vec![Duration::hours(3)]).unwrap();
+        assert_eq!(percentiles.p50, Duration::hours(3));
+        assert_eq!(percentiles.p95, Duration::hours(3));
+    }
+
+    #[test]
+    fn test_flow_summary_groups() {
+        let flow = |closed_day, label: &str, estimate| {
+            let events = [transfer(1, Some("Backlog"), "In Progress")];
+            let mut flow = IssueFlow::new(
+                issue(Some(closed_day), label),
+                &events,
+                "In Progress",
+                date(20),
+            );
+            flow.estimate = estimate;
+            flow
+        };
+        let flows = [
+            flow(4, "bug", Some(3)),
+            flow(9, "feature", Some(8)),
+            flow(11, "feature", Some(8)),
+        ];
+
+        let by_estimate = FlowSummary::by_estimate(&flows);
+        assert_eq!(
+            by_estimate.keys().copied().collect::<Vec<_>>(),
+            vec![Some(3), Some(8)]
+        );
+        assert_eq!(by_estimate[&Some(8)].issues, 2);
+        assert_eq!(
+            by_estimate[&Some(8)].cycle_time.as_ref().unwrap().p50,
+            Duration::days(8)
+        );
+
+        let by_label = FlowSummary::by_label(&flows);
+        assert_eq!(
+            by_label["bug"].lead_time.as_ref().unwrap().p95,
+            Duration::days(3)
+        );
+        assert_eq!(by_label["feature"].pipeline_times["In Progress"].count, 2);
+    }
+}
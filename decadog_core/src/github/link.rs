@@ -0,0 +1,261 @@
+/// Parsing of `Link` headers, as specified by RFC 8288.
+use std::str::Chars;
+
+use url::Url;
+
+use crate::error::Error;
+
+/// A single link from a `Link` header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    /// Target of the link, resolved against the url of the response.
+    pub url: Url,
+    /// Relation types of the link, lowercased.
+    pub rel: Vec<String>,
+    /// Other parameters of the link, with lowercased names.
+    pub params: Vec<(String, String)>,
+}
+
+/// All links from a `Link` header.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Links(Vec<Link>);
+
+fn invalid(value: &str) -> Error {
+    Error::Unknown {
+        description: format!("Invalid Link header: {}", value),
+    }
+}
+
+/// Whether `c` may appear in a token, as defined by RFC 7230.
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+/// A cursor over a header value, consuming it as it is parsed.
+struct Parser<'a> {
+    value: &'a str,
+    chars: std::iter::Peekable<Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(value: &'a str) -> Self {
+        Self {
+            value,
+            chars: value.chars().peekable(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ') | Some('\t') = self.chars.peek() {
+            self.chars.next();
+        }
+    }
+
+    /// Consume `expected` if it is the next character.
+    fn consume(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&expected) {
+            self.chars.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_empty(&mut self) -> bool {
+        self.skip_whitespace();
+        self.chars.peek().is_none()
+    }
+
+    fn uri_reference(&mut self) -> Result<String, Error> {
+        if !self.consume('<') {
+            return Err(invalid(self.value));
+        }
+        let mut uri = String::new();
+        loop {
+            match self.chars.next() {
+                Some('>') => return Ok(uri),
+                Some(c) => uri.push(c),
+                None => return Err(invalid(self.value)),
+            }
+        }
+    }
+
+    fn token(&mut self) -> Result<String, Error> {
+        self.skip_whitespace();
+        let mut token = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !is_token_char(c) {
+                break;
+            }
+            token.push(c);
+            self.chars.next();
+        }
+        if token.is_empty() {
+            return Err(invalid(self.value));
+        }
+        Ok(token)
+    }
+
+    fn quoted_string(&mut self) -> Result<String, Error> {
+        let mut string = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => match self.chars.next() {
+                    Some(c) => string.push(c),
+                    None => return Err(invalid(self.value)),
+                },
+                Some(c) => string.push(c),
+                None => return Err(invalid(self.value)),
+            }
+        }
+    }
+
+    /// Parse a parameter, which may have a token or quoted string value, or none at all.
+    fn param(&mut self) -> Result<(String, String), Error> {
+        let name = self.token()?.to_ascii_lowercase();
+        if !self.consume('=') {
+            return Ok((name, String::new()));
+        }
+        let value = if self.consume('"') {
+            self.quoted_string()?
+        } else {
+            self.token()?
+        };
+        Ok((name, value))
+    }
+}
+
+impl Links {
+    /// Parse a `Link` header value, resolving relative links against `base`.
+    pub fn parse(value: &str, base: &Url) -> Result<Self, Error> {
+        let mut parser = Parser::new(value);
+        let mut links = Vec::new();
+        loop {
+            // Empty list elements are allowed, and ignored
+            while parser.consume(',') {}
+            if parser.is_empty() {
+                break;
+            }
+
+            let url = base.join(&parser.uri_reference()?)?;
+            let mut rel = Vec::new();
+            let mut params = Vec::new();
+            while parser.consume(';') {
+                let (name, value) = parser.param()?;
+                // Only the first rel parameter counts
+                if name == "rel" {
+                    if rel.is_empty() {
+                        rel = value
+                            .split_whitespace()
+                            .map(|rel| rel.to_ascii_lowercase())
+                            .collect();
+                    }
+                } else {
+                    params.push((name, value));
+                }
+            }
+            links.push(Link { url, rel, params });
+
+            if !parser.consume(',') && !parser.is_empty() {
+                return Err(invalid(value));
+            }
+        }
+        Ok(Self(links))
+    }
+
+    /// Get the target of the first link with relation type `rel`.
+    pub fn get(&self, rel: &str) -> Option<&Url> {
+        self.0
+            .iter()
+            .find(|link| link.rel.iter().any(|link_rel| link_rel == rel))
+            .map(|link| &link.url)
+    }
+
+    pub fn next(&self) -> Option<&Url> {
+        self.get("next")
+    }
+
+    pub fn prev(&self) -> Option<&Url> {
+        self.get("prev")
+    }
+
+    pub fn first(&self) -> Option<&Url> {
+        self.get("first")
+    }
+
+    pub fn last(&self) -> Option<&Url> {
+        self.get("last")
+    }
+
+    /// All links in the header, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &Link> {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn base() -> Url {
+        Url::parse("https://api.github.com/search/issues?q=test").unwrap()
+    }
+
+    #[test]
+    fn test_parse_github() {
+        let links = Links::parse(
+            r#"<https://api.github.com/search/issues?q=test&page=2>; rel="next", <https://api.github.com/search/issues?q=test&page=34>; rel="last""#,
+            &base(),
+        )
+        .unwrap();
+        assert_eq!(
+            links.next().unwrap().as_str(),
+            "https://api.github.com/search/issues?q=test&page=2"
+        );
+        assert_eq!(
+            links.last().unwrap().as_str(),
+            "https://api.github.com/search/issues?q=test&page=34"
+        );
+        assert_eq!(links.prev(), None);
+        assert_eq!(links.first(), None);
+    }
+
+    #[test]
+    fn test_parse_rfc_8288() {
+        let links = Links::parse(
+            r#"</page/1>;rel=first , <https://example.com/a,b;c>; title="a \"quoted\", title"; rel="PREV start";type="text/html",,"#,
+            &base(),
+        )
+        .unwrap();
+        assert_eq!(
+            links.first().unwrap().as_str(),
+            "https://api.github.com/page/1"
+        );
+        assert_eq!(links.prev().unwrap().as_str(), "https://example.com/a,b;c");
+        assert_eq!(links.get("start"), links.prev());
+        assert_eq!(
+            links.iter().nth(1).unwrap().params,
+            vec![
+                ("title".to_owned(), r#"a "quoted", title"#.to_owned()),
+                ("type".to_owned(), "text/html".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(Links::parse("", &base()).unwrap(), Links::default());
+        for value in &[
+            "https://example.com; rel=next",
+            "<https://example.com; rel=next",
+            r#"<https://example.com>; rel="next"#,
+            "<https://example.com> <https://example.org>",
+        ] {
+            assert!(Links::parse(value, &base()).is_err(), "{}", value);
+        }
+    }
+}
@@ -2,28 +2,49 @@
 use std::collections::hash_map::DefaultHasher;
 use std::fmt;
 use std::hash::Hasher;
+use std::time::Duration;
 
-use chrono::{DateTime, FixedOffset, TimeZone};
+use chrono::{DateTime, FixedOffset};
 use log::debug;
-use reqwest::header::{HeaderMap, AUTHORIZATION};
+use reqwest::header::{AUTHORIZATION, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
 use reqwest::{
-    blocking::{Client as ReqwestClient, ClientBuilder, RequestBuilder},
-    Method, Url,
+    blocking::{Client as ReqwestClient, ClientBuilder, Request, Response},
+    Method, StatusCode, Url,
 };
 use serde_derive::{Deserialize, Serialize};
 
+use crate::base_url::BaseUrl;
+use crate::cache::{Cache, CacheEntry};
 use crate::error::Error;
+use crate::retry::RetryPolicy;
 
+pub mod auth;
+pub mod link;
 pub mod paginate;
 pub mod request;
+pub mod search;
 
-use paginate::PaginatedSearch;
-use request::RequestBuilderExt;
+use crate::rate_limit::{RateLimit, RateLimitedApi, RateLimiter};
+use auth::{AppCredentials, Auth};
+use paginate::{PaginatedList, PaginatedSearch};
+use request::RequestBuilder;
+pub use search::{DateQualifier, DateRange, SearchQueryBuilder};
+
+/// Number of items to request per page, by default. This is the maximum Github allows.
+pub const DEFAULT_PER_PAGE: u32 = 100;
+
+/// Maximum time to wait for a rate limit to reset, by default.
+pub const DEFAULT_MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
 
 pub struct Client {
     id: u64,
     reqwest_client: ReqwestClient,
-    base_url: Url,
+    base_url: BaseUrl,
+    auth: Auth,
+    per_page: u32,
+    rate_limiter: RateLimiter,
+    retry_policy: RetryPolicy,
+    cache: Option<Cache>,
 }
 
 impl fmt::Debug for Client {
@@ -35,9 +56,13 @@ impl fmt::Debug for Client {
 /// Detail of a single client error.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GithubClientErrorDetail {
+    #[serde(default)]
     pub resource: String,
+    #[serde(default)]
     pub field: String,
     pub code: String,
+    /// Only given for custom errors.
+    pub message: Option<String>,
 }
 
 /// Returned from the API when one or more client errors have been made.
@@ -48,60 +73,202 @@ pub struct GithubClientErrorBody {
     pub documentation_url: Option<String>,
 }
 
+impl fmt::Display for GithubClientErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        for detail in self.errors.iter().flatten() {
+            match &detail.message {
+                Some(message) => write!(f, " ({})", message)?,
+                None => write!(f, " ({} {} {})", detail.resource, detail.field, detail.code)?,
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Client {
     /// Create a new client that can make requests to the Github API using token auth.
     pub fn new(url: &str, token: &str) -> Result<Client, Error> {
-        // Create reqwest client to interact with APIs
-        // TODO: should we pass in an external client here?
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            AUTHORIZATION,
-            format!("token {}", token)
-                .parse()
-                .map_err(|_| Error::Config {
-                    description: "Invalid Github token for Authorization header.".to_owned(),
-                })?,
-        );
-
-        let reqwest_client = ClientBuilder::new()
-            .default_headers(headers)
-            .user_agent("decadog")
-            .build()?;
+        let mut hasher = DefaultHasher::new();
+        hasher.write(url.as_bytes());
+        hasher.write(token.as_bytes());
+        let id = hasher.finish();
 
-        let base_url = Url::parse(url).map_err(|_| Error::Config {
-            description: format!("Invalid Github base url {}", url),
-        })?;
+        Self::with_auth(id, url, Auth::token(token)?)
+    }
 
+    /// Create a new client that can make requests to the Github API as a Github App installation.
+    ///
+    /// Installation tokens are created as required, and refreshed before they expire.
+    pub fn new_app(url: &str, credentials: AppCredentials) -> Result<Client, Error> {
         let mut hasher = DefaultHasher::new();
         hasher.write(url.as_bytes());
-        hasher.write(token.as_bytes());
+        hasher.write(&credentials.app_id().to_be_bytes());
+        hasher.write(&credentials.installation_id().to_be_bytes());
         let id = hasher.finish();
 
+        Self::with_auth(id, url, Auth::app(credentials))
+    }
+
+    fn with_auth(id: u64, url: &str, auth: Auth) -> Result<Client, Error> {
+        // Create reqwest client to interact with APIs
+        // TODO: should we pass in an external client here?
+        let reqwest_client = ClientBuilder::new().user_agent("decadog").build()?;
+
+        let base_url = BaseUrl::parse(url, "Github")?;
+
         Ok(Client {
             id,
             reqwest_client,
             base_url,
+            auth,
+            per_page: DEFAULT_PER_PAGE,
+            rate_limiter: RateLimiter::new(RateLimitedApi::Github, DEFAULT_MAX_RATE_LIMIT_WAIT),
+            retry_policy: RetryPolicy::default(),
+            cache: None,
         })
     }
 
+    /// Set the number of items requested per page from paginated endpoints.
+    ///
+    /// Github accepts values between 1 and 100.
+    pub fn with_per_page(mut self, per_page: u32) -> Result<Self, Error> {
+        if !(1..=100).contains(&per_page) {
+            return Err(Error::Config {
+                description: format!(
+                    "Github per_page must be between 1 and 100, got {}",
+                    per_page
+                ),
+            });
+        }
+        self.per_page = per_page;
+        Ok(self)
+    }
+
+    /// Set the maximum time to wait for a rate limit to reset, before failing.
+    pub fn with_max_rate_limit_wait(mut self, max_wait: Duration) -> Self {
+        self.rate_limiter = RateLimiter::new(RateLimitedApi::Github, max_wait);
+        self
+    }
+
+    /// Set the policy for retrying requests that fail transiently.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Cache responses to GET requests in `cache`.
+    ///
+    /// Cached responses are revalidated with a conditional request, so are never stale.
+    /// Github does not count unchanged responses against the rate limit.
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
     pub fn id(&self) -> u64 {
         self.id
     }
 
+    pub fn per_page(&self) -> u32 {
+        self.per_page
+    }
+
+    /// The most recent rate limit state reported by Github, if any.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limiter.state()
+    }
+
     /// Returns a `request::RequestBuilder` authorized to the Github API.
-    pub fn request(&self, method: Method, url: Url) -> RequestBuilder {
-        debug!("{} {}", method, url.as_str());
-        self.reqwest_client.request(method, url)
+    pub fn request(&self, method: Method, url: Url) -> RequestBuilder<'_> {
+        RequestBuilder::new(self, self.reqwest_client.request(method, url))
+    }
+
+    /// Execute a request against the Github API.
+    ///
+    /// Requests with idempotent methods are retried on transient failure.
+    pub fn execute(&self, request: Request) -> Result<Response, Error> {
+        self.execute_retrying(request, false)
+    }
+
+    /// Execute a request against the Github API, retrying on transient failure.
+    ///
+    /// Requests with methods that are not idempotent are only retried if `retry_unsafe` is set.
+    pub fn execute_retrying(
+        &self,
+        request: Request,
+        retry_unsafe: bool,
+    ) -> Result<Response, Error> {
+        match &self.cache {
+            Some(cache) if request.method() == Method::GET => self.execute_cached(cache, request),
+            _ => self
+                .retry_policy
+                .execute(request, retry_unsafe, |request| self.execute_once(request)),
+        }
+    }
+
+    /// Execute a GET request, using a cached response if it has not been modified.
+    fn execute_cached(&self, cache: &Cache, mut request: Request) -> Result<Response, Error> {
+        // Responses may differ by credentials, so include our client id in the key
+        let key = format!("{} {}", self.id, request.url());
+        let entry = cache.get(&key);
+        if let Some(entry) = &entry {
+            if let Some(etag) = entry.header(&ETAG).and_then(|etag| etag.parse().ok()) {
+                request.headers_mut().insert(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = entry
+                .header(&LAST_MODIFIED)
+                .and_then(|last_modified| last_modified.parse().ok())
+            {
+                request
+                    .headers_mut()
+                    .insert(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = self
+            .retry_policy
+            .execute(request, false, |request| self.execute_once(request))?;
+        let cacheable = response.status().is_success()
+            && (response.headers().contains_key(ETAG)
+                || response.headers().contains_key(LAST_MODIFIED));
+        match entry {
+            Some(entry) if response.status() == StatusCode::NOT_MODIFIED => {
+                debug!("Using cached response for {}", &key);
+                Ok(entry.to_response())
+            }
+            _ if cacheable => {
+                let entry = CacheEntry::from_response(response)?;
+                cache.put(&key, &entry);
+                Ok(entry.to_response())
+            }
+            _ => Ok(response),
+        }
+    }
+
+    /// Execute a request against the Github API once.
+    ///
+    /// If a rate limit is hit, waits for it to reset and retries the request.
+    fn execute_once(&self, request: Request) -> Result<Response, Error> {
+        self.rate_limiter.execute(request, |mut request| {
+            // Authorize as late as possible, so that tokens are always fresh. Requests
+            // for installation tokens are already authorized as the app.
+            if !request.headers().contains_key(AUTHORIZATION) {
+                let authorization = self.auth.header(self)?;
+                request.headers_mut().insert(AUTHORIZATION, authorization);
+            }
+
+            debug!("{} {}", request.method(), request.url().as_str());
+            Ok(self.reqwest_client.execute(request)?)
+        })
     }
 
     /// Get an issue by owner, repo name and issue number.
     pub fn get_issue(&self, owner: &str, repo: &str, issue_number: u32) -> Result<Issue, Error> {
         self.request(
             Method::GET,
-            self.base_url.join(&format!(
-                "/repos/{}/{}/issues/{}",
-                owner, repo, issue_number
-            ))?,
+            self.base_url
+                .join(&format!("repos/{}/{}/issues/{}", owner, repo, issue_number))?,
         )
         .send_github()
     }
@@ -110,35 +277,103 @@ impl Client {
     pub fn get_repository(&self, owner: &str, repo: &str) -> Result<Repository, Error> {
         self.request(
             Method::GET,
-            self.base_url.join(&format!("/repos/{}/{}", owner, repo))?,
+            self.base_url.join(&format!("repos/{}/{}", owner, repo))?,
         )
         .send_github()
     }
 
+    /// Get a paginated list of members from `path`.
+    fn get_member_list(&self, path: &str) -> Result<PaginatedList<'_, OrganisationMember>, Error> {
+        let query = GetPage {
+            per_page: Some(self.per_page),
+        };
+        let request = self
+            .request(Method::GET, self.base_url.join(path)?)
+            .query(&query)
+            .build()?;
+
+        PaginatedList::<OrganisationMember>::new(self, request)
+    }
+
     /// Get members by organisation.
-    pub fn get_members(&self, organisation: &str) -> Result<Vec<OrganisationMember>, Error> {
+    pub fn get_members(
+        &self,
+        organisation: &str,
+    ) -> Result<PaginatedList<'_, OrganisationMember>, Error> {
+        self.get_member_list(&format!("orgs/{}/members", organisation))
+    }
+
+    /// Get members of a team, by organisation and team slug.
+    pub fn get_team_members(
+        &self,
+        organisation: &str,
+        team_slug: &str,
+    ) -> Result<PaginatedList<'_, OrganisationMember>, Error> {
+        self.get_member_list(&format!(
+            "orgs/{}/teams/{}/members",
+            organisation, team_slug
+        ))
+    }
+
+    /// Get users that issues in a repository can be assigned to.
+    pub fn get_assignees(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<PaginatedList<'_, OrganisationMember>, Error> {
+        self.get_member_list(&format!("repos/{}/{}/assignees", owner, repo))
+    }
+
+    /// Get collaborators on a repository.
+    pub fn get_collaborators(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<PaginatedList<'_, OrganisationMember>, Error> {
+        self.get_member_list(&format!("repos/{}/{}/collaborators", owner, repo))
+    }
+
+    /// Get a user by login.
+    pub fn get_user(&self, login: &str) -> Result<OrganisationMember, Error> {
         self.request(
             Method::GET,
-            self.base_url
-                .join(&format!("orgs/{}/members", organisation))?,
+            self.base_url.join(&format!("users/{}", login))?,
         )
         .send_github()
     }
 
-    /// Get milestones by owner and repo name.
-    pub fn get_milestones(&self, owner: &str, repo: &str) -> Result<Vec<Milestone>, Error> {
+    /// Get open milestones by owner and repo name.
+    pub fn get_milestones(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<PaginatedList<'_, Milestone>, Error> {
+        self.get_milestones_in_state(owner, repo, None)
+    }
+
+    /// Get milestones by owner and repo name, in `state` or open by default.
+    pub fn get_milestones_in_state(
+        &self,
+        owner: &str,
+        repo: &str,
+        state: Option<SearchState>,
+    ) -> Result<PaginatedList<'_, Milestone>, Error> {
         let query = GetMilestones {
-            state: None,
+            state,
             sort: None,
             direction: Some(Direction::Descending),
+            per_page: Some(self.per_page),
         };
-        self.request(
-            Method::GET,
-            self.base_url
-                .join(&format!("/repos/{}/{}/milestones", owner, repo))?,
-        )
-        .query(&query)
-        .send_github()
+        let request = self
+            .request(
+                Method::GET,
+                self.base_url
+                    .join(&format!("repos/{}/{}/milestones", owner, repo))?,
+            )
+            .query(&query)
+            .build()?;
+
+        PaginatedList::<Milestone>::new(self, request)
     }
 
     /// Get milestones by owner and repo name.
@@ -151,7 +386,7 @@ impl Client {
         self.request(
             Method::POST,
             self.base_url
-                .join(&format!("/repos/{}/{}/milestones", owner, repo))?,
+                .join(&format!("repos/{}/{}/milestones", owner, repo))?,
         )
         .json(&create)
         .send_github()
@@ -167,12 +402,11 @@ impl Client {
     ) -> Result<Issue, Error> {
         self.request(
             Method::PATCH,
-            self.base_url.join(&format!(
-                "/repos/{}/{}/issues/{}",
-                owner, repo, issue_number
-            ))?,
+            self.base_url
+                .join(&format!("repos/{}/{}/issues/{}", owner, repo, issue_number))?,
         )
         .json(update)
+        .retry()
         .send_github()
     }
 
@@ -183,7 +417,7 @@ impl Client {
             .query(&query);
         let request = builder.build()?;
 
-        PaginatedSearch::<Issue>::new(&self.reqwest_client, request)
+        PaginatedSearch::<Issue>::new(self, request)
     }
 
     pub fn patch_milestone(
@@ -196,13 +430,31 @@ impl Client {
         self.request(
             Method::PATCH,
             self.base_url.join(&format!(
-                "/repos/{}/{}/milestones/{}",
+                "repos/{}/{}/milestones/{}",
                 owner, repo, milestone_number
             ))?,
         )
         .json(update)
+        .retry()
         .send_github()
     }
+
+    /// Delete a milestone.
+    pub fn delete_milestone(
+        &self,
+        owner: &str,
+        repo: &str,
+        milestone_number: u32,
+    ) -> Result<(), Error> {
+        self.request(
+            Method::DELETE,
+            self.base_url.join(&format!(
+                "repos/{}/{}/milestones/{}",
+                owner, repo, milestone_number
+            ))?,
+        )
+        .send_github_no_response()
+    }
 }
 
 /// Update an issue.
@@ -236,80 +488,6 @@ pub enum Direction {
     Descending,
 }
 
-#[derive(Debug, Default, Clone, PartialEq)]
-pub struct SearchQueryBuilder {
-    query: String,
-}
-
-impl SearchQueryBuilder {
-    pub fn new() -> Self {
-        Self::default()
-    }
-
-    pub fn build(&self) -> &str {
-        &self.query
-    }
-
-    fn push_separator(&mut self) {
-        if !self.query.is_empty() {
-            self.query.push_str(" ");
-        };
-    }
-
-    pub fn term(&mut self, term: &str) -> &mut Self {
-        self.push_separator();
-        self.query.push_str(term);
-        self
-    }
-
-    pub fn key_value(&mut self, key: &str, value: &str) -> &mut Self {
-        self.push_separator();
-        self.query.push_str(key);
-        self.query.push_str(":");
-        self.query.push_str(value);
-        self
-    }
-
-    pub fn label(&mut self, label_name: &str) -> &mut Self {
-        self.key_value("label", label_name)
-    }
-
-    pub fn not_label(&mut self, label_name: &str) -> &mut Self {
-        self.key_value("-label", label_name)
-    }
-
-    pub fn issue(&mut self) -> &mut Self {
-        self.key_value("type", "issue")
-    }
-
-    pub fn state(&mut self, state: &State) -> &mut Self {
-        self.key_value(
-            "state",
-            &serde_plain::to_string(state).expect("Serializing state to string failed"),
-        )
-    }
-
-    pub fn milestone(&mut self, milestone_title: &str) -> &mut Self {
-        self.term(&format!(r#"milestone:"{}""#, milestone_title))
-    }
-
-    pub fn no_milestone(&mut self) -> &mut Self {
-        self.key_value("no", "milestone")
-    }
-
-    pub fn closed_on_or_after<Tz: TimeZone>(&mut self, datetime: &DateTime<Tz>) -> &mut Self
-    where
-        Tz::Offset: fmt::Display,
-    {
-        self.state(&State::Closed)
-            .term(&format!("closed:>={}", &datetime.format("%Y-%m-%d")))
-    }
-
-    pub fn owner_repo(&mut self, owner: &str, repo: &str) -> &mut Self {
-        self.term(&format!("repo:{}/{}", owner, repo))
-    }
-}
-
 /// Request to search issues.
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct SearchIssues<'request> {
@@ -323,6 +501,13 @@ pub struct SearchIssues<'request> {
     pub per_page: Option<u32>,
 }
 
+/// Request a single page from a list endpoint.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct GetPage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<u32>,
+}
+
 /// Request to get milestones.
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct GetMilestones {
@@ -332,6 +517,8 @@ pub struct GetMilestones {
     pub sort: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub direction: Option<Direction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<u32>,
 }
 
 /// A Github Milestone.
@@ -340,8 +527,14 @@ pub struct Milestone {
     pub id: u32,
     pub number: u32,
     pub title: String,
+    pub description: Option<String>,
     pub state: State,
-    pub due_on: DateTime<FixedOffset>,
+    pub open_issues: u32,
+    pub closed_issues: u32,
+    pub created_at: DateTime<FixedOffset>,
+    pub due_on: Option<DateTime<FixedOffset>>,
+    pub closed_at: Option<DateTime<FixedOffset>>,
+    pub html_url: String,
 }
 
 /// Update a milestone.
@@ -407,6 +600,32 @@ pub struct Issue {
     pub updated_at: DateTime<FixedOffset>,
     pub closed_at: Option<DateTime<FixedOffset>>,
     pub html_url: String,
+    /// API url of the repository this issue belongs to.
+    pub repository_url: String,
+}
+
+impl Issue {
+    /// Owner and name of the repository this issue belongs to.
+    pub fn owner_repo(&self) -> Option<(&str, &str)> {
+        let mut segments = self.repository_url.trim_end_matches('/').rsplit('/');
+        let repo = segments.next().filter(|repo| !repo.is_empty())?;
+        let owner = segments.next().filter(|owner| !owner.is_empty())?;
+        Some((owner, repo))
+    }
+
+    /// Full name of the repository this issue belongs to, as `owner/repo`.
+    pub fn full_name(&self) -> Option<String> {
+        self.owner_repo()
+            .map(|(owner, repo)| format!("{}/{}", owner, repo))
+    }
+
+    /// Short reference to this issue, as `repo#number`.
+    pub fn reference(&self) -> String {
+        match self.owner_repo() {
+            Some((_, repo)) => format!("{}#{}", repo, self.number),
+            None => format!("#{}", self.number),
+        }
+    }
 }
 
 /// A Github Repository.
@@ -418,13 +637,22 @@ pub struct Repository {
 
 impl fmt::Display for Milestone {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.title)
+        write!(f, "{} (", self.title)?;
+        if let Some(due_on) = &self.due_on {
+            write!(f, "due {}, ", due_on.format("%Y-%m-%d"))?;
+        }
+        write!(
+            f,
+            "{}/{} closed)",
+            self.closed_issues,
+            self.open_issues + self.closed_issues
+        )
     }
 }
 
 impl fmt::Display for Issue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}: {}", self.number, self.title)
+        write!(f, "{}: {}", self.reference(), self.title)
     }
 }
 
@@ -435,7 +663,9 @@ pub mod tests {
     use mockito::mock;
     use pretty_assertions::assert_eq;
 
+    use super::auth::tests::TEST_PRIVATE_KEY;
     use super::*;
+    use crate::cache::tests::temporary_cache;
 
     const MOCK_GITHUB_TOKEN: &str = "mock_token";
     lazy_static! {
@@ -456,43 +686,6 @@ pub mod tests {
         }
     }
 
-    #[test]
-    fn search_query_builder() {
-        assert_eq!(SearchQueryBuilder::new().build(), "");
-        assert_eq!(
-            SearchQueryBuilder::new().state(&State::Open).build(),
-            "state:open"
-        );
-        assert_eq!(
-            SearchQueryBuilder::new().issue().label("spam").build(),
-            "type:issue label:spam"
-        );
-        assert_eq!(
-            SearchQueryBuilder::new()
-                .milestone("Sprint 2")
-                .not_label("spam")
-                .build(),
-            r#"milestone:"Sprint 2" -label:spam"#
-        );
-        assert_eq!(
-            SearchQueryBuilder::new()
-                .term("arbitrary")
-                .key_value("k", "v")
-                .build(),
-            "arbitrary k:v"
-        );
-        assert_eq!(
-            SearchQueryBuilder::new()
-                .closed_on_or_after(
-                    &FixedOffset::east(0)
-                        .from_utc_datetime(&NaiveDate::from_ymd(2011, 4, 22).and_hms(13, 33, 48)),
-                )
-                .owner_repo("ow", "re")
-                .build(),
-            "state:closed closed:>=2011-04-22 repo:ow/re"
-        );
-    }
-
     #[test]
     fn test_get_issue() {
         let body = r#"{
@@ -512,7 +705,13 @@ pub mod tests {
     "number": 1,
     "state": "open",
     "title": "v1.0",
-    "due_on": "2012-10-09T23:39:01Z"
+    "description": "Tracking milestone for version 1.0",
+    "open_issues": 4,
+    "closed_issues": 8,
+    "created_at": "2011-04-10T20:09:31Z",
+    "due_on": "2012-10-09T23:39:01Z",
+    "closed_at": null,
+    "html_url": "https://github.com/tommilligan/decadog/milestones/v1.0"
   },
   "labels": [
     {
@@ -522,7 +721,8 @@ pub mod tests {
   ],
   "created_at": "2011-04-22T13:33:48Z",
   "updated_at": "2011-04-22T13:33:48Z",
-  "html_url": "http://foo.bar"
+  "html_url": "http://foo.bar",
+  "repository_url": "https://api.github.com/repos/tommilligan/decadog"
 }"#;
         let mock = mock("GET", "/repos/tommilligan/decadog/issues/1")
             .match_header("authorization", "token mock_token")
@@ -546,9 +746,19 @@ pub mod tests {
                     id: 1_002_604,
                     number: 1,
                     title: "v1.0".to_owned(),
+                    description: Some("Tracking milestone for version 1.0".to_owned()),
                     state: State::Open,
-                    due_on: FixedOffset::east(0)
-                        .from_utc_datetime(&NaiveDate::from_ymd(2012, 10, 9).and_hms(23, 39, 1)),
+                    open_issues: 4,
+                    closed_issues: 8,
+                    created_at: FixedOffset::east(0)
+                        .from_utc_datetime(&NaiveDate::from_ymd(2011, 4, 10).and_hms(20, 9, 31)),
+                    due_on: Some(
+                        FixedOffset::east(0).from_utc_datetime(
+                            &NaiveDate::from_ymd(2012, 10, 9).and_hms(23, 39, 1)
+                        )
+                    ),
+                    closed_at: None,
+                    html_url: "https://github.com/tommilligan/decadog/milestones/v1.0".to_owned(),
                 }),
                 assignees: vec![OrganisationMember {
                     login: "tommilligan".to_owned(),
@@ -564,6 +774,7 @@ pub mod tests {
                     .from_utc_datetime(&NaiveDate::from_ymd(2011, 4, 22).and_hms(13, 33, 48)),
                 closed_at: None,
                 html_url: "http://foo.bar".to_owned(),
+                repository_url: "https://api.github.com/repos/tommilligan/decadog".to_owned(),
             }
         );
     }
@@ -581,7 +792,8 @@ pub mod tests {
   "labels": [],
   "created_at": "2011-04-22T13:33:48Z",
   "updated_at": "2011-04-22T13:33:48Z",
-  "html_url": "http://foo.bar"
+  "html_url": "http://foo.bar",
+  "repository_url": "https://api.github.com/repos/tommilligan/decadog"
 }"#;
         let mock = mock("PATCH", "/repos/tommilligan/decadog/issues/1")
             .match_header("authorization", "token mock_token")
@@ -613,6 +825,7 @@ pub mod tests {
                     .from_utc_datetime(&NaiveDate::from_ymd(2011, 4, 22).and_hms(13, 33, 48)),
                 closed_at: None,
                 html_url: "http://foo.bar".to_owned(),
+                repository_url: "https://api.github.com/repos/tommilligan/decadog".to_owned(),
             }
         );
     }
@@ -624,7 +837,13 @@ pub mod tests {
   "number": 1,
   "state": "closed",
   "title": "Mock Title",
-  "due_on": "2011-04-22T13:33:48Z"
+  "description": null,
+  "open_issues": 0,
+  "closed_issues": 3,
+  "created_at": "2011-04-10T20:09:31Z",
+  "due_on": null,
+  "closed_at": "2011-04-22T13:33:48Z",
+  "html_url": "http://foo.bar"
 }"#;
         let mock = mock("PATCH", "/repos/tommilligan/decadog/milestones/1")
             .match_header("authorization", "token mock_token")
@@ -647,9 +866,538 @@ pub mod tests {
                 number: 1,
                 state: State::Closed,
                 title: "Mock Title".to_owned(),
-                due_on: FixedOffset::east(0)
-                    .from_utc_datetime(&NaiveDate::from_ymd(2011, 4, 22).and_hms(13, 33, 48)),
+                description: None,
+                open_issues: 0,
+                closed_issues: 3,
+                created_at: FixedOffset::east(0)
+                    .from_utc_datetime(&NaiveDate::from_ymd(2011, 4, 10).and_hms(20, 9, 31)),
+                due_on: None,
+                closed_at: Some(
+                    FixedOffset::east(0)
+                        .from_utc_datetime(&NaiveDate::from_ymd(2011, 4, 22).and_hms(13, 33, 48))
+                ),
+                html_url: "http://foo.bar".to_owned(),
             }
         );
     }
+
+    #[test]
+    fn test_issue_repository() {
+        let mut issue: Issue = serde_json::from_str(
+            r#"{"id": 1, "number": 12, "state": "open", "title": "Mock Title", "milestone": null,
+  "assignees": [], "labels": [], "created_at": "2011-04-22T13:33:48Z",
+  "updated_at": "2011-04-22T13:33:48Z", "closed_at": null, "html_url": "http://foo.bar",
+  "repository_url": "https://ghe.example.com/api/v3/repos/tommilligan/decadog"}"#,
+        )
+        .unwrap();
+        assert_eq!(issue.owner_repo(), Some(("tommilligan", "decadog")));
+        assert_eq!(issue.full_name(), Some("tommilligan/decadog".to_owned()));
+        assert_eq!(issue.to_string(), "decadog#12: Mock Title");
+        assert_eq!(issue.reference(), "decadog#12");
+
+        issue.repository_url = "".to_owned();
+        assert_eq!(issue.owner_repo(), None);
+        assert_eq!(issue.to_string(), "#12: Mock Title");
+    }
+
+    #[test]
+    fn test_milestone_display() {
+        let mut milestone = Milestone {
+            id: 1,
+            number: 12,
+            title: "Sprint 12".to_owned(),
+            description: None,
+            state: State::Open,
+            open_issues: 6,
+            closed_issues: 14,
+            created_at: FixedOffset::east(0)
+                .from_utc_datetime(&NaiveDate::from_ymd(2026, 10, 16).and_hms(9, 0, 0)),
+            due_on: Some(
+                FixedOffset::east(0)
+                    .from_utc_datetime(&NaiveDate::from_ymd(2026, 10, 30).and_hms(7, 0, 0)),
+            ),
+            closed_at: None,
+            html_url: "http://foo.bar".to_owned(),
+        };
+        assert_eq!(
+            milestone.to_string(),
+            "Sprint 12 (due 2026-10-30, 14/20 closed)"
+        );
+        milestone.due_on = None;
+        assert_eq!(milestone.to_string(), "Sprint 12 (14/20 closed)");
+    }
+
+    #[test]
+    fn test_get_members_paginated() {
+        let mock_page_one = mock("GET", "/orgs/tommilligan/members?per_page=100")
+            .match_header("authorization", "token mock_token")
+            .with_status(200)
+            .with_header(
+                "link",
+                &format!(
+                    r#"<{}/orgs/tommilligan/members?per_page=100&page=2>; rel="next""#,
+                    &mockito::server_url()
+                ),
+            )
+            .with_body(r#"[{"login": "tommilligan", "id": 1}]"#)
+            .create();
+        let mock_page_two = mock("GET", "/orgs/tommilligan/members?per_page=100&page=2")
+            .match_header("authorization", "token mock_token")
+            .with_status(200)
+            .with_body(r#"[{"login": "octocat", "id": 2}]"#)
+            .create();
+
+        let members = MOCK_GITHUB_CLIENT
+            .get_members("tommilligan")
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        mock_page_one.assert();
+        mock_page_two.assert();
+
+        assert_eq!(
+            members,
+            vec![
+                OrganisationMember {
+                    login: "tommilligan".to_owned(),
+                    id: 1
+                },
+                OrganisationMember {
+                    login: "octocat".to_owned(),
+                    id: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enterprise_path_prefix() {
+        let client = Client::new(
+            &format!("{}/api/v3", mockito::server_url()),
+            MOCK_GITHUB_TOKEN,
+        )
+        .unwrap();
+        let mock_repository = mock("GET", "/api/v3/repos/tommilligan/enterprise")
+            .match_header("authorization", "token mock_token")
+            .with_status(200)
+            .with_body(r#"{"id": 1, "name": "enterprise"}"#)
+            .create();
+        let mock_members = mock("GET", "/api/v3/orgs/enterprise/members?per_page=100")
+            .with_status(200)
+            .with_body(r#"[{"login": "tommilligan", "id": 1}]"#)
+            .create();
+
+        client.get_repository("tommilligan", "enterprise").unwrap();
+        let members = client
+            .get_members("enterprise")
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        mock_repository.assert();
+        mock_members.assert();
+        assert_eq!(members.len(), 1);
+    }
+
+    #[test]
+    fn invalid_per_page() {
+        let client = Client::new("https://api.mygithub.com/", "github_token").unwrap();
+        assert!(client.with_per_page(0).is_err());
+        let client = Client::new("https://api.mygithub.com/", "github_token").unwrap();
+        assert_eq!(client.with_per_page(30).unwrap().per_page(), 30);
+    }
+
+    #[test]
+    fn test_rate_limited() {
+        let client = Client::new(&mockito::server_url(), MOCK_GITHUB_TOKEN)
+            .unwrap()
+            .with_max_rate_limit_wait(Duration::from_secs(0));
+        let mock = mock("GET", "/repos/tommilligan/rate-limited")
+            .with_status(403)
+            .with_header("x-ratelimit-limit", "5000")
+            .with_header("x-ratelimit-remaining", "0")
+            .with_header("x-ratelimit-reset", "4102444800")
+            .with_body(r#"{"message": "API rate limit exceeded"}"#)
+            .create();
+
+        match client
+            .get_repository("tommilligan", "rate-limited")
+            .unwrap_err()
+        {
+            Error::RateLimited { reset_at, .. } => assert!(reset_at > chrono::Utc::now()),
+            _ => panic!("Unexpected error"),
+        }
+        mock.assert();
+        assert_eq!(
+            client.rate_limit(),
+            Some(RateLimit {
+                limit: 5000,
+                remaining: 0,
+                reset_at: chrono::Utc.timestamp(4_102_444_800, 0),
+            })
+        );
+
+        // Once we know the limit is exhausted, we should not send further requests
+        match client
+            .get_repository("tommilligan", "rate-limited")
+            .unwrap_err()
+        {
+            Error::RateLimited { .. } => (),
+            _ => panic!("Unexpected error"),
+        }
+        mock.assert();
+    }
+
+    #[test]
+    fn test_secondary_rate_limit_retried() {
+        let client = Client::new(&mockito::server_url(), MOCK_GITHUB_TOKEN).unwrap();
+        let mock_limited = mock("GET", "/repos/tommilligan/secondary-limit")
+            .with_status(403)
+            .with_header("retry-after", "0")
+            .with_body(r#"{"message": "You have exceeded a secondary rate limit."}"#)
+            .create();
+        let mock_ok = mock("GET", "/repos/tommilligan/secondary-limit")
+            .with_status(200)
+            .with_body(r#"{"id": 1, "name": "secondary-limit"}"#)
+            .create();
+
+        let repository = client
+            .get_repository("tommilligan", "secondary-limit")
+            .unwrap();
+        mock_limited.assert();
+        mock_ok.assert();
+        assert_eq!(repository.name, "secondary-limit");
+    }
+
+    #[test]
+    fn test_secondary_rate_limit_without_retry_after() {
+        let client = Client::new(&mockito::server_url(), MOCK_GITHUB_TOKEN)
+            .unwrap()
+            .with_max_rate_limit_wait(Duration::from_secs(0));
+        let mock = mock("GET", "/repos/tommilligan/secondary-limit-body")
+            .with_status(403)
+            .with_header("x-ratelimit-limit", "5000")
+            .with_header("x-ratelimit-remaining", "4999")
+            .with_header("x-ratelimit-reset", "4102444800")
+            .with_body(r#"{"message": "You have exceeded a secondary rate limit."}"#)
+            .create();
+
+        match client
+            .get_repository("tommilligan", "secondary-limit-body")
+            .unwrap_err()
+        {
+            Error::RateLimited { .. } => (),
+            error => panic!("Unexpected error {}", error),
+        }
+        mock.assert();
+    }
+
+    fn fast_retry_client() -> Client {
+        Client::new(&mockito::server_url(), MOCK_GITHUB_TOKEN)
+            .unwrap()
+            .with_retry_policy(RetryPolicy {
+                attempts: 3,
+                base_delay: Duration::from_millis(0),
+                jitter: Duration::from_millis(0),
+            })
+    }
+
+    #[test]
+    fn test_get_retried_on_server_error() {
+        let client = fast_retry_client();
+        let mock_error = mock("GET", "/repos/tommilligan/flaky")
+            .with_status(502)
+            .expect(2)
+            .create();
+        let mock_ok = mock("GET", "/repos/tommilligan/flaky")
+            .with_status(200)
+            .with_body(r#"{"id": 1, "name": "flaky"}"#)
+            .create();
+
+        let repository = client.get_repository("tommilligan", "flaky").unwrap();
+        mock_error.assert();
+        mock_ok.assert();
+        assert_eq!(repository.name, "flaky");
+    }
+
+    #[test]
+    fn test_get_retries_exhausted() {
+        let client = fast_retry_client();
+        let mock_error = mock("GET", "/repos/tommilligan/down")
+            .with_status(503)
+            .expect(3)
+            .create();
+
+        match client.get_repository("tommilligan", "down").unwrap_err() {
+            Error::Api { status, .. } => assert_eq!(status, 503),
+            _ => panic!("Unexpected error"),
+        }
+        mock_error.assert();
+    }
+
+    #[test]
+    fn test_post_not_retried() {
+        let client = fast_retry_client();
+        let mock_error = mock("POST", "/repos/tommilligan/flaky/milestones")
+            .with_status(502)
+            .expect(1)
+            .create();
+
+        assert!(client
+            .create_milestone("tommilligan", "flaky", &MilestoneUpdate::default())
+            .is_err());
+        mock_error.assert();
+    }
+
+    #[test]
+    fn test_patch_retried_with_opt_in() {
+        let client = fast_retry_client();
+        let mock_error = mock("PATCH", "/repos/tommilligan/flaky/milestones/1")
+            .with_status(502)
+            .create();
+        let mock_ok = mock("PATCH", "/repos/tommilligan/flaky/milestones/1")
+            .with_status(200)
+            .with_body(
+                r#"{
+  "id": 1,
+  "number": 1,
+  "state": "closed",
+  "title": "Mock Title",
+  "description": null,
+  "open_issues": 0,
+  "closed_issues": 3,
+  "created_at": "2011-04-10T20:09:31Z",
+  "due_on": null,
+  "closed_at": "2011-04-22T13:33:48Z",
+  "html_url": "http://foo.bar"
+}"#,
+            )
+            .create();
+
+        let update = MilestoneUpdate {
+            state: Some(State::Closed),
+            ..Default::default()
+        };
+        let milestone = client
+            .patch_milestone("tommilligan", "flaky", 1, &update)
+            .unwrap();
+        mock_error.assert();
+        mock_ok.assert();
+        assert_eq!(milestone.state, State::Closed);
+    }
+
+    #[test]
+    fn test_cached_get_revalidated() {
+        let client = Client::new(&mockito::server_url(), MOCK_GITHUB_TOKEN)
+            .unwrap()
+            .with_cache(temporary_cache("github-revalidated"));
+        let mock_fresh = mock("GET", "/repos/tommilligan/cached")
+            .match_header("if-none-match", mockito::Matcher::Missing)
+            .with_status(200)
+            .with_header("etag", r#""abc""#)
+            .with_body(r#"{"id": 1, "name": "cached"}"#)
+            .create();
+        let mock_not_modified = mock("GET", "/repos/tommilligan/cached")
+            .match_header("if-none-match", r#""abc""#)
+            .with_status(304)
+            .create();
+
+        let repository = client.get_repository("tommilligan", "cached").unwrap();
+        assert_eq!(repository.name, "cached");
+        mock_fresh.assert();
+
+        let repository = client.get_repository("tommilligan", "cached").unwrap();
+        assert_eq!(repository.name, "cached");
+        mock_not_modified.assert();
+    }
+
+    fn mock_app_client() -> Client {
+        mock_app_client_for(42)
+    }
+
+    fn mock_app_client_for(installation_id: u64) -> Client {
+        let credentials =
+            AppCredentials::new(1234, installation_id, &TEST_PRIVATE_KEY.to_owned().into())
+                .unwrap();
+        Client::new_app(&mockito::server_url(), credentials).unwrap()
+    }
+
+    #[test]
+    fn test_app_installation_token() {
+        let client = mock_app_client();
+        let mock_token = mock("POST", "/app/installations/42/access_tokens")
+            .match_header(
+                "authorization",
+                mockito::Matcher::Regex(r"^Bearer [\w-]+\.[\w-]+\.[\w-]+$".to_owned()),
+            )
+            .with_status(201)
+            .with_body(r#"{"token": "ghs_installation", "expires_at": "2099-01-01T00:00:00Z"}"#)
+            .expect(1)
+            .create();
+        let mock_repository = mock("GET", "/repos/tommilligan/app")
+            .match_header("authorization", "token ghs_installation")
+            .with_status(200)
+            .with_body(r#"{"id": 1, "name": "app"}"#)
+            .expect(2)
+            .create();
+
+        // The installation token should be reused while valid
+        client.get_repository("tommilligan", "app").unwrap();
+        client.get_repository("tommilligan", "app").unwrap();
+        mock_token.assert();
+        mock_repository.assert();
+    }
+
+    #[test]
+    fn test_app_installation_token_refreshed() {
+        let client = mock_app_client();
+        let mock_token = mock("POST", "/app/installations/42/access_tokens")
+            .with_status(201)
+            .with_body(r#"{"token": "ghs_expiring", "expires_at": "2000-01-01T00:00:00Z"}"#)
+            .expect(2)
+            .create();
+        let mock_repository = mock("GET", "/repos/tommilligan/app-refresh")
+            .match_header("authorization", "token ghs_expiring")
+            .with_status(200)
+            .with_body(r#"{"id": 1, "name": "app-refresh"}"#)
+            .expect(2)
+            .create();
+
+        // An expiring token should be replaced before each request
+        client.get_repository("tommilligan", "app-refresh").unwrap();
+        client.get_repository("tommilligan", "app-refresh").unwrap();
+        mock_token.assert();
+        mock_repository.assert();
+    }
+
+    #[test]
+    fn test_app_installation_token_retried() {
+        let client = mock_app_client_for(43).with_retry_policy(RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_millis(0),
+            jitter: Duration::from_millis(0),
+        });
+        let mock_error = mock("POST", "/app/installations/43/access_tokens")
+            .with_status(502)
+            .expect(1)
+            .create();
+        let mock_token = mock("POST", "/app/installations/43/access_tokens")
+            .with_status(201)
+            .with_body(r#"{"token": "ghs_retried", "expires_at": "2099-01-01T00:00:00Z"}"#)
+            .create();
+        let mock_repository = mock("GET", "/repos/tommilligan/app-retried")
+            .match_header("authorization", "token ghs_retried")
+            .with_status(200)
+            .with_body(r#"{"id": 1, "name": "app-retried"}"#)
+            .create();
+
+        client.get_repository("tommilligan", "app-retried").unwrap();
+        mock_error.assert();
+        mock_token.assert();
+        mock_repository.assert();
+    }
+
+    #[test]
+    fn test_app_installation_token_failed() {
+        let client = mock_app_client();
+        let mock_token = mock("POST", "/app/installations/42/access_tokens")
+            .with_status(401)
+            .with_body(r#"{"message": "A JSON web token could not be decoded"}"#)
+            .create();
+
+        match client
+            .get_repository("tommilligan", "app-failed")
+            .unwrap_err()
+        {
+            Error::GithubBadCredentials { method, path, .. } => {
+                assert_eq!(method, Method::POST);
+                assert_eq!(path, "/app/installations/42/access_tokens");
+            }
+            _ => panic!("Unexpected error"),
+        }
+        mock_token.assert();
+    }
+
+    fn mock_error_response(repo: &str, status: usize, body: &str) -> Error {
+        let mock = mock("GET", &*format!("/repos/tommilligan/{}", repo))
+            .with_status(status)
+            .with_header("x-accepted-oauth-scopes", "repo")
+            .with_header("x-oauth-scopes", "read:org, user")
+            .with_body(body)
+            .create();
+        let error = MOCK_GITHUB_CLIENT
+            .get_repository("tommilligan", repo)
+            .unwrap_err();
+        mock.assert();
+        error
+    }
+
+    #[test]
+    fn test_client_errors() {
+        match mock_error_response("unauthorized", 401, r#"{"message": "Bad credentials"}"#) {
+            Error::GithubBadCredentials {
+                method,
+                path,
+                error,
+            } => {
+                assert_eq!(method, Method::GET);
+                assert_eq!(path, "/repos/tommilligan/unauthorized");
+                assert_eq!(error.message, "Bad credentials");
+            }
+            error => panic!("Unexpected error {}", error),
+        }
+        match mock_error_response(
+            "forbidden",
+            403,
+            r#"{"message": "Resource not accessible"}"#,
+        ) {
+            Error::GithubMissingScope {
+                accepted_scopes, ..
+            } => assert_eq!(accepted_scopes, Some("repo".to_owned())),
+            error => panic!("Unexpected error {}", error),
+        }
+
+        // A token with an accepted scope was denied for some other reason
+        let mock_granted = mock("GET", "/repos/tommilligan/forbidden-granted")
+            .with_status(403)
+            .with_header("x-accepted-oauth-scopes", "public_repo, repo")
+            .with_header("x-oauth-scopes", "repo, user")
+            .with_body(r#"{"message": "Resource not accessible"}"#)
+            .create();
+        match MOCK_GITHUB_CLIENT
+            .get_repository("tommilligan", "forbidden-granted")
+            .unwrap_err()
+        {
+            Error::Github { status, .. } => assert_eq!(status, 403),
+            error => panic!("Unexpected error {}", error),
+        }
+        mock_granted.assert();
+        match mock_error_response(
+            "abuse",
+            403,
+            r#"{"message": "You have triggered an abuse detection mechanism."}"#,
+        ) {
+            Error::GithubAbuseDetected { .. } => (),
+            error => panic!("Unexpected error {}", error),
+        }
+        match mock_error_response("missing", 404, r#"{"message": "Not Found"}"#) {
+            Error::GithubNotFound { .. } => (),
+            error => panic!("Unexpected error {}", error),
+        }
+        let error = mock_error_response(
+            "invalid",
+            422,
+            r#"{"message": "Validation Failed", "errors": [{"resource": "Issue", "field": "title", "code": "missing_field"}, {"code": "custom", "message": "Too many labels"}]}"#,
+        );
+        assert_eq!(
+            error.to_string(),
+            "Github validation failed for GET /repos/tommilligan/invalid: Validation Failed (Issue title missing_field) (Too many labels)"
+        );
+        match mock_error_response("teapot", 418, "<html>I'm a teapot</html>\n") {
+            Error::Github { status, error, .. } => {
+                assert_eq!(status, 418);
+                assert_eq!(error.message, "<html>I'm a teapot</html>");
+            }
+            error => panic!("Unexpected error {}", error),
+        }
+    }
 }
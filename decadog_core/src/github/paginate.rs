@@ -1,52 +1,155 @@
+use std::marker::PhantomData;
 use std::vec::IntoIter;
 
-use log::debug;
-use reqwest::blocking::{Client as ReqwestClient, Request, Response};
+use reqwest::blocking::{Request, Response};
+use reqwest::Method;
 use serde::de::DeserializeOwned;
 use serde_derive::{Deserialize, Serialize};
 use url::Url;
 
+use log::{info, warn};
+
 use crate::error::Error;
 
+use super::link::Links;
 use super::request::ResponseExt;
+use super::Client;
+
+/// Maximum number of results Github returns for a single search.
+pub const SEARCH_RESULT_LIMIT: u64 = 1000;
 
 /// A single page from the Github search API.
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct GithubSearchResults<T> {
+    /// Total number of results matching the search, across all pages.
+    #[serde(default)]
+    pub total_count: Option<u64>,
     pub incomplete_results: bool,
     pub items: Vec<T>,
 }
 
-/// Represents a paginated search query over some collection of items `T`.
+/// The body of a single page of results from the Github API.
+pub trait Page<T> {
+    /// Total number of items across all pages, if known.
+    fn total_count(&self) -> Option<u64> {
+        None
+    }
+
+    /// Whether the API gave up before finding all matching items.
+    fn incomplete_results(&self) -> bool {
+        false
+    }
+
+    /// Consume the page, returning the items it contains.
+    fn into_items(self) -> Vec<T>;
+}
+
+/// List endpoints return a plain JSON array of items.
+impl<T> Page<T> for Vec<T> {
+    fn into_items(self) -> Vec<T> {
+        self
+    }
+}
+
+/// Search endpoints wrap items in an object with extra metadata.
+impl<T> Page<T> for GithubSearchResults<T> {
+    fn total_count(&self) -> Option<u64> {
+        self.total_count
+    }
+
+    fn incomplete_results(&self) -> bool {
+        self.incomplete_results
+    }
+
+    fn into_items(self) -> Vec<T> {
+        self.items
+    }
+}
+
+/// Represents a paginated query over some collection of items `T`, where each
+/// page of results is returned with body `P`.
 ///
-/// Used as an iterator, the `PaginatedSearch` will continue to fetch more results
-/// until no more are available.
-pub struct PaginatedSearch<'a, T>
+/// Used as an iterator, the `Paginated` query will continue to fetch more results
+/// until no more are available. Subsequent pages are found using the `Link` header.
+pub struct Paginated<'a, T, P>
 where
     Self: Sized,
     T: DeserializeOwned,
+    P: Page<T> + DeserializeOwned,
 {
-    client: &'a ReqwestClient,
+    client: &'a Client,
     page: IntoIter<T>,
-    next_page_url: Option<Url>,
+    page_url: Option<Url>,
+    links: Links,
+    page_number: u32,
+    total_count: Option<u64>,
+    incomplete_results: bool,
+    strict: bool,
+    truncation_warned: bool,
+    body: PhantomData<P>,
 }
 
-impl<'a, T> PaginatedSearch<'a, T>
+/// How far through a paginated query we are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    /// The page most recently fetched, starting from 1.
+    pub page: u32,
+    /// Total number of pages, if known.
+    pub total_pages: Option<u32>,
+    /// Total number of items across all pages, if known.
+    pub total_count: Option<u64>,
+}
+
+/// The number of the page a url refers to, from its `page` query parameter.
+pub fn page_number(url: &Url) -> Option<u32> {
+    url.query_pairs()
+        .find(|(key, _)| key == "page")
+        .and_then(|(_, value)| value.parse().ok())
+}
+
+/// Set the `page` query parameter of a url, preserving all other parameters.
+pub fn set_page_number(url: &mut Url, page: u32) {
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| key != "page")
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair("page", &page.to_string());
+}
+
+/// A paginated query over the Github search API.
+pub type PaginatedSearch<'a, T> = Paginated<'a, T, GithubSearchResults<T>>;
+
+/// A paginated query over a Github list endpoint, returning a JSON array.
+pub type PaginatedList<'a, T> = Paginated<'a, T, Vec<T>>;
+
+impl<'a, T, P> Paginated<'a, T, P>
 where
     Self: Sized,
     T: DeserializeOwned,
+    P: Page<T> + DeserializeOwned,
 {
-    /// Create a new paginated search, and load the first page.
-    pub fn new(client: &'a ReqwestClient, initial_request: Request) -> Result<Self, Error> {
+    /// Create a new paginated query, and load the first page.
+    pub fn new(client: &'a Client, initial_request: Request) -> Result<Self, Error> {
         // The initial request is a special case
-        debug!("{} {}", initial_request.method(), initial_request.url());
+        let page_number = page_number(initial_request.url()).unwrap_or(1);
         let response = client.execute(initial_request)?;
 
         // Apply our intial response to an empty struct
         let mut new_self = Self {
             client,
             page: vec![].into_iter(),
-            next_page_url: None,
+            page_url: None,
+            links: Links::default(),
+            page_number,
+            total_count: None,
+            incomplete_results: false,
+            strict: false,
+            truncation_warned: false,
+            body: PhantomData,
         };
         new_self.apply_response(response)?;
 
@@ -54,32 +157,125 @@ where
         Ok(new_self)
     }
 
-    /// Apply a response from the search API to update our state:
+    /// Create a new paginated query starting from page `page`, and load that page.
+    pub fn starting_at(
+        client: &'a Client,
+        initial_request: Request,
+        page: u32,
+    ) -> Result<Self, Error> {
+        let mut initial_request = initial_request;
+        set_page_number(initial_request.url_mut(), page);
+        Self::new(client, initial_request)
+    }
+
+    /// Apply a response from the API to update our state:
     /// - store the new items to iterate throught
-    /// - extract and store the url for the next page
+    /// - extract and store the links to other pages
     fn apply_response(&mut self, response: Response) -> Result<(), Error> {
-        self.next_page_url = response.next_page_url()?;
-        self.page = response
-            .into_github::<GithubSearchResults<T>>()?
-            .items
-            .into_iter();
+        self.links = response.links()?;
+        self.page_url = Some(response.url().clone());
+        let url = response.url().clone();
+        let body = response.into_github::<P>(&Method::GET)?;
+        if let Some(total_count) = body.total_count() {
+            self.total_count = Some(total_count);
+        }
+        if body.incomplete_results() {
+            if self.strict {
+                return Err(Error::IncompleteResults {
+                    description: format!("Github timed out searching {}", url),
+                });
+            }
+            warn!("Github returned incomplete results for {}", url);
+            self.incomplete_results = true;
+        }
+        self.page = body.into_items().into_iter();
+
+        let progress = self.progress();
+        match progress.total_pages {
+            Some(total_pages) => info!("Fetched page {} of {}", progress.page, total_pages),
+            None => info!("Fetched page {}", progress.page),
+        }
         Ok(())
     }
 
-    /// Fetch the next page, and apply the response to our state.
+    /// Fetch a page, and apply the response to our state.
     fn update_page(&mut self, url: Url) -> Result<(), Error> {
-        debug!("GET {}", &url);
-        let request = self.client.get(url).build()?;
+        let page_number = page_number(&url).unwrap_or(self.page_number + 1);
+        let request = self.client.request(Method::GET, url).build()?;
         let response = self.client.execute(request)?;
+        self.page_number = page_number;
         self.apply_response(response)?;
         Ok(())
     }
+
+    /// Fail with an error if any page of results is incomplete, rather than warning.
+    pub fn strict(mut self) -> Result<Self, Error> {
+        if self.incomplete_results {
+            return Err(Error::IncompleteResults {
+                description: "Github timed out searching".to_owned(),
+            });
+        }
+        self.strict = true;
+        Ok(self)
+    }
+
+    /// Whether any page of results fetched so far was incomplete.
+    pub fn incomplete_results(&self) -> bool {
+        self.incomplete_results
+    }
+
+    /// Warn if the query matched more items than Github will return, once all are fetched.
+    fn warn_if_truncated(&mut self) {
+        match self.total_count {
+            Some(total_count) if total_count > SEARCH_RESULT_LIMIT && !self.truncation_warned => {
+                warn!(
+                    "Search matched {} results, but Github only returns the first {}",
+                    total_count, SEARCH_RESULT_LIMIT
+                );
+                self.truncation_warned = true;
+            }
+            _ => (),
+        }
+    }
+
+    /// Our progress through the query, based on the page most recently fetched.
+    pub fn progress(&self) -> Progress {
+        let total_pages = match self.links.last() {
+            Some(last) => page_number(last),
+            // Without a next page, this must be the last page
+            None if self.links.next().is_none() => Some(self.page_number),
+            None => None,
+        };
+        Progress {
+            page: self.page_number,
+            total_pages,
+            total_count: self.total_count,
+        }
+    }
+
+    /// Discard any remaining items on the current page, and fetch page `page` instead.
+    ///
+    /// Iteration continues from the start of the new page.
+    pub fn jump_to(&mut self, page: u32) -> Result<(), Error> {
+        let mut url = self
+            .links
+            .first()
+            .or(self.links.last())
+            .or(self.page_url.as_ref())
+            .cloned()
+            .ok_or_else(|| Error::Unknown {
+                description: "No url to jump to another page from.".to_owned(),
+            })?;
+        set_page_number(&mut url, page);
+        self.update_page(url)
+    }
 }
 
-impl<'a, T> Iterator for PaginatedSearch<'a, T>
+impl<'a, T, P> Iterator for Paginated<'a, T, P>
 where
     Self: Sized,
     T: DeserializeOwned,
+    P: Page<T> + DeserializeOwned,
 {
     type Item = Result<T, Error>;
 
@@ -88,8 +284,11 @@ where
             // if we still have the current page, iterate it
             Some(item) => Some(Ok(item)),
             // otherwise, get another page
-            None => match self.next_page_url.clone() {
-                None => None,
+            None => match self.links.next().cloned() {
+                None => {
+                    self.warn_if_truncated();
+                    None
+                }
                 Some(url) => match self.update_page(url) {
                     Err(e) => Some(Err(e)),
                     Ok(_) => self.page.next().map(Ok),
@@ -105,6 +304,7 @@ pub mod tests {
     use pretty_assertions::assert_eq;
     use serde_derive::{Deserialize, Serialize};
 
+    use super::super::tests::MOCK_GITHUB_CLIENT;
     use super::*;
 
     #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -118,9 +318,12 @@ pub mod tests {
         let page_two_path = "/url-for-page-two";
 
         // First page should be fetched immediately
-        let client = ReqwestClient::new();
+        let client = &*MOCK_GITHUB_CLIENT;
         let initial_request = client
-            .get(&format!("{}{}", &mockito::server_url(), &page_one_path))
+            .request(
+                Method::GET,
+                Url::parse(&format!("{}{}", &mockito::server_url(), &page_one_path)).unwrap(),
+            )
             .build()
             .unwrap();
         let mock_page_one = mock("GET", page_one_path)
@@ -149,7 +352,7 @@ pub mod tests {
             .create();
 
         let mut paginated_items =
-            PaginatedSearch::<TestItem>::new(&client, initial_request).unwrap();
+            PaginatedSearch::<TestItem>::new(client, initial_request).unwrap();
 
         mock_page_one.assert();
         assert_eq!(
@@ -183,4 +386,123 @@ pub mod tests {
         // As the last page didn't have a link, the next issue should be None
         assert!(paginated_items.next().is_none());
     }
+
+    #[test]
+    fn test_paginated_list() {
+        let page_one_path = "/url-for-list-page-one";
+        let page_two_path = "/url-for-list-page-two";
+
+        let client = &*MOCK_GITHUB_CLIENT;
+        let initial_request = client
+            .request(
+                Method::GET,
+                Url::parse(&format!("{}{}", &mockito::server_url(), &page_one_path)).unwrap(),
+            )
+            .build()
+            .unwrap();
+        let mock_page_one = mock("GET", page_one_path)
+            .with_header(
+                "link",
+                &format!(
+                    r#"<{}{}>; rel="next", <{}{}>; rel="last""#,
+                    &mockito::server_url(),
+                    &page_two_path,
+                    &mockito::server_url(),
+                    &page_two_path
+                ),
+            )
+            .with_body(r#"[{"data": 0}]"#)
+            .create();
+        let mock_page_two = mock("GET", page_two_path)
+            .with_body(r#"[{"data": 1}]"#)
+            .create();
+
+        let items = PaginatedList::<TestItem>::new(client, initial_request)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        mock_page_one.assert();
+        mock_page_two.assert();
+        assert_eq!(items, vec![TestItem { data: 0 }, TestItem { data: 1 }]);
+    }
+
+    #[test]
+    fn test_paginated_progress_and_jump() {
+        let path = "/url-for-progress";
+        let page_url = |page: u32| format!("{}{}?q=x&page={}", mockito::server_url(), path, page);
+
+        let client = &*MOCK_GITHUB_CLIENT;
+        let initial_request = client
+            .request(
+                Method::GET,
+                Url::parse(&format!("{}{}?q=x", mockito::server_url(), path)).unwrap(),
+            )
+            .build()
+            .unwrap();
+        let mock_page_two = mock("GET", "/url-for-progress?q=x&page=2")
+            .with_header(
+                "link",
+                &format!(
+                    r#"<{}>; rel="prev", <{}>; rel="next", <{}>; rel="last", <{}>; rel="first""#,
+                    page_url(1),
+                    page_url(3),
+                    page_url(5),
+                    page_url(1)
+                ),
+            )
+            .with_body(r#"{"total_count": 9, "incomplete_results": false, "items": [{"data": 2}]}"#)
+            .create();
+
+        let mut paginated_items =
+            PaginatedSearch::<TestItem>::starting_at(client, initial_request, 2).unwrap();
+        mock_page_two.assert();
+        assert_eq!(
+            paginated_items.progress(),
+            Progress {
+                page: 2,
+                total_pages: Some(5),
+                total_count: Some(9),
+            }
+        );
+
+        let mock_page_five = mock("GET", "/url-for-progress?q=x&page=5")
+            .with_header(
+                "link",
+                &format!(
+                    r#"<{}>; rel="prev", <{}>; rel="first""#,
+                    page_url(4),
+                    page_url(1)
+                ),
+            )
+            .with_body(r#"{"total_count": 9, "incomplete_results": false, "items": [{"data": 5}]}"#)
+            .create();
+        paginated_items.jump_to(5).unwrap();
+        mock_page_five.assert();
+        assert_eq!(
+            paginated_items.progress(),
+            Progress {
+                page: 5,
+                total_pages: Some(5),
+                total_count: Some(9),
+            }
+        );
+        assert_eq!(
+            paginated_items.collect::<Result<Vec<_>, _>>().unwrap(),
+            vec![TestItem { data: 5 }]
+        );
+    }
+
+    #[test]
+    fn test_set_page_number() {
+        let mut url =
+            Url::parse("https://api.github.com/search/issues?q=a+b&page=2&per_page=10").unwrap();
+        assert_eq!(page_number(&url), Some(2));
+        set_page_number(&mut url, 7);
+        assert_eq!(page_number(&url), Some(7));
+        assert_eq!(
+            url.as_str(),
+            "https://api.github.com/search/issues?q=a+b&per_page=10&page=7"
+        );
+    }
 }
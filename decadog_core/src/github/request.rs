@@ -1,80 +1,231 @@
-use lazy_static::lazy_static;
-use regex::Regex;
-use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::blocking::{Request, RequestBuilder as ReqwestRequestBuilder, Response};
 use reqwest::header::LINK;
+use reqwest::{Method, StatusCode};
 use serde::de::DeserializeOwned;
-use url::Url;
+use serde::Serialize;
 
-use crate::error::Error;
+use super::link::Links;
+use super::{Client, GithubClientErrorBody};
+use crate::error::{error_body, Error};
 
-lazy_static! {
-    static ref RX_LINK_NEXT: Regex =
-        Regex::new(r#"<(?P<url>[^>]+)>;[^,]* rel="next""#).expect("Invalid link regex.");
-}
+/// Header listing the scopes that would have allowed a request.
+const HEADER_ACCEPTED_OAUTH_SCOPES: &str = "x-accepted-oauth-scopes";
+/// Header listing the scopes the token has.
+const HEADER_OAUTH_SCOPES: &str = "x-oauth-scopes";
 
 /// Interpret a response with potential JSON errors from the Github API.
 pub trait ResponseExt {
-    fn into_github<T>(self) -> Result<T, Error>
+    /// Deserialize a successful response, or return an error describing the failure.
+    ///
+    /// `method` is that of the request, used to describe any error.
+    fn into_github<T>(self, method: &Method) -> Result<T, Error>
     where
         Self: Sized,
         T: DeserializeOwned;
 
-    fn next_page_url(&self) -> Result<Option<Url>, Error>;
+    /// Links to related resources, such as other pages of results.
+    fn links(&self) -> Result<Links, Error>;
+}
+
+/// Whether a response indicates Github's abuse detection or secondary rate limit was triggered.
+fn is_abuse_detected(status: StatusCode, error: &GithubClientErrorBody) -> bool {
+    let message = error.message.to_lowercase();
+    status == StatusCode::TOO_MANY_REQUESTS
+        || message.contains("abuse")
+        || message.contains("secondary rate limit")
+}
+
+/// Whether the token has none of the scopes that would have allowed a request.
+///
+/// Any one accepted scope is enough. If either header is missing, as for tokens without
+/// OAuth scopes, scopes are not known to be the cause.
+fn is_missing_scope(accepted_scopes: Option<&str>, scopes: Option<&str>) -> bool {
+    let split = |scopes: &str| -> Vec<String> {
+        scopes
+            .split(',')
+            .map(|scope| scope.trim().to_owned())
+            .filter(|scope| !scope.is_empty())
+            .collect()
+    };
+    match (accepted_scopes.map(split), scopes.map(split)) {
+        (Some(accepted_scopes), Some(scopes)) => {
+            !accepted_scopes.is_empty()
+                && !accepted_scopes
+                    .iter()
+                    .any(|accepted_scope| scopes.contains(accepted_scope))
+        }
+        _ => false,
+    }
+}
+
+/// Classify a client error response from Github.
+fn client_error(method: &Method, response: Response) -> Result<Error, Error> {
+    let method = method.clone();
+    let path = response.url().path().to_owned();
+    let status = response.status();
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_owned())
+    };
+    let accepted_scopes = header(HEADER_ACCEPTED_OAUTH_SCOPES);
+    let missing_scope = is_missing_scope(
+        accepted_scopes.as_deref(),
+        header(HEADER_OAUTH_SCOPES).as_deref(),
+    );
+
+    let error = Box::new(
+        error_body(response)?.unwrap_or_else(|message| GithubClientErrorBody {
+            message,
+            errors: None,
+            documentation_url: None,
+        }),
+    );
+
+    Ok(match status {
+        StatusCode::UNAUTHORIZED => Error::GithubBadCredentials {
+            method,
+            path,
+            error,
+        },
+        StatusCode::NOT_FOUND => Error::GithubNotFound {
+            method,
+            path,
+            error,
+        },
+        StatusCode::UNPROCESSABLE_ENTITY => Error::GithubValidationFailed {
+            method,
+            path,
+            error,
+        },
+        status if is_abuse_detected(status, &error) => Error::GithubAbuseDetected {
+            method,
+            path,
+            error,
+        },
+        StatusCode::FORBIDDEN if missing_scope => Error::GithubMissingScope {
+            method,
+            path,
+            accepted_scopes,
+            error,
+        },
+        status => Error::Github {
+            method,
+            path,
+            error,
+            status,
+        },
+    })
+}
+
+/// Return a successful response, or an error describing the failure.
+fn check_status(method: &Method, response: Response) -> Result<Response, Error> {
+    let status = response.status();
+    if status.is_success() {
+        Ok(response)
+    } else if status.is_client_error() {
+        Err(client_error(method, response)?)
+    } else {
+        Err(Error::Api {
+            description: "Unexpected response status code.".to_owned(),
+            status,
+        })
+    }
 }
 
 impl ResponseExt for Response {
-    fn into_github<T>(self) -> Result<T, Error>
+    fn into_github<T>(self, method: &Method) -> Result<T, Error>
     where
         Self: Sized,
         T: DeserializeOwned,
     {
-        let status = self.status();
-        if status.is_success() {
-            Ok(self.json()?)
-        } else if status.is_client_error() {
-            Err(Error::Github {
-                error: self.json()?,
-                status,
-            })
-        } else {
-            Err(Error::Api {
-                description: "Unexpected response status code.".to_owned(),
-                status,
-            })
-        }
+        Ok(check_status(method, self)?.json()?)
     }
 
-    fn next_page_url(&self) -> Result<Option<Url>, Error> {
-        // TODO(tommilligan) Find an actual Link header parsing implementation
+    fn links(&self) -> Result<Links, Error> {
         match self.headers().get(LINK) {
-            None => Ok(None),
-            Some(header_value) => {
-                match RX_LINK_NEXT.captures(header_value.to_str().map_err(|_| Error::Unknown {
+            None => Ok(Links::default()),
+            Some(header_value) => Links::parse(
+                header_value.to_str().map_err(|_| Error::Unknown {
                     description: "Expected Github Link header to be valid.".to_owned(),
-                })?) {
-                    None => Ok(None),
-                    Some(captured_groups) => Ok(Some(Url::parse(&captured_groups["url"])?)),
-                }
-            }
+                })?,
+                self.url(),
+            ),
         }
     }
 }
 
-/// Send a HTTP request to Github, and return the resulting struct.
-pub trait RequestBuilderExt {
-    fn send_github<T>(self) -> Result<T, Error>
-    where
-        Self: Sized,
-        T: DeserializeOwned;
+/// A request to the Github API, which will be sent by the `Client` that built it.
+///
+/// This ensures every request respects the state shared by the client, such as rate limits.
+pub struct RequestBuilder<'a> {
+    client: &'a Client,
+    builder: ReqwestRequestBuilder,
+    retry_unsafe: bool,
 }
 
-impl RequestBuilderExt for RequestBuilder {
-    fn send_github<T>(self) -> Result<T, Error>
+impl<'a> RequestBuilder<'a> {
+    pub fn new(client: &'a Client, builder: ReqwestRequestBuilder) -> Self {
+        Self {
+            client,
+            builder,
+            retry_unsafe: false,
+        }
+    }
+
+    /// Retry this request on transient failure, even if the method is not idempotent.
+    ///
+    /// Only use this where sending the request twice has the same effect as sending it once.
+    pub fn retry(self) -> Self {
+        Self {
+            retry_unsafe: true,
+            ..self
+        }
+    }
+
+    /// Modify the query string of the URL.
+    pub fn query<T: Serialize + ?Sized>(self, query: &T) -> Self {
+        Self {
+            builder: self.builder.query(query),
+            ..self
+        }
+    }
+
+    /// Send a JSON body.
+    pub fn json<T: Serialize + ?Sized>(self, json: &T) -> Self {
+        Self {
+            builder: self.builder.json(json),
+            ..self
+        }
+    }
+
+    /// Build a `Request`, which can be executed by the client.
+    pub fn build(self) -> Result<Request, Error> {
+        Ok(self.builder.build()?)
+    }
+
+    /// Send a HTTP request to Github, and return the resulting struct.
+    pub fn send_github<T>(self) -> Result<T, Error>
     where
-        Self: Sized,
         T: DeserializeOwned,
     {
-        let response = self.send()?;
-        response.into_github()
+        let client = self.client;
+        let retry_unsafe = self.retry_unsafe;
+        let request = self.build()?;
+        let method = request.method().clone();
+        let response = client.execute_retrying(request, retry_unsafe)?;
+        response.into_github(&method)
+    }
+
+    /// Send a HTTP request to Github, ignoring any response body.
+    pub fn send_github_no_response(self) -> Result<(), Error> {
+        let client = self.client;
+        let retry_unsafe = self.retry_unsafe;
+        let request = self.build()?;
+        let method = request.method().clone();
+        check_status(&method, client.execute_retrying(request, retry_unsafe)?)?;
+        Ok(())
     }
 }
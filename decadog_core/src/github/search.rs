@@ -0,0 +1,423 @@
+/// Building queries for the Github search API.
+use std::borrow::Cow;
+use std::fmt;
+
+use chrono::{DateTime, NaiveDate, TimeZone};
+
+use super::{Direction, State};
+
+/// Quote a qualifier value if required, so that it is read as a single value.
+///
+/// Quotes and backslashes within quoted values are escaped with a backslash.
+pub fn quote(value: &str) -> Cow<'_, str> {
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || "\"\\:()".contains(c));
+    if !needs_quotes {
+        return Cow::Borrowed(value);
+    }
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    Cow::Owned(quoted)
+}
+
+/// An inclusive range of dates, either bound of which may be open.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DateRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    pub fn between(from: NaiveDate, to: NaiveDate) -> Self {
+        Self {
+            from: Some(from),
+            to: Some(to),
+        }
+    }
+
+    pub fn since(from: NaiveDate) -> Self {
+        Self {
+            from: Some(from),
+            to: None,
+        }
+    }
+
+    pub fn until(to: NaiveDate) -> Self {
+        Self {
+            from: None,
+            to: Some(to),
+        }
+    }
+}
+
+impl fmt::Display for DateRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.from, self.to) {
+            (Some(from), Some(to)) => write!(f, "{}..{}", from, to),
+            (Some(from), None) => write!(f, ">={}", from),
+            (None, Some(to)) => write!(f, "<={}", to),
+            (None, None) => write!(f, "*..*"),
+        }
+    }
+}
+
+/// A date an issue can be searched by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateQualifier {
+    Created,
+    Updated,
+    Closed,
+}
+
+impl DateQualifier {
+    fn key(self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Updated => "updated",
+            Self::Closed => "closed",
+        }
+    }
+}
+
+/// Builds a query string for the Github search API.
+///
+/// Qualifier values are quoted as required. Any qualifier or term can be negated by
+/// calling `not` before it.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SearchQueryBuilder {
+    query: String,
+    negate_next: bool,
+}
+
+impl SearchQueryBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn build(&self) -> &str {
+        &self.query
+    }
+
+    /// Separate the next part of the query, negating it if `not` was called.
+    fn push_separator(&mut self) {
+        if !self.query.is_empty() {
+            self.query.push(' ');
+        };
+        if self.negate_next {
+            self.query.push('-');
+            self.negate_next = false;
+        }
+    }
+
+    /// Add free text to the query, as given.
+    pub fn term(&mut self, term: &str) -> &mut Self {
+        self.push_separator();
+        self.query.push_str(term);
+        self
+    }
+
+    /// Negate the next qualifier or term added.
+    pub fn not(&mut self) -> &mut Self {
+        self.negate_next = true;
+        self
+    }
+
+    /// Add a qualifier, quoting the value if required.
+    pub fn key_value(&mut self, key: &str, value: &str) -> &mut Self {
+        self.qualifier(key, &quote(value))
+    }
+
+    /// Add a qualifier with a value that must not be quoted, such as a range.
+    fn qualifier(&mut self, key: &str, value: &str) -> &mut Self {
+        self.push_separator();
+        self.query.push_str(key);
+        self.query.push(':');
+        self.query.push_str(value);
+        self
+    }
+
+    pub fn label(&mut self, label_name: &str) -> &mut Self {
+        self.key_value("label", label_name)
+    }
+
+    pub fn not_label(&mut self, label_name: &str) -> &mut Self {
+        self.not().label(label_name)
+    }
+
+    pub fn no_label(&mut self) -> &mut Self {
+        self.key_value("no", "label")
+    }
+
+    pub fn assignee(&mut self, login: &str) -> &mut Self {
+        self.key_value("assignee", login)
+    }
+
+    pub fn no_assignee(&mut self) -> &mut Self {
+        self.key_value("no", "assignee")
+    }
+
+    pub fn author(&mut self, login: &str) -> &mut Self {
+        self.key_value("author", login)
+    }
+
+    pub fn mentions(&mut self, login: &str) -> &mut Self {
+        self.key_value("mentions", login)
+    }
+
+    pub fn issue(&mut self) -> &mut Self {
+        self.key_value("type", "issue")
+    }
+
+    pub fn is_issue(&mut self) -> &mut Self {
+        self.key_value("is", "issue")
+    }
+
+    pub fn is_pull_request(&mut self) -> &mut Self {
+        self.key_value("is", "pr")
+    }
+
+    /// Only match free text terms against issue titles.
+    pub fn in_title(&mut self) -> &mut Self {
+        self.key_value("in", "title")
+    }
+
+    pub fn state(&mut self, state: &State) -> &mut Self {
+        self.key_value(
+            "state",
+            &serde_plain::to_string(state).expect("Serializing state to string failed"),
+        )
+    }
+
+    pub fn milestone(&mut self, milestone_title: &str) -> &mut Self {
+        self.key_value("milestone", milestone_title)
+    }
+
+    pub fn no_milestone(&mut self) -> &mut Self {
+        self.key_value("no", "milestone")
+    }
+
+    /// Restrict the date given by `qualifier` to `range`.
+    pub fn date(&mut self, qualifier: DateQualifier, range: &DateRange) -> &mut Self {
+        self.qualifier(qualifier.key(), &range.to_string())
+    }
+
+    pub fn created(&mut self, range: &DateRange) -> &mut Self {
+        self.date(DateQualifier::Created, range)
+    }
+
+    pub fn updated(&mut self, range: &DateRange) -> &mut Self {
+        self.date(DateQualifier::Updated, range)
+    }
+
+    pub fn closed(&mut self, range: &DateRange) -> &mut Self {
+        self.date(DateQualifier::Closed, range)
+    }
+
+    pub fn closed_on_or_after<Tz: TimeZone>(&mut self, datetime: &DateTime<Tz>) -> &mut Self {
+        self.state(&State::Closed)
+            .closed(&DateRange::since(datetime.naive_local().date()))
+    }
+
+    /// Sort results by `field`, such as `created` or `updated`.
+    pub fn sort(&mut self, field: &str, direction: &Direction) -> &mut Self {
+        let direction =
+            serde_plain::to_string(direction).expect("Serializing direction to string failed");
+        self.qualifier("sort", &format!("{}-{}", field, direction))
+    }
+
+    pub fn owner_repo(&mut self, owner: &str, repo: &str) -> &mut Self {
+        self.key_value("repo", &format!("{}/{}", owner, repo))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, NaiveDate};
+    use pretty_assertions::assert_eq;
+    use quickcheck::quickcheck;
+
+    use super::*;
+
+    /// A qualifier parsed back out of a query string.
+    #[derive(Debug, Clone, PartialEq)]
+    struct Qualifier {
+        negated: bool,
+        key: String,
+        value: String,
+    }
+
+    /// Parse a query made up only of qualifiers, following Github's quoting rules.
+    fn parse(query: &str) -> Vec<Qualifier> {
+        let mut qualifiers = Vec::new();
+        let mut chars = query.chars().peekable();
+        loop {
+            while let Some(c) = chars.peek() {
+                if !c.is_whitespace() {
+                    break;
+                }
+                chars.next();
+            }
+            if chars.peek().is_none() {
+                return qualifiers;
+            }
+
+            let negated = chars.peek() == Some(&'-');
+            if negated {
+                chars.next();
+            }
+            let key: String = chars.by_ref().take_while(|c| *c != ':').collect();
+            let mut value = String::new();
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => value.extend(chars.next()),
+                        c => value.push(c),
+                    }
+                }
+            } else {
+                while let Some(c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    value.extend(chars.next());
+                }
+            }
+            qualifiers.push(Qualifier {
+                negated,
+                key,
+                value,
+            });
+        }
+    }
+
+    #[test]
+    fn search_query_builder() {
+        assert_eq!(SearchQueryBuilder::new().build(), "");
+        assert_eq!(
+            SearchQueryBuilder::new().state(&State::Open).build(),
+            "state:open"
+        );
+        assert_eq!(
+            SearchQueryBuilder::new().issue().label("spam").build(),
+            "type:issue label:spam"
+        );
+        assert_eq!(
+            SearchQueryBuilder::new()
+                .milestone("Sprint 2")
+                .not_label("spam")
+                .build(),
+            r#"milestone:"Sprint 2" -label:spam"#
+        );
+        assert_eq!(
+            SearchQueryBuilder::new()
+                .term("arbitrary")
+                .key_value("k", "v")
+                .build(),
+            "arbitrary k:v"
+        );
+        assert_eq!(
+            SearchQueryBuilder::new()
+                .closed_on_or_after(
+                    &FixedOffset::east(0)
+                        .from_utc_datetime(&NaiveDate::from_ymd(2011, 4, 22).and_hms(13, 33, 48)),
+                )
+                .owner_repo("ow", "re")
+                .build(),
+            "state:closed closed:>=2011-04-22 repo:ow/re"
+        );
+    }
+
+    #[test]
+    fn search_query_builder_qualifiers() {
+        assert_eq!(
+            SearchQueryBuilder::new()
+                .is_pull_request()
+                .assignee("octocat")
+                .not()
+                .author("tommilligan")
+                .mentions("someone")
+                .build(),
+            "is:pr assignee:octocat -author:tommilligan mentions:someone"
+        );
+        assert_eq!(
+            SearchQueryBuilder::new()
+                .is_issue()
+                .no_assignee()
+                .not()
+                .no_label()
+                .term("crash")
+                .in_title()
+                .build(),
+            "is:issue no:assignee -no:label crash in:title"
+        );
+        assert_eq!(
+            SearchQueryBuilder::new()
+                .not()
+                .term("crash")
+                .label("bug")
+                .build(),
+            "-crash label:bug"
+        );
+        assert_eq!(
+            SearchQueryBuilder::new()
+                .created(&DateRange::between(
+                    NaiveDate::from_ymd(2020, 1, 1),
+                    NaiveDate::from_ymd(2020, 1, 31)
+                ))
+                .not()
+                .updated(&DateRange::until(NaiveDate::from_ymd(2020, 2, 1)))
+                .closed(&DateRange::default())
+                .sort("updated", &Direction::Ascending)
+                .build(),
+            "created:2020-01-01..2020-01-31 -updated:<=2020-02-01 closed:*..* sort:updated-asc"
+        );
+    }
+
+    #[test]
+    fn search_query_builder_quoting() {
+        assert_eq!(
+            SearchQueryBuilder::new()
+                .label("good first issue")
+                .label("")
+                .label(r#"say "hi""#)
+                .label(r"back\slash")
+                .label("type:bug")
+                .build(),
+            r#"label:"good first issue" label:"" label:"say \"hi\"" label:"back\\slash" label:"type:bug""#
+        );
+    }
+
+    quickcheck! {
+        fn qualifiers_round_trip(qualifiers: Vec<(bool, u8, String)>) -> bool {
+            const KEYS: &[&str] = &["label", "assignee", "author", "mentions", "milestone"];
+            let expected: Vec<Qualifier> = qualifiers
+                .into_iter()
+                .map(|(negated, key, value)| Qualifier {
+                    negated,
+                    key: KEYS[key as usize % KEYS.len()].to_owned(),
+                    value,
+                })
+                .collect();
+
+            let mut builder = SearchQueryBuilder::new();
+            for qualifier in expected.iter() {
+                if qualifier.negated {
+                    builder.not();
+                }
+                builder.key_value(&qualifier.key, &qualifier.value);
+            }
+            parse(builder.build()) == expected
+        }
+    }
+}
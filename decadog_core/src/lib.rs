@@ -1,31 +1,83 @@
 #![deny(clippy::all)]
 
+use std::cmp::Reverse;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::hash::Hasher;
+use std::sync::Mutex;
 
-use chrono::{DateTime, FixedOffset};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, Utc};
+use log::{debug, warn};
 
+pub mod base_url;
+pub mod cache;
 mod core;
 pub mod error;
+pub mod flow;
 pub mod github;
+pub mod rate_limit;
+pub mod release;
+pub mod retry;
 pub mod secret;
+pub mod store;
+pub mod webhook;
 pub mod zenhub;
 
-pub use crate::core::{AssignedTo, Sprint};
+use crate::core::reorder_moves;
+pub use crate::core::{
+    AssignedTo, BoardEntry, BoardIndex, Dependencies, EpicStatus, MemberSource, MilestoneSet,
+    PipelineOrder, Placement, Repositories, Sprint, SprintBackend,
+};
 pub use error::Error;
+use flow::IssueFlow;
 use github::{
-    paginate::PaginatedSearch, Direction, Issue, IssueUpdate, Milestone, MilestoneUpdate,
-    OrganisationMember, Repository, SearchIssues, SearchQueryBuilder, State,
+    paginate::{PaginatedSearch, SEARCH_RESULT_LIMIT},
+    DateQualifier, DateRange, Direction, Issue, IssueUpdate, Milestone, MilestoneUpdate,
+    OrganisationMember, Repository, SearchIssues, SearchQueryBuilder, SearchState, State,
+};
+use release::ReleaseProgress;
+use store::Store;
+use zenhub::{
+    Board, CreateRelease, IssueReference, NativeSprint, Pipeline, PipelinePosition, Position,
+    Release, SprintState, StartDate, UpdateReleaseIssues, Workspace,
 };
-use zenhub::{Board, Pipeline, PipelinePosition, StartDate, Workspace};
+
+/// Year before which no issues exist, used as the start of unbounded date searches.
+const SEARCH_EPOCH_YEAR: i32 = 2008;
+
+/// Number of concurrent requests made by batch operations, by default.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// List workspaces for error messages.
+pub fn describe_workspaces(workspaces: &[Workspace]) -> String {
+    if workspaces.is_empty() {
+        return "none".to_owned();
+    }
+    workspaces
+        .iter()
+        .map(|workspace| workspace.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Split a repository full name into owner and name.
+fn split_full_name(full_name: &str) -> (&str, &str) {
+    let mut parts = full_name.splitn(2, '/');
+    let owner = parts.next().unwrap_or_default();
+    (owner, parts.next().unwrap_or_default())
+}
 
 /// Decadog client, used to abstract complex tasks over several APIs.
 pub struct Client<'a> {
     owner: &'a str,
     repo: &'a str,
+    /// Owner and name of every repository worked with, starting with the primary.
+    repositories: Vec<(&'a str, &'a str)>,
     github: &'a github::Client,
     zenhub: &'a zenhub::Client,
+    concurrency: usize,
+    store: Option<&'a Store>,
 
     id: u64,
 }
@@ -55,11 +107,58 @@ impl<'a> Client<'a> {
             id,
             owner,
             repo,
+            repositories: vec![(owner, repo)],
             github,
             zenhub,
+            concurrency: DEFAULT_CONCURRENCY,
+            store: None,
         })
     }
 
+    /// Also work with other repositories, given as `owner/repo`, or `repo` for
+    /// repositories with the same owner as the primary repository.
+    pub fn with_repositories(mut self, repositories: &'a [String]) -> Result<Self, Error> {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&self.id.to_be_bytes());
+        for repository in repositories.iter() {
+            let (owner, repo) = match repository.find('/') {
+                Some(index) => (&repository[..index], &repository[index + 1..]),
+                None => (self.owner, repository.as_ref()),
+            };
+            if owner.is_empty() || repo.is_empty() || repo.contains('/') {
+                return Err(Error::Config {
+                    description: format!("Invalid repository {}", repository),
+                });
+            }
+            if !self
+                .repositories
+                .iter()
+                .any(|(existing_owner, existing_repo)| {
+                    existing_owner.eq_ignore_ascii_case(owner)
+                        && existing_repo.eq_ignore_ascii_case(repo)
+                })
+            {
+                hasher.write(owner.as_bytes());
+                hasher.write(repo.as_bytes());
+                self.repositories.push((owner, repo));
+            }
+        }
+        self.id = hasher.finish();
+        Ok(self)
+    }
+
+    /// Set the maximum number of concurrent requests made by batch operations.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Read issues from a local store kept up to date by webhooks, before the API.
+    pub fn with_store(mut self, store: &'a Store) -> Self {
+        self.store = Some(store);
+        self
+    }
+
     pub fn owner(&self) -> &str {
         self.owner
     }
@@ -68,6 +167,16 @@ impl<'a> Client<'a> {
         self.repo
     }
 
+    /// Owner and name of every repository worked with, starting with the primary.
+    pub fn owner_repos(&self) -> &[(&'a str, &'a str)] {
+        &self.repositories
+    }
+
+    /// Owner and name of the repository an issue belongs to, defaulting to the primary.
+    fn issue_owner_repo<'i>(&'i self, issue: &'i Issue) -> (&'i str, &'i str) {
+        issue.owner_repo().unwrap_or((self.owner, self.repo))
+    }
+
     /// Get Zenhub StartDate for a Github Milestone.
     pub fn get_start_date(
         &self,
@@ -77,9 +186,28 @@ impl<'a> Client<'a> {
         self.zenhub.get_start_date(repository.id, milestone.number)
     }
 
-    /// Get Zenhub first workspace for a repository.
-    pub fn get_first_workspace(&self, repository: &Repository) -> Result<Workspace, Error> {
-        self.zenhub.get_first_workspace(repository.id)
+    /// Get Zenhub workspaces for a repository.
+    pub fn get_workspaces(&self, repository: &Repository) -> Result<Vec<Workspace>, Error> {
+        self.zenhub.get_workspaces(repository.id)
+    }
+
+    /// Get the Zenhub workspace for a repository with the given name or id.
+    pub fn get_workspace(
+        &self,
+        repository: &Repository,
+        name_or_id: &str,
+    ) -> Result<Workspace, Error> {
+        let workspaces = self.get_workspaces(repository)?;
+        let available = describe_workspaces(&workspaces);
+        workspaces
+            .into_iter()
+            .find(|workspace| workspace.matches(name_or_id))
+            .ok_or_else(|| Error::Config {
+                description: format!(
+                    "No Zenhub workspace {} for repository {}. Available workspaces: {}",
+                    name_or_id, repository.name, available
+                ),
+            })
     }
 
     /// Get Zenhub board for a repository.
@@ -91,6 +219,87 @@ impl<'a> Client<'a> {
         self.zenhub.get_board(repository.id, &workspace.id)
     }
 
+    /// Get the Zenhub board for a workspace, merged across every repository in it.
+    ///
+    /// Pipelines are matched by id, and each issue is tagged with its repository.
+    pub fn get_workspace_board(
+        &self,
+        repositories: &Repositories,
+        workspace: &Workspace,
+    ) -> Result<Board, Error> {
+        self.merge_workspace_board(repositories, workspace, |repository| {
+            self.zenhub.get_board(repository.id, &workspace.id)
+        })
+    }
+
+    /// Get a pipeline from the Zenhub board for a workspace, bypassing any cache.
+    ///
+    /// Use this to work out positions in a pipeline that is about to be changed.
+    pub fn get_pipeline(
+        &self,
+        repositories: &Repositories,
+        workspace: &Workspace,
+        pipeline_id: &str,
+    ) -> Result<Pipeline, Error> {
+        let board = self.merge_workspace_board(repositories, workspace, |repository| {
+            self.zenhub.get_board_uncached(repository.id, &workspace.id)
+        })?;
+        board
+            .pipelines
+            .into_iter()
+            .find(|pipeline| pipeline.id == pipeline_id)
+            .ok_or_else(|| Error::Config {
+                description: format!("No pipeline {} in workspace {}", pipeline_id, workspace.id),
+            })
+    }
+
+    fn merge_workspace_board<F>(
+        &self,
+        repositories: &Repositories,
+        workspace: &Workspace,
+        get_board: F,
+    ) -> Result<Board, Error>
+    where
+        F: Fn(&Repository) -> Result<Board, Error>,
+    {
+        let mut board: Option<Board> = None;
+        for (_, repository) in repositories.iter() {
+            if !workspace.repositories.is_empty()
+                && !workspace.repositories.contains(&repository.id)
+            {
+                warn!(
+                    "Repository {} is not in workspace {}",
+                    repository.name, workspace.id
+                );
+                continue;
+            }
+            let mut repo_board = get_board(repository)?;
+            for pipeline in repo_board.pipelines.iter_mut() {
+                for issue in pipeline.issues.iter_mut() {
+                    issue.repo_id = Some(repository.id);
+                }
+            }
+            match board.as_mut() {
+                None => board = Some(repo_board),
+                Some(board) => {
+                    for repo_pipeline in repo_board.pipelines {
+                        match board
+                            .pipelines
+                            .iter_mut()
+                            .find(|pipeline| pipeline.id == repo_pipeline.id)
+                        {
+                            Some(pipeline) => pipeline.issues.extend(repo_pipeline.issues),
+                            None => board.pipelines.push(repo_pipeline),
+                        }
+                    }
+                }
+            }
+        }
+        board.ok_or_else(|| Error::Unknown {
+            description: format!("No repositories in workspace {}.", workspace.id),
+        })
+    }
+
     /// Get Zenhub issue metadata.
     pub fn get_zenhub_issue(
         &self,
@@ -100,6 +309,154 @@ impl<'a> Client<'a> {
         self.zenhub.get_issue(repository.id, issue.number)
     }
 
+    /// Get an index of the issues on the Zenhub board of `workspace`.
+    pub fn get_board_index(
+        &self,
+        repositories: &Repositories,
+        workspace: &Workspace,
+    ) -> Result<BoardIndex, Error> {
+        let board = self.get_workspace_board(repositories, workspace)?;
+        Ok(BoardIndex::new(&board, repositories.primary().id))
+    }
+
+    /// Get Zenhub issue metadata for many issues, keyed by issue id.
+    ///
+    /// Issues in `index` are read from it, and the rest are fetched concurrently.
+    /// Every issue must belong to one of `repositories`.
+    pub fn get_zenhub_issues<'i, I>(
+        &self,
+        repositories: &Repositories,
+        index: Option<&BoardIndex>,
+        issues: I,
+    ) -> Result<HashMap<u32, zenhub::Issue>, Error>
+    where
+        I: IntoIterator<Item = &'i Issue>,
+    {
+        let mut zenhub_issues = HashMap::new();
+        let mut issue_keys = Vec::new();
+        for issue in issues {
+            let reference = repositories.reference(issue)?;
+            match index.and_then(|index| index.get(&reference)) {
+                Some(entry) => {
+                    zenhub_issues.insert(
+                        issue.id,
+                        zenhub::Issue {
+                            estimate: entry.estimate.clone(),
+                            is_epic: entry.is_epic,
+                        },
+                    );
+                }
+                None => issue_keys.push((issue.id, reference)),
+            }
+        }
+        debug!(
+            "{} Zenhub issues from the board, fetching {}",
+            zenhub_issues.len(),
+            issue_keys.len()
+        );
+        let fetched = self.concurrently(issue_keys, |(issue_id, reference)| {
+            Ok((
+                issue_id,
+                self.zenhub
+                    .get_issue(reference.repo_id, reference.issue_number)?,
+            ))
+        })?;
+        zenhub_issues.extend(fetched);
+        Ok(zenhub_issues)
+    }
+
+    /// Call `f` with every item, making up to `concurrency` calls at once.
+    ///
+    /// Results are returned in no particular order.
+    fn concurrently<K, T, F>(&self, items: Vec<K>, f: F) -> Result<Vec<T>, Error>
+    where
+        K: Send,
+        T: Send,
+        F: Fn(K) -> Result<T, Error> + Sync,
+    {
+        let workers = self.concurrency.min(items.len());
+        let queue = Mutex::new(items.into_iter());
+        let results = Mutex::new(Vec::new());
+
+        crossbeam_utils::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|_| loop {
+                    let item = match queue.lock().expect("Queue poisoned").next() {
+                        Some(item) => item,
+                        None => break,
+                    };
+                    let result = f(item);
+                    results.lock().expect("Results poisoned").push(result);
+                });
+            }
+        })
+        .map_err(|_| Error::Unknown {
+            description: "Concurrent requests failed unexpectedly.".to_owned(),
+        })?;
+
+        results
+            .into_inner()
+            .expect("Results poisoned")
+            .into_iter()
+            .collect()
+    }
+
+    /// Get the progress of every Zenhub epic in `repositories`.
+    ///
+    /// Open issues in an epic are listed if they are in `sprint`, if given.
+    pub fn get_epic_statuses(
+        &self,
+        repositories: &Repositories,
+        sprint: Option<&Sprint>,
+    ) -> Result<Vec<EpicStatus>, Error> {
+        let mut statuses = Vec::new();
+        for (full_name, repository) in repositories.iter() {
+            let (owner, repo) = split_full_name(full_name);
+            for epic_reference in self.zenhub.get_epics(repository.id)? {
+                let epic = self
+                    .zenhub
+                    .get_epic(repository.id, epic_reference.issue_number)?;
+                let epic_issue = self.get_stored_issue(owner, repo, epic_reference.issue_number)?;
+
+                let mut unknown_issues = 0;
+                let mut issue_keys = Vec::new();
+                for issue in epic.issues.into_iter().filter(|issue| !issue.is_epic) {
+                    match repositories.get_by_id(issue.repo_id) {
+                        Some((issue_full_name, _)) => issue_keys.push((issue_full_name, issue)),
+                        None => unknown_issues += 1,
+                    }
+                }
+                let issues = self.concurrently(issue_keys, |(issue_full_name, issue)| {
+                    let (owner, repo) = split_full_name(issue_full_name);
+                    let github_issue = self.get_stored_issue(owner, repo, issue.issue_number)?;
+                    Ok((github_issue, issue.estimate))
+                })?;
+
+                let mut status = EpicStatus {
+                    epic: epic_issue,
+                    total_points: 0,
+                    done_points: 0,
+                    in_sprint: Vec::new(),
+                    unknown_issues,
+                };
+                for (issue, estimate) in issues {
+                    let points = estimate.map(|estimate| estimate.value).unwrap_or(0);
+                    status.total_points += points;
+                    if issue.state == State::Closed {
+                        status.done_points += points;
+                    } else if let Some(true) =
+                        sprint.map(|sprint| sprint.contains(repositories, &issue))
+                    {
+                        status.in_sprint.push(issue);
+                    }
+                }
+                status.in_sprint.sort_by_key(|issue| issue.number);
+                statuses.push(status);
+            }
+        }
+        Ok(statuses)
+    }
+
     /// Set Zenhub issue estimate.
     pub fn set_estimate(
         &self,
@@ -111,23 +468,200 @@ impl<'a> Client<'a> {
             .set_estimate(repository.id, issue.number, estimate)
     }
 
-    /// Get sprint for milestone.
+    /// Get sprint for a milestone in the primary repository.
+    ///
+    /// Milestones with the same title in other repositories are part of the sprint.
     pub fn get_sprint(
         &self,
-        repository: &Repository,
+        repositories: &Repositories,
         milestone: Milestone,
     ) -> Result<Sprint, Error> {
-        let start_date = self.get_start_date(repository, &milestone)?;
-        Ok(Sprint {
+        let start_date = self.get_start_date(repositories.primary(), &milestone)?;
+        let milestones = self.get_sprint_milestones(&milestone)?;
+        Ok(Sprint::Milestone {
             milestone,
             start_date,
+            milestones,
         })
     }
 
-    /// Create a new sprint.
+    /// Get the Zenhub native sprints in a workspace, earliest first.
+    pub fn get_native_sprints(
+        &self,
+        workspace_id: &str,
+        state: SprintState,
+    ) -> Result<Vec<NativeSprint>, Error> {
+        self.zenhub.get_sprints(workspace_id, state)
+    }
+
+    /// Get sprint for a Zenhub native sprint.
+    pub fn get_native_sprint(&self, sprint: NativeSprint) -> Result<Sprint, Error> {
+        let issues = self.zenhub.get_sprint_issues(&sprint.id)?;
+        Ok(Sprint::Zenhub {
+            sprint,
+            issues: issues.into_iter().collect(),
+        })
+    }
+
+    /// Get the issues in a sprint, optionally only those in `state`.
+    ///
+    /// Issues are fetched again, so include any added since the sprint was fetched.
+    /// Issues of a Zenhub sprint in repositories that are not worked with are ignored.
+    pub fn get_sprint_issues(
+        &self,
+        repositories: &Repositories,
+        sprint: &Sprint,
+        state: Option<&State>,
+    ) -> Result<Vec<Issue>, Error> {
+        let issues = match sprint {
+            Sprint::Milestone {
+                milestone,
+                milestones,
+                ..
+            } => {
+                let mut query = SearchQueryBuilder::new();
+                query.milestone(&milestone.title);
+                if let Some(state) = state {
+                    query.state(state);
+                }
+                self.search_issues(&mut query)?
+                    .strict()?
+                    .collect::<Result<Vec<_>, _>>()?
+                    .into_iter()
+                    // Only issues in this sprint, not others with the same title
+                    .filter(|issue| milestones.contains(issue))
+                    .collect()
+            }
+            Sprint::Zenhub { sprint, .. } => {
+                let references = self.zenhub.get_sprint_issues(&sprint.id)?;
+                self.get_referenced_issues(repositories, references)?
+                    .into_iter()
+                    .filter(|issue| state.map(|state| issue.state == *state).unwrap_or(true))
+                    .collect()
+            }
+        };
+        Ok(issues)
+    }
+
+    /// Add an issue to a sprint, recording it in `sprint`.
+    ///
+    /// For a sprint kept as milestones, this replaces any milestone the issue has.
+    pub fn add_issue_to_sprint(
+        &self,
+        repositories: &Repositories,
+        sprint: &mut Sprint,
+        issue: &Issue,
+    ) -> Result<(), Error> {
+        match sprint {
+            Sprint::Milestone { milestones, .. } => {
+                let milestone = milestones.for_issue(issue).ok_or_else(|| Error::Config {
+                    description: format!("No sprint milestone in the repository of {}", issue),
+                })?;
+                self.assign_issue_to_milestone(issue, Some(milestone))?;
+            }
+            Sprint::Zenhub { sprint, .. } => {
+                let repository = repositories.for_issue(issue)?;
+                self.zenhub
+                    .add_issue_to_sprint(&sprint.id, repository.id, issue.number)?;
+            }
+        }
+        sprint.insert(repositories.reference(issue)?);
+        Ok(())
+    }
+
+    /// Remove an issue from a sprint.
+    pub fn remove_issue_from_sprint(
+        &self,
+        repositories: &Repositories,
+        sprint: &Sprint,
+        issue: &Issue,
+    ) -> Result<(), Error> {
+        match sprint {
+            Sprint::Milestone { .. } => {
+                self.assign_issue_to_milestone(issue, None)?;
+            }
+            Sprint::Zenhub { sprint, .. } => {
+                let repository = repositories.for_issue(issue)?;
+                self.zenhub
+                    .remove_issue_from_sprint(&sprint.id, repository.id, issue.number)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the titles of the sprints other than `sprint` that each issue is in, keyed by issue id.
+    ///
+    /// Issues in no other sprint, open or closed, are left out.
+    pub fn get_other_sprints(
+        &self,
+        repositories: &Repositories,
+        sprint: &Sprint,
+        issues: &[Issue],
+    ) -> Result<HashMap<u32, Vec<String>>, Error> {
+        let other_sprints = match sprint {
+            Sprint::Milestone { milestones, .. } => issues
+                .iter()
+                .filter_map(|issue| match &issue.milestone {
+                    Some(milestone) if !milestones.contains(issue) => {
+                        Some((issue.id, vec![milestone.title.clone()]))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            Sprint::Zenhub { sprint, .. } => {
+                let other_sprints = self.concurrently(issues.iter().collect(), |issue| {
+                    let repository = repositories.for_issue(issue)?;
+                    let titles: Vec<String> = self
+                        .zenhub
+                        .get_issue_sprints(repository.id, issue.number)?
+                        .into_iter()
+                        .filter(|other| other.id != sprint.id)
+                        .map(|other| other.title().to_owned())
+                        .collect();
+                    Ok((issue.id, titles))
+                })?;
+                other_sprints
+                    .into_iter()
+                    .filter(|(_, titles)| !titles.is_empty())
+                    .collect()
+            }
+        };
+        Ok(other_sprints)
+    }
+
+    /// Get the milestones with the same title as `milestone` in every repository.
+    ///
+    /// `milestone` must be in the primary repository. It may be open or closed.
+    pub fn get_sprint_milestones(&self, milestone: &Milestone) -> Result<MilestoneSet, Error> {
+        let mut milestones = MilestoneSet::default();
+        for (index, (owner, repo)) in self.repositories.iter().enumerate() {
+            let full_name = format!("{}/{}", owner, repo);
+            if index == 0 {
+                milestones.insert(&full_name, milestone.clone());
+                continue;
+            }
+            let repo_milestone = self
+                .github
+                .get_milestones_in_state(owner, repo, Some(SearchState::All))?
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .find(|repo_milestone| repo_milestone.title == milestone.title);
+            match repo_milestone {
+                Some(repo_milestone) => milestones.insert(&full_name, repo_milestone),
+                None => warn!("No milestone '{}' in {}", milestone.title, full_name),
+            }
+        }
+        Ok(milestones)
+    }
+
+    /// Create a new sprint, with a milestone in every repository.
+    ///
+    /// No milestones are created if the sprint already exists in any repository, and those
+    /// created are deleted again if creating the sprint fails.
+    /// Zenhub creates native sprints itself, from the sprint settings of the workspace.
     pub fn create_sprint(
         &self,
-        repository: &Repository,
+        repositories: &Repositories,
         sprint_number: &str,
         start_date: DateTime<FixedOffset>,
         due_on: DateTime<FixedOffset>,
@@ -135,49 +669,547 @@ impl<'a> Client<'a> {
         let mut milestone_spec = MilestoneUpdate::default();
         milestone_spec.title = Some(format!("Sprint {}", sprint_number));
         milestone_spec.due_on = Some(due_on);
+        let start_date = start_date.into();
 
-        let milestone = self
-            .github
-            .create_milestone(self.owner, self.repo, &milestone_spec)?;
+        let title = milestone_spec.title.as_deref().unwrap_or_default();
+        let mut existing = Vec::new();
+        for (owner, repo) in self.repositories.iter() {
+            let exists = self
+                .github
+                .get_milestones_in_state(owner, repo, Some(SearchState::All))?
+                .collect::<Result<Vec<_>, _>>()?
+                .iter()
+                .any(|milestone| milestone.title == title);
+            if exists {
+                existing.push(format!("{}/{}", owner, repo));
+            }
+        }
+        if !existing.is_empty() {
+            return Err(Error::Config {
+                description: format!(
+                    "Milestone '{}' already exists in {}",
+                    title,
+                    existing.join(", ")
+                ),
+            });
+        }
 
-        let start_date = start_date.into();
-        let start_date =
-            self.zenhub
-                .set_start_date(repository.id, milestone.number, &start_date)?;
-        Ok(Sprint {
+        let mut milestones = MilestoneSet::default();
+        let start_date = match self.create_sprint_milestones(
+            repositories,
+            &milestone_spec,
+            &start_date,
+            &mut milestones,
+        ) {
+            Ok(start_date) => start_date,
+            Err(error) => return Err(self.delete_sprint_milestones(&milestones, error)),
+        };
+        let (owner, repo) = self.repositories[0];
+        let milestone = milestones
+            .get(&format!("{}/{}", owner, repo))
+            .expect("Milestone created in the primary repository.")
+            .clone();
+        Ok(Sprint::Milestone {
             milestone,
             start_date,
+            milestones,
         })
     }
 
-    /// Move issue to a Zenhub pipeline.
+    /// Create a sprint milestone in every repository, adding each to `milestones` as it is
+    /// created, and return the start date in the primary repository.
+    fn create_sprint_milestones(
+        &self,
+        repositories: &Repositories,
+        milestone_spec: &MilestoneUpdate,
+        start_date: &StartDate,
+        milestones: &mut MilestoneSet,
+    ) -> Result<StartDate, Error> {
+        let mut primary_start_date = None;
+        for (owner, repo) in self.repositories.iter() {
+            let full_name = format!("{}/{}", owner, repo);
+            let repository = repositories.get(&full_name).ok_or_else(|| Error::Unknown {
+                description: format!("Repository {} was not loaded.", full_name),
+            })?;
+            let milestone = self.github.create_milestone(owner, repo, milestone_spec)?;
+            let milestone_number = milestone.number;
+            milestones.insert(&full_name, milestone);
+            let repo_start_date =
+                self.zenhub
+                    .set_start_date(repository.id, milestone_number, start_date)?;
+            if primary_start_date.is_none() {
+                primary_start_date = Some(repo_start_date);
+            }
+        }
+        Ok(primary_start_date.expect("At least one repository is required."))
+    }
+
+    /// Delete the milestones of a sprint that could not be created, returning `error`
+    /// along with any milestones that could not be deleted.
+    fn delete_sprint_milestones(&self, milestones: &MilestoneSet, error: Error) -> Error {
+        let mut remaining = Vec::new();
+        for (full_name, milestone) in milestones.iter() {
+            let (owner, repo) = split_full_name(full_name);
+            if let Err(delete_error) = self.github.delete_milestone(owner, repo, milestone.number) {
+                warn!(
+                    "Failed to delete milestone '{}' in {}: {}",
+                    milestone.title, full_name, delete_error
+                );
+                remaining.push(full_name.to_owned());
+            }
+        }
+        if remaining.is_empty() {
+            error
+        } else {
+            remaining.sort();
+            Error::IncompleteSprint {
+                source: Box::new(error),
+                repositories: remaining,
+            }
+        }
+    }
+
+    /// Rename the milestones of a sprint in every repository.
+    ///
+    /// Zenhub native sprints cannot be renamed.
+    pub fn rename_sprint(&self, sprint: &Sprint, new_title: &str) -> Result<(), Error> {
+        let update = MilestoneUpdate {
+            title: Some(new_title.to_owned()),
+            ..Default::default()
+        };
+        self.update_sprint_milestones(sprint, &update)
+    }
+
+    /// Close the milestones of a sprint in every repository.
+    ///
+    /// Zenhub closes native sprints itself, at their end date.
+    pub fn close_sprint(&self, sprint: &Sprint) -> Result<(), Error> {
+        let update = MilestoneUpdate {
+            state: Some(State::Closed),
+            ..Default::default()
+        };
+        self.update_sprint_milestones(sprint, &update)
+    }
+
+    fn update_sprint_milestones(
+        &self,
+        sprint: &Sprint,
+        update: &MilestoneUpdate,
+    ) -> Result<(), Error> {
+        let milestones = sprint.milestones().ok_or_else(|| Error::Config {
+            description: format!("Sprint {} is not kept as milestones", sprint),
+        })?;
+        for (full_name, milestone) in milestones.iter() {
+            let (owner, repo) = split_full_name(full_name);
+            self.github
+                .patch_milestone(owner, repo, milestone.number, update)?;
+        }
+        Ok(())
+    }
+
+    /// Get the Zenhub releases of every repository, open and closed.
+    pub fn get_releases(&self, repositories: &Repositories) -> Result<Vec<Release>, Error> {
+        let mut releases: Vec<Release> = Vec::new();
+        for (_, repository) in repositories.iter() {
+            for release in self.zenhub.get_releases(repository.id)? {
+                // Releases spanning several repositories are listed by each
+                if !releases
+                    .iter()
+                    .any(|existing| existing.release_id == release.release_id)
+                {
+                    releases.push(release);
+                }
+            }
+        }
+        Ok(releases)
+    }
+
+    /// Create a Zenhub release spanning every repository.
+    pub fn create_release(
+        &self,
+        repositories: &Repositories,
+        title: &str,
+        description: Option<&str>,
+        start_date: DateTime<FixedOffset>,
+        desired_end_date: DateTime<FixedOffset>,
+    ) -> Result<Release, Error> {
+        let primary = repositories.primary();
+        let release = CreateRelease {
+            title: title.to_owned(),
+            description: description.map(ToOwned::to_owned),
+            start_date,
+            desired_end_date,
+            repositories: repositories
+                .iter()
+                .map(|(_, repository)| repository.id)
+                .filter(|id| *id != primary.id)
+                .collect(),
+        };
+        self.zenhub.create_release(primary.id, &release)
+    }
+
+    /// Get the progress of a release, with velocity over the recent `window`.
+    ///
+    /// Estimates are read from `index` where possible. Issues in repositories that are
+    /// not worked with are ignored.
+    pub fn get_release_progress(
+        &self,
+        repositories: &Repositories,
+        index: Option<&BoardIndex>,
+        release: Release,
+        window: Duration,
+    ) -> Result<ReleaseProgress, Error> {
+        let references = self.zenhub.get_release_issues(&release.release_id)?;
+        let issues = self.get_referenced_issues(repositories, references)?;
+        let zenhub_issues = self.get_zenhub_issues(repositories, index, &issues)?;
+        let issues: Vec<(Issue, Option<u32>)> = issues
+            .into_iter()
+            // Epics are made up of other issues, so would count their points twice
+            .filter(|issue| !zenhub_issues[&issue.id].is_epic)
+            .map(|issue| {
+                let estimate = zenhub_issues[&issue.id]
+                    .estimate
+                    .as_ref()
+                    .map(|estimate| estimate.value);
+                (issue, estimate)
+            })
+            .collect();
+        Ok(ReleaseProgress::new(
+            release,
+            &issues,
+            window,
+            Utc::now().into(),
+        ))
+    }
+
+    /// Add issues to, and remove issues from, a release.
+    pub fn update_release_issues(
+        &self,
+        repositories: &Repositories,
+        release: &Release,
+        add: &[Issue],
+        remove: &[Issue],
+    ) -> Result<(), Error> {
+        let references = |issues: &[Issue]| {
+            issues
+                .iter()
+                .map(|issue| repositories.reference(issue))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let update = UpdateReleaseIssues {
+            add_issues: references(add)?,
+            remove_issues: references(remove)?,
+        };
+        self.zenhub
+            .update_release_issues(&release.release_id, &update)
+    }
+
+    /// Work out where `placement` puts `issue` in `pipeline`.
+    ///
+    /// Issues placed above or below another are placed by index, allowing for `issue`
+    /// already being in the pipeline.
+    pub fn pipeline_position(
+        &self,
+        repositories: &Repositories,
+        pipeline: &Pipeline,
+        issue: &Issue,
+        placement: &Placement,
+    ) -> Result<Position, Error> {
+        let target_reference = match placement {
+            Placement::Top => return Ok(Position::Top),
+            Placement::Bottom => return Ok(Position::Bottom),
+            Placement::Index(index) => return Ok(Position::Index(*index)),
+            Placement::Above(reference) | Placement::Below(reference) => reference,
+        };
+        let target = repositories.reference(&self.get_issue_by_reference(target_reference)?)?;
+        let mut index = pipeline.index_of(&target).ok_or_else(|| Error::Config {
+            description: format!(
+                "Issue {} is not in pipeline {}",
+                target_reference, pipeline.name
+            ),
+        })?;
+        if let Some(current_index) = pipeline.index_of(&repositories.reference(issue)?) {
+            // The target moves up once the issue is taken out above it
+            if current_index < index {
+                index -= 1;
+            }
+        }
+        if let Placement::Below(_) = placement {
+            index += 1;
+        }
+        Ok(Position::Index(index as u32))
+    }
+
+    /// Move issue to a Zenhub pipeline, at `placement` within it.
+    ///
+    /// Issues placed above or below another are placed using the current issues in the
+    /// pipeline, rather than those in `pipeline`.
     pub fn move_issue_to_pipeline(
         &self,
-        repository: &Repository,
+        repositories: &Repositories,
         workspace: &Workspace,
         issue: &Issue,
         pipeline: &Pipeline,
+        placement: &Placement,
     ) -> Result<(), Error> {
-        let mut position = PipelinePosition::default();
-        position.pipeline_id = pipeline.id.clone();
-
+        let position = match placement {
+            Placement::Above(_) | Placement::Below(_) => {
+                let current = self.get_pipeline(repositories, workspace, &pipeline.id)?;
+                self.pipeline_position(repositories, &current, issue, placement)?
+            }
+            _ => self.pipeline_position(repositories, pipeline, issue, placement)?,
+        };
+        let position = PipelinePosition {
+            pipeline_id: pipeline.id.clone(),
+            position,
+        };
+        let repository = repositories.for_issue(issue)?;
         self.zenhub
             .move_issue(repository.id, &workspace.id, issue.number, &position)
     }
 
+    /// Reorder the issues in a Zenhub pipeline, returning the number of issues moved.
+    ///
+    /// The current issues in the pipeline are read from Zenhub, rather than from `pipeline`.
+    /// Issues that compare equal keep their current order.
+    pub fn reorder_pipeline(
+        &self,
+        repositories: &Repositories,
+        workspace: &Workspace,
+        pipeline: &Pipeline,
+        order: &PipelineOrder,
+    ) -> Result<usize, Error> {
+        let pipeline = &self.get_pipeline(repositories, workspace, &pipeline.id)?;
+        let primary_id = repositories.primary().id;
+        let current: Vec<IssueReference> = pipeline
+            .issues
+            .iter()
+            .map(|issue| issue.reference(primary_id))
+            .collect();
+
+        let mut desired = current.clone();
+        match order {
+            PipelineOrder::Estimate => {
+                let estimates: HashMap<&IssueReference, Option<u32>> = current
+                    .iter()
+                    .zip(pipeline.issues.iter())
+                    .map(|(reference, issue)| {
+                        (
+                            reference,
+                            issue.estimate.as_ref().map(|estimate| estimate.value),
+                        )
+                    })
+                    .collect();
+                desired.sort_by_key(|reference| {
+                    let estimate = estimates[reference];
+                    (estimate.is_none(), Reverse(estimate.unwrap_or_default()))
+                });
+            }
+            PipelineOrder::Labels(labels) => {
+                let issues = self.concurrently(current.clone(), |reference| {
+                    let issue = self.get_referenced_issue(repositories, &reference)?;
+                    Ok((reference, issue))
+                })?;
+                let mut ranks: HashMap<IssueReference, usize> = HashMap::new();
+                for (reference, issue) in issues {
+                    let rank = issue
+                        .and_then(|issue| {
+                            labels.iter().position(|label| {
+                                issue
+                                    .labels
+                                    .iter()
+                                    .any(|issue_label| issue_label.name.eq_ignore_ascii_case(label))
+                            })
+                        })
+                        .unwrap_or(labels.len());
+                    ranks.insert(reference, rank);
+                }
+                desired.sort_by_key(|reference| ranks[reference]);
+            }
+        }
+
+        let moves = reorder_moves(&current, &desired);
+        for (reference, index) in moves.iter() {
+            let position = PipelinePosition {
+                pipeline_id: pipeline.id.clone(),
+                position: Position::Index(*index),
+            };
+            self.zenhub.move_issue(
+                reference.repo_id,
+                &workspace.id,
+                reference.issue_number,
+                &position,
+            )?;
+        }
+        Ok(moves.len())
+    }
+
     /// Get a repository from the API.
     pub fn get_repository(&self) -> Result<Repository, Error> {
         self.github.get_repository(self.owner, self.repo)
     }
 
-    /// Get an issue from the API.
+    /// Get every repository worked with from the API.
+    pub fn get_repositories(&self) -> Result<Repositories, Error> {
+        let repositories = self
+            .repositories
+            .iter()
+            .map(|(owner, repo)| {
+                Ok((
+                    format!("{}/{}", owner, repo),
+                    self.github.get_repository(owner, repo)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Repositories::new(repositories)
+    }
+
+    /// Get an issue from the store if present and fresh, otherwise the API.
+    fn get_stored_issue(&self, owner: &str, repo: &str, issue_number: u32) -> Result<Issue, Error> {
+        let full_name = format!("{}/{}", owner, repo);
+        if let Some(issue) = self
+            .store
+            .and_then(|store| store.issue(&full_name, issue_number))
+        {
+            debug!("Using stored issue {}#{}", full_name, issue_number);
+            return Ok(issue.clone());
+        }
+        self.github.get_issue(owner, repo, issue_number)
+    }
+
+    /// Get an issue from a Zenhub reference, if it is in one of `repositories`.
+    pub fn get_referenced_issue(
+        &self,
+        repositories: &Repositories,
+        reference: &IssueReference,
+    ) -> Result<Option<Issue>, Error> {
+        match repositories.get_by_id(reference.repo_id) {
+            Some((full_name, _)) => {
+                let (owner, repo) = split_full_name(full_name);
+                Ok(Some(self.get_stored_issue(
+                    owner,
+                    repo,
+                    reference.issue_number,
+                )?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Get the issues for Zenhub references, in order, ignoring those in repositories
+    /// that are not worked with.
+    fn get_referenced_issues(
+        &self,
+        repositories: &Repositories,
+        references: Vec<IssueReference>,
+    ) -> Result<Vec<Issue>, Error> {
+        let mut issues = self.concurrently(
+            references.into_iter().enumerate().collect(),
+            |(index, reference)| Ok((index, self.get_referenced_issue(repositories, &reference)?)),
+        )?;
+        issues.sort_by_key(|(index, _)| *index);
+        Ok(issues.into_iter().filter_map(|(_, issue)| issue).collect())
+    }
+
+    /// Work out how `issues` moved through pipelines, from their Zenhub events.
+    ///
+    /// Cycle time starts when an issue first enters the `in_progress` pipeline.
+    /// Flows are returned in the order of `issues`.
+    pub fn get_issue_flows(
+        &self,
+        repositories: &Repositories,
+        issues: Vec<Issue>,
+        in_progress: &str,
+    ) -> Result<Vec<IssueFlow>, Error> {
+        let now = Utc::now().into();
+        let mut flows = self.concurrently(
+            issues.into_iter().enumerate().collect(),
+            |(index, issue)| {
+                let repository = repositories.for_issue(&issue)?;
+                let events = self.zenhub.get_issue_events(repository.id, issue.number)?;
+                Ok((index, IssueFlow::new(issue, &events, in_progress, now)))
+            },
+        )?;
+        flows.sort_by_key(|(index, _)| *index);
+        Ok(flows.into_iter().map(|(_, flow)| flow).collect())
+    }
+
+    /// Get the dependencies between issues in `repositories`.
+    pub fn get_dependencies(&self, repositories: &Repositories) -> Result<Dependencies, Error> {
+        let mut dependencies = Vec::new();
+        for (_, repository) in repositories.iter() {
+            dependencies.extend(self.zenhub.get_dependencies(repository.id)?);
+        }
+        Ok(Dependencies::new(dependencies))
+    }
+
+    /// Get the open issues blocking `issue`.
+    ///
+    /// Blocking issues in repositories that are not worked with are ignored.
+    pub fn get_open_blockers(
+        &self,
+        repositories: &Repositories,
+        dependencies: &Dependencies,
+        issue: &Issue,
+    ) -> Result<Vec<Issue>, Error> {
+        let reference = repositories.reference(issue)?;
+        let mut blockers = Vec::new();
+        for blocking in dependencies.blocking(&reference) {
+            match self.get_referenced_issue(repositories, blocking)? {
+                Some(blocker) if blocker.state == State::Open => blockers.push(blocker),
+                Some(_) => (),
+                None => debug!(
+                    "Ignoring blocker #{} in unknown repository {}",
+                    blocking.issue_number, blocking.repo_id
+                ),
+            }
+        }
+        Ok(blockers)
+    }
+
+    /// Get an issue, from the store if it is known there, otherwise the API.
     pub fn get_issue(&self, issue_number: u32) -> Result<Issue, Error> {
-        self.github.get_issue(self.owner, self.repo, issue_number)
+        self.get_stored_issue(self.owner, self.repo, issue_number)
+    }
+
+    /// Get an issue by reference, such as `12`, `repo#12` or `owner/repo#12`.
+    ///
+    /// Issues without a repository are in the primary repository. Repositories without
+    /// an owner must be one of those worked with.
+    pub fn get_issue_by_reference(&self, reference: &str) -> Result<Issue, Error> {
+        let invalid = || Error::Config {
+            description: format!("Invalid issue reference {}", reference),
+        };
+        let (repository, issue_number) = match reference.rfind('#') {
+            Some(index) => (Some(&reference[..index]), &reference[index + 1..]),
+            None => (None, reference),
+        };
+        let issue_number = issue_number.parse().map_err(|_| invalid())?;
+        let (owner, repo) = match repository {
+            None | Some("") => (self.owner, self.repo),
+            Some(repository) => match repository.find('/') {
+                Some(index) => (&repository[..index], &repository[index + 1..]),
+                None => self
+                    .repositories
+                    .iter()
+                    .find(|(_, repo)| repo.eq_ignore_ascii_case(repository))
+                    .copied()
+                    .ok_or_else(invalid)?,
+            },
+        };
+        self.get_stored_issue(owner, repo, issue_number)
     }
 
     /// Get milestones from the API.
     pub fn get_milestones(&self) -> Result<Vec<Milestone>, Error> {
-        self.github.get_milestones(self.owner, self.repo)
+        self.github.get_milestones(self.owner, self.repo)?.collect()
+    }
+
+    /// Get open and closed milestones from the API.
+    pub fn get_all_milestones(&self) -> Result<Vec<Milestone>, Error> {
+        self.github
+            .get_milestones_in_state(self.owner, self.repo, Some(SearchState::All))?
+            .collect()
     }
 
     /// Assign an issue to a milestone. Passing `None` will set to no milestone.
@@ -191,8 +1223,8 @@ impl<'a> Client<'a> {
         let mut update = IssueUpdate::default();
         update.milestone = Some(milestone.map(|milestone| milestone.number));
 
-        self.github
-            .patch_issue(&self.owner, &self.repo, issue.number, &update)
+        let (owner, repo) = self.issue_owner_repo(issue);
+        self.github.patch_issue(owner, repo, issue.number, &update)
     }
 
     /// Assign an organisation member to an issue.
@@ -206,30 +1238,104 @@ impl<'a> Client<'a> {
         let mut update = IssueUpdate::default();
         update.assignees = Some(vec![member.login.clone()]);
 
-        self.github
-            .patch_issue(&self.owner, &self.repo, issue.number, &update)
+        let (owner, repo) = self.issue_owner_repo(issue);
+        self.github.patch_issue(owner, repo, issue.number, &update)
     }
 
-    /// Get issues by the given query, in ascending order of time updated.
+    /// Get issues by the given query, from any repository, in ascending order of time updated.
     pub fn search_issues(
         &self,
         query_builder: &mut SearchQueryBuilder,
     ) -> Result<PaginatedSearch<Issue>, Error> {
+        for (owner, repo) in self.repositories.iter() {
+            query_builder.owner_repo(owner, repo);
+        }
         let query = SearchIssues {
-            q: query_builder
-                .owner_repo(self.owner, self.repo)
-                .issue()
-                .build(),
+            q: query_builder.issue().build(),
             sort: Some("updated"),
             order: Some(Direction::Ascending),
-            per_page: Some(100),
+            per_page: Some(self.github.per_page()),
         };
         self.github.search_issues(&query)
     }
 
-    /// Get organisation members.
-    pub fn get_members(&self) -> Result<Vec<OrganisationMember>, Error> {
-        self.github.get_members(self.owner)
+    /// Get all issues by the given query, with the date given by `qualifier` in `range`.
+    ///
+    /// Github only returns the first 1000 results of a search. To get past this, the
+    /// range is split into smaller windows until each has fewer results, or covers a
+    /// single day. Results from all windows are merged, without duplicates.
+    pub fn search_issues_windowed(
+        &self,
+        query_builder: &SearchQueryBuilder,
+        qualifier: DateQualifier,
+        range: &DateRange,
+    ) -> Result<Vec<Issue>, Error> {
+        let from = range
+            .from
+            .unwrap_or_else(|| NaiveDate::from_ymd(SEARCH_EPOCH_YEAR, 1, 1));
+        let to = range.to.unwrap_or_else(|| Utc::today().naive_utc());
+
+        let mut seen = HashSet::new();
+        let mut issues = Vec::new();
+        // Windows still to search, latest first so that we pop the earliest
+        let mut windows = vec![(from, to)];
+        while let Some((from, to)) = windows.pop() {
+            let mut window_query = query_builder.clone();
+            window_query.date(qualifier, &DateRange::between(from, to));
+            let window_issues = self.search_issues(&mut window_query)?;
+
+            let progress = window_issues.progress();
+            let too_many = progress.total_count.unwrap_or(0) > SEARCH_RESULT_LIMIT;
+            if (too_many || window_issues.incomplete_results()) && from < to {
+                let middle = from + Duration::days((to - from).num_days() / 2);
+                debug!("Splitting search window {}..{} at {}", from, to, middle);
+                windows.push((middle.succ(), to));
+                windows.push((from, middle));
+                continue;
+            }
+            if too_many {
+                warn!(
+                    "Search matched more than {} results on {}, some will be missing",
+                    SEARCH_RESULT_LIMIT, from
+                );
+            }
+
+            for issue in window_issues {
+                let issue = issue?;
+                if seen.insert(issue.id) {
+                    issues.push(issue);
+                }
+            }
+        }
+        Ok(issues)
+    }
+
+    /// Get the people who can be assigned to issues, from `source`.
+    pub fn get_members(&self, source: &MemberSource) -> Result<Vec<OrganisationMember>, Error> {
+        match source {
+            MemberSource::Organisation => match self.github.get_members(self.owner) {
+                Ok(members) => members.collect(),
+                // The owner is a user, not an organisation
+                Err(Error::GithubNotFound { .. }) => {
+                    debug!("No organisation {}, using repository assignees", self.owner);
+                    self.github.get_assignees(self.owner, self.repo)?.collect()
+                }
+                Err(error) => Err(error),
+            },
+            MemberSource::Assignees => self.github.get_assignees(self.owner, self.repo)?.collect(),
+            MemberSource::Collaborators => self
+                .github
+                .get_collaborators(self.owner, self.repo)?
+                .collect(),
+            MemberSource::Team(team_slug) => self
+                .github
+                .get_team_members(self.owner, team_slug)?
+                .collect(),
+            MemberSource::Logins(logins) => logins
+                .iter()
+                .map(|login| self.github.get_user(login))
+                .collect(),
+        }
     }
 
     /// Update milestone title with provided title
@@ -257,11 +1363,11 @@ impl<'a> Client<'a> {
 mod tests {
     use chrono::{FixedOffset, NaiveDate, TimeZone};
     use lazy_static::lazy_static;
-    use mockito::mock;
+    use mockito::{mock, Matcher, Mock};
     use pretty_assertions::assert_eq;
 
     use super::github::{tests::MOCK_GITHUB_CLIENT, State};
-    use super::zenhub::tests::MOCK_ZENHUB_CLIENT;
+    use super::zenhub::{tests::MOCK_ZENHUB_CLIENT, Estimate};
     use super::*;
 
     const OWNER: &str = "tommilligan";
@@ -300,6 +1406,149 @@ mod tests {
         assert_eq!(issues, vec![]);
     }
 
+    fn search_body(total_count: u32, issue_ids: &[u32]) -> String {
+        let items: Vec<String> = issue_ids
+            .iter()
+            .map(|id| {
+                format!(
+                    r#"{{"id": {0}, "number": {0}, "state": "closed", "title": "Issue {0}", "milestone": null,
+  "assignees": [], "labels": [], "created_at": "2020-01-01T00:00:00Z", "updated_at": "2020-01-01T00:00:00Z",
+  "closed_at": "2020-01-01T00:00:00Z", "html_url": "https://github.com/tommilligan/decadog/issues/{0}",
+  "repository_url": "https://api.github.com/repos/tommilligan/decadog"}}"#,
+                    id
+                )
+            })
+            .collect();
+        format!(
+            r#"{{"total_count": {}, "incomplete_results": false, "items": [{}]}}"#,
+            total_count,
+            items.join(",")
+        )
+    }
+
+    #[test]
+    fn test_search_issues_windowed() {
+        let window = |from: &str, to: &str| {
+            Matcher::Regex(format!(
+                r"^/search/issues\?q=label%3Awindowed\+closed%3A{}\.\.{}\+",
+                from, to
+            ))
+        };
+        let mock_all = mock("GET", window("2020-01-01", "2020-01-04"))
+            .with_status(200)
+            .with_body(search_body(1001, &[1]))
+            .create();
+        let mock_early = mock("GET", window("2020-01-01", "2020-01-02"))
+            .with_status(200)
+            .with_body(search_body(2, &[1, 2]))
+            .create();
+        // Issues may move between windows while we search
+        let mock_late = mock("GET", window("2020-01-03", "2020-01-04"))
+            .with_status(200)
+            .with_body(search_body(2, &[2, 3]))
+            .create();
+
+        let issues = MOCK_CLIENT
+            .search_issues_windowed(
+                SearchQueryBuilder::new().label("windowed"),
+                DateQualifier::Closed,
+                &DateRange::between(
+                    NaiveDate::from_ymd(2020, 1, 1),
+                    NaiveDate::from_ymd(2020, 1, 4),
+                ),
+            )
+            .unwrap();
+
+        mock_all.assert();
+        mock_early.assert();
+        mock_late.assert();
+        assert_eq!(
+            issues.iter().map(|issue| issue.id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_search_issues_incomplete() {
+        let mock = mock(
+            "GET",
+            Matcher::Regex(r"^/search/issues\?q=label%3Aincomplete\+".to_owned()),
+        )
+        .with_status(200)
+        .with_body(r#"{"total_count": 0, "incomplete_results": true, "items": []}"#)
+        .create();
+
+        let issues = MOCK_CLIENT
+            .search_issues(SearchQueryBuilder::new().label("incomplete"))
+            .unwrap();
+        assert!(issues.incomplete_results());
+        match issues.strict().err().unwrap() {
+            Error::IncompleteResults { .. } => (),
+            _ => panic!("Unexpected error"),
+        }
+        mock.assert();
+    }
+
+    #[test]
+    fn test_get_members_user_owner() {
+        let github = &*MOCK_GITHUB_CLIENT;
+        let zenhub = &*MOCK_ZENHUB_CLIENT;
+        let client = Client::new("octocat", "hello-world", github, zenhub).unwrap();
+        let mock_organisation = mock("GET", "/orgs/octocat/members?per_page=100")
+            .with_status(404)
+            .with_body(r#"{"message": "Not Found"}"#)
+            .create();
+        let mock_assignees = mock("GET", "/repos/octocat/hello-world/assignees?per_page=100")
+            .with_status(200)
+            .with_body(r#"[{"login": "octocat", "id": 1}]"#)
+            .create();
+
+        let members = client.get_members(&MemberSource::Organisation).unwrap();
+        mock_organisation.assert();
+        mock_assignees.assert();
+        assert_eq!(
+            members,
+            vec![OrganisationMember {
+                login: "octocat".to_owned(),
+                id: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn test_get_members_team() {
+        let mock = mock("GET", "/orgs/tommilligan/teams/core/members?per_page=100")
+            .with_status(200)
+            .with_body(r#"[{"login": "tommilligan", "id": 1}]"#)
+            .create();
+
+        let members = MOCK_CLIENT
+            .get_members(&MemberSource::Team("core".to_owned()))
+            .unwrap();
+        mock.assert();
+        assert_eq!(members.len(), 1);
+    }
+
+    #[test]
+    fn test_get_members_logins() {
+        let mock = mock("GET", "/users/octocat")
+            .with_status(200)
+            .with_body(r#"{"login": "octocat", "id": 2}"#)
+            .create();
+
+        let members = MOCK_CLIENT
+            .get_members(&MemberSource::Logins(vec!["octocat".to_owned()]))
+            .unwrap();
+        mock.assert();
+        assert_eq!(
+            members,
+            vec![OrganisationMember {
+                login: "octocat".to_owned(),
+                id: 2
+            }]
+        );
+    }
+
     #[test]
     fn test_get_milestone_open_issues() {
         let body = r#"{
@@ -326,4 +1575,1199 @@ mod tests {
 
         assert_eq!(issues, vec![]);
     }
+
+    #[test]
+    fn test_get_zenhub_issues() {
+        let mocks: Vec<_> = (1..=3)
+            .map(|issue_number| {
+                mock(
+                    "GET",
+                    &*format!("/p1/repositories/4321/issues/{}", issue_number),
+                )
+                .match_header("x-authentication-token", "mock_token")
+                .with_status(200)
+                .with_body(format!(
+                    r#"{{"estimate": {{"value": {}}}, "is_epic": false}}"#,
+                    issue_number
+                ))
+                .create()
+            })
+            .collect();
+
+        let repositories = Repositories::new(vec![(
+            format!("{}/{}", OWNER, REPO),
+            Repository {
+                id: 4321,
+                name: REPO.to_owned(),
+            },
+        )])
+        .unwrap();
+        let issues: Vec<Issue> = (1..=3)
+            .map(|number| Issue {
+                id: number,
+                number,
+                repository_url: format!("https://api.github.com/repos/{}/{}", OWNER, REPO),
+                ..Default::default()
+            })
+            .collect();
+        let zenhub_issues = MOCK_CLIENT
+            .get_zenhub_issues(&repositories, None, &issues)
+            .unwrap();
+
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+        assert_eq!(zenhub_issues.len(), 3);
+        for issue_number in 1..=3 {
+            assert_eq!(
+                zenhub_issues[&issue_number],
+                zenhub::Issue {
+                    estimate: Some(zenhub::Estimate {
+                        value: issue_number
+                    }),
+                    is_epic: false,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_zenhub_issues_from_board() {
+        let mock_on_board = mock(
+            "GET",
+            Matcher::Regex(r"^/p1/repositories/11/issues/7[01]$".to_owned()),
+        )
+        .expect(0)
+        .create();
+        let mock_off_board = mock("GET", "/p1/repositories/12/issues/72")
+            .with_status(200)
+            .with_body(r#"{"estimate": {"value": 8}, "is_epic": false}"#)
+            .create();
+        let board = Board {
+            pipelines: vec![Pipeline {
+                id: "p1".to_owned(),
+                name: "Backlog".to_owned(),
+                issues: vec![
+                    zenhub::PipelineIssue {
+                        issue_number: 70,
+                        estimate: Some(zenhub::Estimate { value: 3 }),
+                        is_epic: false,
+                        repo_id: Some(11),
+                    },
+                    zenhub::PipelineIssue {
+                        issue_number: 71,
+                        estimate: None,
+                        is_epic: true,
+                        repo_id: Some(11),
+                    },
+                ],
+            }],
+        };
+        let repositories = multi_repositories();
+        let index = BoardIndex::new(&board, repositories.primary().id);
+        let issues = [
+            serde_json::from_str(&issue_body("decadog", 70, "open", None)).unwrap(),
+            serde_json::from_str(&issue_body("decadog", 71, "open", None)).unwrap(),
+            serde_json::from_str::<Issue>(&issue_body("frontend", 72, "closed", None)).unwrap(),
+        ];
+
+        let zenhub_issues = MOCK_CLIENT
+            .get_zenhub_issues(&repositories, Some(&index), &issues)
+            .unwrap();
+        mock_on_board.assert();
+        mock_off_board.assert();
+        let estimate = |issue_id| {
+            zenhub_issues[&issue_id]
+                .estimate
+                .as_ref()
+                .map(|estimate| estimate.value)
+        };
+        assert_eq!(estimate(70), Some(3));
+        assert_eq!(estimate(71), None);
+        assert!(zenhub_issues[&71].is_epic);
+        assert_eq!(estimate(72), Some(8));
+    }
+
+    /// Repositories for a client spanning `decadog` and `frontend`.
+    fn multi_repositories() -> Repositories {
+        Repositories::new(vec![
+            (
+                "tommilligan/decadog".to_owned(),
+                Repository {
+                    id: 11,
+                    name: "decadog".to_owned(),
+                },
+            ),
+            (
+                "tommilligan/frontend".to_owned(),
+                Repository {
+                    id: 12,
+                    name: "frontend".to_owned(),
+                },
+            ),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_with_repositories() {
+        let repositories = vec!["frontend".to_owned(), "octocat/infra".to_owned()];
+        let client = Client::new(OWNER, REPO, &MOCK_GITHUB_CLIENT, &MOCK_ZENHUB_CLIENT)
+            .unwrap()
+            .with_repositories(&repositories)
+            .unwrap();
+        assert_eq!(
+            client.owner_repos(),
+            &[
+                ("tommilligan", "decadog"),
+                ("tommilligan", "frontend"),
+                ("octocat", "infra")
+            ]
+        );
+        assert_ne!(client.id, MOCK_CLIENT.id);
+
+        let invalid = vec!["octocat/".to_owned()];
+        assert!(
+            Client::new(OWNER, REPO, &MOCK_GITHUB_CLIENT, &MOCK_ZENHUB_CLIENT)
+                .unwrap()
+                .with_repositories(&invalid)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_search_issues_repositories() {
+        let mock = mock("GET", "/search/issues?q=label%3Amulti+repo%3Atommilligan%2Fdecadog+repo%3Atommilligan%2Ffrontend+type%3Aissue&sort=updated&order=asc&per_page=100")
+            .with_status(200)
+            .with_body(r#"{"total_count": 0, "incomplete_results": false, "items": []}"#)
+            .create();
+
+        let repositories = vec!["frontend".to_owned()];
+        let client = Client::new(OWNER, REPO, &MOCK_GITHUB_CLIENT, &MOCK_ZENHUB_CLIENT)
+            .unwrap()
+            .with_repositories(&repositories)
+            .unwrap();
+        let issues = client
+            .search_issues(SearchQueryBuilder::new().label("multi"))
+            .unwrap()
+            .collect::<Result<Vec<Issue>, _>>()
+            .unwrap();
+        mock.assert();
+        assert_eq!(issues, vec![]);
+    }
+
+    /// Mock listing every milestone in the repository `full_name`.
+    fn mock_milestones(full_name: &str, body: &str) -> Mock {
+        mock(
+            "GET",
+            Matcher::Regex(format!(r"^/repos/{}/milestones\?state=all", full_name)),
+        )
+        .with_status(200)
+        .with_body(body)
+        .create()
+    }
+
+    /// The repositories `octocat/{name}` and `octocat/{name}-web`.
+    fn sprint_repositories(name: &str) -> Repositories {
+        Repositories::new(vec![
+            (
+                format!("octocat/{}", name),
+                Repository {
+                    id: 31,
+                    name: name.to_owned(),
+                },
+            ),
+            (
+                format!("octocat/{}-web", name),
+                Repository {
+                    id: 32,
+                    name: format!("{}-web", name),
+                },
+            ),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_create_sprint_existing() {
+        let mocks = [
+            mock_milestones("octocat/existing", "[]"),
+            mock_milestones(
+                "octocat/existing-web",
+                r#"[{"id": 201, "number": 4, "title": "Sprint 4", "state": "closed",
+  "open_issues": 0, "closed_issues": 0, "created_at": "2020-01-01T00:00:00Z",
+  "html_url": "http://foo.bar"}]"#,
+            ),
+        ];
+        let create = mock(
+            "POST",
+            Matcher::Regex("^/repos/octocat/existing".to_owned()),
+        )
+        .expect(0)
+        .create();
+
+        let web = vec!["existing-web".to_owned()];
+        let client = Client::new(
+            "octocat",
+            "existing",
+            &MOCK_GITHUB_CLIENT,
+            &MOCK_ZENHUB_CLIENT,
+        )
+        .unwrap()
+        .with_repositories(&web)
+        .unwrap();
+        let repositories = sprint_repositories("existing");
+        let start_date = FixedOffset::east(0).ymd(2020, 1, 1).and_hms(12, 0, 0);
+        let error = client
+            .create_sprint(
+                &repositories,
+                "4",
+                start_date,
+                start_date + Duration::days(13),
+            )
+            .unwrap_err();
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+        create.assert();
+        assert_eq!(
+            error.to_string(),
+            "Decadog config error: Milestone 'Sprint 4' already exists in octocat/existing-web"
+        );
+    }
+
+    #[test]
+    fn test_create_sprint_rolls_back() {
+        let mocks = [
+            mock_milestones("octocat/rollback", "[]"),
+            mock_milestones("octocat/rollback-web", "[]"),
+            mock("POST", "/repos/octocat/rollback/milestones")
+                .with_status(201)
+                .with_body(
+                    r#"{"id": 301, "number": 5, "title": "Sprint 5", "state": "open",
+  "open_issues": 0, "closed_issues": 0, "created_at": "2020-01-01T00:00:00Z",
+  "html_url": "http://foo.bar"}"#,
+                )
+                .create(),
+            mock("POST", "/p1/repositories/31/milestones/5/start_date")
+                .with_status(200)
+                .with_body(r#"{"start_date": "2020-01-01T12:00:00Z"}"#)
+                .create(),
+            mock("POST", "/repos/octocat/rollback-web/milestones")
+                .with_status(422)
+                .with_body(r#"{"message": "Validation Failed"}"#)
+                .create(),
+            mock("DELETE", "/repos/octocat/rollback/milestones/5")
+                .with_status(204)
+                .create(),
+        ];
+
+        let web = vec!["rollback-web".to_owned()];
+        let client = Client::new(
+            "octocat",
+            "rollback",
+            &MOCK_GITHUB_CLIENT,
+            &MOCK_ZENHUB_CLIENT,
+        )
+        .unwrap()
+        .with_repositories(&web)
+        .unwrap();
+        let repositories = sprint_repositories("rollback");
+        let start_date = FixedOffset::east(0).ymd(2020, 1, 1).and_hms(12, 0, 0);
+        let error = client
+            .create_sprint(
+                &repositories,
+                "5",
+                start_date,
+                start_date + Duration::days(13),
+            )
+            .unwrap_err();
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+        match error {
+            Error::GithubValidationFailed { .. } => {}
+            error => panic!("Unexpected error {:?}", error),
+        }
+    }
+
+    #[test]
+    fn test_create_sprint_repositories() {
+        let milestone_body = |id: u32| {
+            format!(
+                r#"{{"id": {}, "number": 3, "title": "Sprint 3", "state": "open",
+  "open_issues": 0, "closed_issues": 0, "created_at": "2020-01-01T00:00:00Z",
+  "due_on": "2020-01-14T12:00:00Z", "html_url": "http://foo.bar"}}"#,
+                id
+            )
+        };
+        let start_date_body = r#"{"start_date": "2020-01-01T12:00:00Z"}"#;
+        let mocks = [
+            mock_milestones("tommilligan/decadog", "[]"),
+            mock_milestones("tommilligan/frontend", "[]"),
+            mock("POST", "/repos/tommilligan/decadog/milestones")
+                .with_status(201)
+                .with_body(milestone_body(101))
+                .create(),
+            mock("POST", "/repos/tommilligan/frontend/milestones")
+                .with_status(201)
+                .with_body(milestone_body(102))
+                .create(),
+            mock("POST", "/p1/repositories/11/milestones/3/start_date")
+                .with_status(200)
+                .with_body(start_date_body)
+                .create(),
+            mock("POST", "/p1/repositories/12/milestones/3/start_date")
+                .with_status(200)
+                .with_body(start_date_body)
+                .create(),
+        ];
+
+        let repositories = vec!["frontend".to_owned()];
+        let client = Client::new(OWNER, REPO, &MOCK_GITHUB_CLIENT, &MOCK_ZENHUB_CLIENT)
+            .unwrap()
+            .with_repositories(&repositories)
+            .unwrap();
+        let start_date = FixedOffset::east(0).ymd(2020, 1, 1).and_hms(12, 0, 0);
+        let sprint = client
+            .create_sprint(
+                &multi_repositories(),
+                "3",
+                start_date,
+                start_date + Duration::days(13),
+            )
+            .unwrap();
+
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+        let (milestone, milestones) = match &sprint {
+            Sprint::Milestone {
+                milestone,
+                milestones,
+                ..
+            } => (milestone, milestones),
+            sprint => panic!("Unexpected sprint {:?}", sprint),
+        };
+        assert_eq!(milestone.id, 101);
+        assert_eq!(milestones.get("tommilligan/frontend").unwrap().id, 102);
+        let issue = Issue {
+            repository_url: "https://api.github.com/repos/TomMilligan/Frontend".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            sprint.milestones().unwrap().for_issue(&issue).unwrap().id,
+            102
+        );
+    }
+
+    #[test]
+    fn test_get_workspace() {
+        let mock = mock("GET", "/p2/repositories/21/workspaces")
+            .with_status(200)
+            .with_body(
+                r#"[{"name": "Platform", "description": null, "id": "ws1", "repositories": [21]},
+  {"name": "Frontend", "description": null, "id": "ws2", "repositories": [21, 22]}]"#,
+            )
+            .expect(3)
+            .create();
+        let repository = Repository {
+            id: 21,
+            name: "workspaces".to_owned(),
+        };
+
+        assert_eq!(
+            MOCK_CLIENT
+                .get_workspace(&repository, "frontend")
+                .unwrap()
+                .id,
+            "ws2"
+        );
+        assert_eq!(
+            MOCK_CLIENT.get_workspace(&repository, "ws1").unwrap().id,
+            "ws1"
+        );
+        match MOCK_CLIENT
+            .get_workspace(&repository, "Mobile")
+            .unwrap_err()
+        {
+            Error::Config { description } => assert_eq!(
+                description,
+                "No Zenhub workspace Mobile for repository workspaces. \
+                 Available workspaces: Platform (ws1), Frontend (ws2)"
+            ),
+            error => panic!("Unexpected error {}", error),
+        }
+        mock.assert();
+    }
+
+    #[test]
+    fn test_get_workspace_board() {
+        let board_body = |issue_number: u32, extra_pipeline: bool| {
+            let extra = if extra_pipeline {
+                r#", {"id": "p3", "name": "Frontend only", "issues": []}"#
+            } else {
+                ""
+            };
+            format!(
+                r#"{{"pipelines": [{{"id": "p1", "name": "Backlog", "issues": [
+  {{"issue_number": {}, "estimate": null, "is_epic": false}}]}},
+  {{"id": "p2", "name": "Done", "issues": []}}{}]}}"#,
+                issue_number, extra
+            )
+        };
+        let mock_decadog = mock("GET", "/p2/workspaces/ws/repositories/11/board")
+            .with_status(200)
+            .with_body(board_body(1, false))
+            .create();
+        let mock_frontend = mock("GET", "/p2/workspaces/ws/repositories/12/board")
+            .with_status(200)
+            .with_body(board_body(1, true))
+            .create();
+
+        let workspace = Workspace {
+            id: "ws".to_owned(),
+            repositories: vec![11, 12],
+            ..Default::default()
+        };
+        let board = MOCK_CLIENT
+            .get_workspace_board(&multi_repositories(), &workspace)
+            .unwrap();
+        mock_decadog.assert();
+        mock_frontend.assert();
+
+        assert_eq!(
+            board
+                .pipelines
+                .iter()
+                .map(|pipeline| pipeline.name.as_ref())
+                .collect::<Vec<&str>>(),
+            vec!["Backlog", "Done", "Frontend only"]
+        );
+        let backlog = &board.pipelines[0];
+        assert_eq!(
+            backlog
+                .issues
+                .iter()
+                .map(|issue| (issue.repo_id, issue.issue_number))
+                .collect::<Vec<_>>(),
+            vec![(Some(11), 1), (Some(12), 1)]
+        );
+    }
+
+    #[test]
+    fn test_get_issue_by_reference() {
+        let issue_body = |repo: &str| {
+            format!(
+                r#"{{"id": 1, "number": 7, "state": "open", "title": "Title", "milestone": null,
+  "assignees": [], "labels": [], "created_at": "2020-01-01T00:00:00Z",
+  "updated_at": "2020-01-01T00:00:00Z", "closed_at": null, "html_url": "http://foo.bar",
+  "repository_url": "https://api.github.com/repos/{}"}}"#,
+                repo
+            )
+        };
+        let mock_primary = mock("GET", "/repos/tommilligan/decadog/issues/7")
+            .with_status(200)
+            .with_body(issue_body("tommilligan/decadog"))
+            .create();
+        let mock_frontend = mock("GET", "/repos/tommilligan/frontend/issues/7")
+            .with_status(200)
+            .with_body(issue_body("tommilligan/frontend"))
+            .create();
+        let mock_other = mock("GET", "/repos/octocat/infra/issues/7")
+            .with_status(200)
+            .with_body(issue_body("octocat/infra"))
+            .create();
+
+        let repositories = vec!["frontend".to_owned()];
+        let client = Client::new(OWNER, REPO, &MOCK_GITHUB_CLIENT, &MOCK_ZENHUB_CLIENT)
+            .unwrap()
+            .with_repositories(&repositories)
+            .unwrap();
+        assert_eq!(
+            client.get_issue_by_reference("7").unwrap().to_string(),
+            "decadog#7: Title"
+        );
+        assert_eq!(
+            client
+                .get_issue_by_reference("Frontend#7")
+                .unwrap()
+                .to_string(),
+            "frontend#7: Title"
+        );
+        assert_eq!(
+            client
+                .get_issue_by_reference("octocat/infra#7")
+                .unwrap()
+                .to_string(),
+            "infra#7: Title"
+        );
+        mock_primary.assert();
+        mock_frontend.assert();
+        mock_other.assert();
+
+        for reference in &["", "#", "backend#7", "frontend#x"] {
+            assert!(
+                client.get_issue_by_reference(reference).is_err(),
+                "{}",
+                reference
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_issue_from_store() {
+        let path = std::env::temp_dir().join(format!(
+            "decadog-test-client-store-{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let mut store = Store::open(&path).unwrap();
+        let event = webhook::Event::parse("issues", webhook::tests::ISSUES_PAYLOAD.as_bytes())
+            .unwrap()
+            .unwrap();
+        store.apply(&event);
+
+        // No mock is set up, so the issue must come from the store
+        let client = Client::new(OWNER, REPO, &MOCK_GITHUB_CLIENT, &MOCK_ZENHUB_CLIENT)
+            .unwrap()
+            .with_store(&store);
+        let issue = client.get_issue_by_reference("decadog#12").unwrap();
+        assert_eq!(issue.title, "Fix the thing");
+
+        // Once the stored issue is stale, it must come from the API
+        let store = store.with_max_age(std::time::Duration::from_secs(0));
+        let mock = mock("GET", "/repos/tommilligan/decadog/issues/12")
+            .with_status(200)
+            .with_body(issue_body("decadog", 12, "open", None))
+            .create();
+        let client = Client::new(OWNER, REPO, &MOCK_GITHUB_CLIENT, &MOCK_ZENHUB_CLIENT)
+            .unwrap()
+            .with_store(&store);
+        let issue = client.get_issue_by_reference("decadog#12").unwrap();
+        assert_eq!(issue.title, "Issue 12");
+        mock.assert();
+    }
+
+    /// Body of an issue in a repository owned by `tommilligan`, optionally in a milestone.
+    fn issue_body(repo: &str, number: u32, state: &str, milestone_id: Option<u32>) -> String {
+        let milestone = match milestone_id {
+            Some(id) => format!(
+                r#"{{"id": {}, "number": 1, "title": "Sprint 1", "state": "open",
+  "open_issues": 1, "closed_issues": 0, "created_at": "2020-01-01T00:00:00Z",
+  "html_url": "http://foo.bar"}}"#,
+                id
+            ),
+            None => "null".to_owned(),
+        };
+        format!(
+            r#"{{"id": {1}, "number": {1}, "state": "{2}", "title": "Issue {1}", "milestone": {3},
+  "assignees": [], "labels": [], "created_at": "2020-01-01T00:00:00Z",
+  "updated_at": "2020-01-01T00:00:00Z", "closed_at": null, "html_url": "http://foo.bar",
+  "repository_url": "https://api.github.com/repos/tommilligan/{0}"}}"#,
+            repo, number, state, milestone
+        )
+    }
+
+    #[test]
+    fn test_get_epic_statuses() {
+        let mocks = [
+            mock("GET", "/p1/repositories/11/epics")
+                .with_status(200)
+                .with_body(
+                    r#"{"epic_issues": [{"issue_number": 20, "repo_id": 11, "issue_url": ""}]}"#,
+                )
+                .create(),
+            mock("GET", "/p1/repositories/12/epics")
+                .with_status(200)
+                .with_body(r#"{"epic_issues": []}"#)
+                .create(),
+            mock("GET", "/p1/repositories/11/epics/20")
+                .with_status(200)
+                .with_body(
+                    r#"{"total_epic_estimates": {"value": 10}, "estimate": null, "issues": [
+  {"issue_number": 21, "repo_id": 11, "estimate": {"value": 3}, "is_epic": false},
+  {"issue_number": 22, "repo_id": 12, "estimate": {"value": 5}, "is_epic": false},
+  {"issue_number": 23, "repo_id": 99, "estimate": {"value": 1}, "is_epic": false},
+  {"issue_number": 24, "repo_id": 11, "estimate": {"value": 2}, "is_epic": true}]}"#,
+                )
+                .create(),
+            mock("GET", "/repos/tommilligan/decadog/issues/20")
+                .with_status(200)
+                .with_body(issue_body("decadog", 20, "open", None))
+                .create(),
+            mock("GET", "/repos/tommilligan/decadog/issues/21")
+                .with_status(200)
+                .with_body(issue_body("decadog", 21, "closed", None))
+                .create(),
+            mock("GET", "/repos/tommilligan/frontend/issues/22")
+                .with_status(200)
+                .with_body(issue_body("frontend", 22, "open", Some(7)))
+                .create(),
+        ];
+
+        let sprint = Sprint::Zenhub {
+            sprint: serde_json::from_value(serde_json::json!({
+                "id": "sprint_1",
+                "name": null,
+                "generatedName": "Sprint 1",
+                "state": "OPEN",
+                "startAt": "2020-01-06T12:00:00Z",
+                "endAt": "2020-01-20T12:00:00Z"
+            }))
+            .unwrap(),
+            issues: vec![IssueReference {
+                repo_id: 12,
+                issue_number: 22,
+            }]
+            .into_iter()
+            .collect(),
+        };
+        let statuses = MOCK_CLIENT
+            .get_epic_statuses(&multi_repositories(), Some(&sprint))
+            .unwrap();
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+
+        assert_eq!(statuses.len(), 1);
+        let status = &statuses[0];
+        assert_eq!(status.epic.number, 20);
+        assert_eq!(status.total_points, 8);
+        assert_eq!(status.done_points, 3);
+        assert_eq!(status.remaining_points(), 5);
+        assert_eq!(status.unknown_issues, 1);
+        assert_eq!(
+            status
+                .in_sprint
+                .iter()
+                .map(|issue| issue.to_string())
+                .collect::<Vec<_>>(),
+            vec!["frontend#22: Issue 22"]
+        );
+    }
+
+    #[test]
+    fn test_get_open_blockers() {
+        let dependency = |blocking_repo, blocking_number| zenhub::Dependency {
+            blocking: IssueReference {
+                repo_id: blocking_repo,
+                issue_number: blocking_number,
+            },
+            blocked: IssueReference {
+                repo_id: 11,
+                issue_number: 31,
+            },
+        };
+        let dependencies = Dependencies::new(vec![
+            dependency(12, 30),
+            dependency(11, 32),
+            dependency(99, 1),
+        ]);
+        let mock_open = mock("GET", "/repos/tommilligan/frontend/issues/30")
+            .with_status(200)
+            .with_body(issue_body("frontend", 30, "open", None))
+            .create();
+        let mock_closed = mock("GET", "/repos/tommilligan/decadog/issues/32")
+            .with_status(200)
+            .with_body(issue_body("decadog", 32, "closed", None))
+            .create();
+
+        let issue: Issue = serde_json::from_str(&issue_body("decadog", 31, "open", None)).unwrap();
+        let blockers = MOCK_CLIENT
+            .get_open_blockers(&multi_repositories(), &dependencies, &issue)
+            .unwrap();
+        mock_open.assert();
+        mock_closed.assert();
+        assert_eq!(
+            blockers
+                .iter()
+                .map(|issue| issue.to_string())
+                .collect::<Vec<_>>(),
+            vec!["frontend#30: Issue 30"]
+        );
+    }
+
+    #[test]
+    fn test_native_sprint_issues() {
+        let mocks = [
+            mock("POST", "/public/graphql")
+                .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                    "variables": { "sprintId": "sprint_native" }
+                })))
+                .with_status(200)
+                .with_body(
+                    r#"{"data": {"node": {"issues": {"nodes": [
+  {"number": 40, "repository": {"ghId": 11}},
+  {"number": 41, "repository": {"ghId": 12}},
+  {"number": 1, "repository": {"ghId": 99}}],
+  "pageInfo": {"hasNextPage": false, "endCursor": null}}}}}"#,
+                )
+                .expect(2)
+                .create(),
+            mock("GET", "/repos/tommilligan/decadog/issues/40")
+                .with_status(200)
+                .with_body(issue_body("decadog", 40, "open", None))
+                .create(),
+            mock("GET", "/repos/tommilligan/frontend/issues/41")
+                .with_status(200)
+                .with_body(issue_body("frontend", 41, "closed", None))
+                .create(),
+        ];
+
+        let native_sprint: NativeSprint = serde_json::from_str(
+            r#"{"id": "sprint_native", "name": null, "generatedName": "Sprint 4",
+  "state": "OPEN", "startAt": "2020-01-06T12:00:00Z", "endAt": "2020-01-20T12:00:00Z"}"#,
+        )
+        .unwrap();
+        let repositories = multi_repositories();
+        let sprint = MOCK_CLIENT.get_native_sprint(native_sprint).unwrap();
+        let open_issues = MOCK_CLIENT
+            .get_sprint_issues(&repositories, &sprint, Some(&State::Open))
+            .unwrap();
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+
+        assert_eq!(sprint.title(), "Sprint 4");
+        assert!(sprint.milestones().is_none());
+        assert_eq!(
+            open_issues
+                .iter()
+                .map(|issue| issue.to_string())
+                .collect::<Vec<_>>(),
+            vec!["decadog#40: Issue 40"]
+        );
+        let issue: Issue = serde_json::from_str(&issue_body("frontend", 41, "open", None)).unwrap();
+        assert!(sprint.contains(&repositories, &issue));
+        let issue: Issue = serde_json::from_str(&issue_body("frontend", 40, "open", None)).unwrap();
+        assert!(!sprint.contains(&repositories, &issue));
+    }
+
+    #[test]
+    fn test_add_issue_to_native_sprint() {
+        let mocks = [
+            mock("POST", "/public/graphql")
+                .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                    "variables": { "repositoryGhId": 12, "issueNumber": 42 }
+                })))
+                .with_status(200)
+                .with_body(r#"{"data": {"issueByInfo": {"id": "issue_12_42"}}}"#)
+                .create(),
+            mock("POST", "/public/graphql")
+                .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                    "variables": { "issueIds": ["issue_12_42"], "sprintIds": ["sprint_add_issue"] }
+                })))
+                .with_status(200)
+                .with_body(r#"{"data": {"addIssuesToSprints": {"clientMutationId": null}}}"#)
+                .create(),
+        ];
+
+        let mut sprint = Sprint::Zenhub {
+            sprint: serde_json::from_str(
+                r#"{"id": "sprint_add_issue", "name": null, "generatedName": "Sprint 5",
+  "state": "OPEN", "startAt": "2020-01-06T12:00:00Z", "endAt": "2020-01-20T12:00:00Z"}"#,
+            )
+            .unwrap(),
+            issues: HashSet::new(),
+        };
+        let repositories = multi_repositories();
+        let issue: Issue = serde_json::from_str(&issue_body("frontend", 42, "open", None)).unwrap();
+        assert!(!sprint.contains(&repositories, &issue));
+
+        MOCK_CLIENT
+            .add_issue_to_sprint(&repositories, &mut sprint, &issue)
+            .unwrap();
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+        assert!(sprint.contains(&repositories, &issue));
+    }
+
+    #[test]
+    fn test_get_other_native_sprints() {
+        let issue_sprints = |issue_number: u32, sprint_ids: &[&str]| {
+            let nodes: Vec<_> = sprint_ids
+                .iter()
+                .map(|id| {
+                    serde_json::json!({
+                        "id": id, "name": null, "generatedName": format!("Sprint {}", id),
+                        "state": "CLOSED", "startAt": "2020-01-06T12:00:00Z",
+                        "endAt": "2020-01-20T12:00:00Z"
+                    })
+                })
+                .collect();
+            mock("POST", "/public/graphql")
+                .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                    "variables": { "repositoryGhId": 12, "issueNumber": issue_number, "after": null }
+                })))
+                .with_status(200)
+                .with_body(
+                    serde_json::json!({ "data": { "issueByInfo": { "sprints": {
+                        "nodes": nodes,
+                        "pageInfo": { "hasNextPage": false, "endCursor": null }
+                    }}}})
+                    .to_string(),
+                )
+                .create()
+        };
+        let mocks = [
+            issue_sprints(43, &[]),
+            issue_sprints(44, &["sprint_current"]),
+            issue_sprints(45, &["sprint_current", "sprint_previous"]),
+        ];
+
+        let sprint = Sprint::Zenhub {
+            sprint: serde_json::from_str(
+                r#"{"id": "sprint_current", "name": null, "generatedName": "Sprint 6",
+  "state": "OPEN", "startAt": "2020-01-20T12:00:00Z", "endAt": "2020-02-03T12:00:00Z"}"#,
+            )
+            .unwrap(),
+            issues: HashSet::new(),
+        };
+        let issues: Vec<Issue> = [43, 44, 45]
+            .iter()
+            .map(|number| serde_json::from_str(&issue_body("frontend", *number, "closed", None)))
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let other_sprints = MOCK_CLIENT
+            .get_other_sprints(&multi_repositories(), &sprint, &issues)
+            .unwrap();
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+        assert_eq!(other_sprints.len(), 1);
+        assert_eq!(
+            other_sprints[&45],
+            vec!["Sprint sprint_previous".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_get_release_progress() {
+        let zenhub_issue = |estimate: Option<u32>, is_epic: bool| {
+            serde_json::json!({ "estimate": estimate.map(|value| serde_json::json!({ "value": value })), "is_epic": is_epic })
+                .to_string()
+        };
+        let mocks = [
+            mock("GET", "/p1/reports/release/release_progress/issues")
+                .with_status(200)
+                .with_body(
+                    r#"[{"repo_id": 11, "issue_number": 50}, {"repo_id": 12, "issue_number": 51},
+  {"repo_id": 11, "issue_number": 52}, {"repo_id": 99, "issue_number": 1}]"#,
+                )
+                .create(),
+            mock("GET", "/repos/tommilligan/decadog/issues/50")
+                .with_status(200)
+                .with_body(issue_body("decadog", 50, "closed", None))
+                .create(),
+            mock("GET", "/repos/tommilligan/frontend/issues/51")
+                .with_status(200)
+                .with_body(issue_body("frontend", 51, "open", None))
+                .create(),
+            mock("GET", "/repos/tommilligan/decadog/issues/52")
+                .with_status(200)
+                .with_body(issue_body("decadog", 52, "open", None))
+                .create(),
+            mock("GET", "/p1/repositories/11/issues/50")
+                .with_status(200)
+                .with_body(zenhub_issue(Some(3), false))
+                .create(),
+            mock("GET", "/p1/repositories/12/issues/51")
+                .with_status(200)
+                .with_body(zenhub_issue(Some(5), false))
+                .create(),
+            mock("GET", "/p1/repositories/11/issues/52")
+                .with_status(200)
+                .with_body(zenhub_issue(Some(8), true))
+                .create(),
+        ];
+
+        let release: Release = serde_json::from_str(
+            r#"{"release_id": "release_progress", "title": "1.0", "description": null,
+  "start_date": "2020-01-01T00:00:00Z", "desired_end_date": "2020-02-01T00:00:00Z",
+  "created_at": "2020-01-01T00:00:00Z", "closed_at": null, "state": "open",
+  "repositories": [11, 12]}"#,
+        )
+        .unwrap();
+        let progress = MOCK_CLIENT
+            .get_release_progress(&multi_repositories(), None, release, Duration::weeks(4))
+            .unwrap();
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+
+        // The epic is not counted, and nor is the issue in an unknown repository
+        assert_eq!(progress.issues, 2);
+        assert_eq!(progress.closed_issues, 1);
+        assert_eq!(progress.total_points, 8);
+        assert_eq!(progress.done_points, 3);
+        assert_eq!(progress.remaining_points(), 5);
+    }
+
+    fn pipeline(issues: &[(u32, Option<u32>)]) -> Pipeline {
+        Pipeline {
+            id: "p1".to_owned(),
+            name: "Backlog".to_owned(),
+            issues: issues
+                .iter()
+                .map(|(issue_number, estimate)| zenhub::PipelineIssue {
+                    issue_number: *issue_number,
+                    estimate: estimate.as_ref().map(Estimate::from),
+                    is_epic: false,
+                    repo_id: Some(11),
+                })
+                .collect(),
+        }
+    }
+
+    /// Mock the boards of `multi_repositories` in `workspace_id`, with `issues` in the
+    /// decadog Backlog pipeline.
+    fn mock_pipeline_boards(workspace_id: &str, issues: &[(u32, Option<u32>)]) -> Vec<Mock> {
+        let issues: Vec<String> = issues
+            .iter()
+            .map(|(issue_number, estimate)| {
+                format!(
+                    r#"{{"issue_number": {}, "estimate": {}, "is_epic": false}}"#,
+                    issue_number,
+                    estimate.map_or("null".to_owned(), |value| format!(
+                        r#"{{"value": {}}}"#,
+                        value
+                    ))
+                )
+            })
+            .collect();
+        vec![
+            mock(
+                "GET",
+                format!("/p2/workspaces/{}/repositories/11/board", workspace_id).as_str(),
+            )
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"pipelines": [{{"id": "p1", "name": "Backlog", "issues": [{}]}}]}}"#,
+                issues.join(", ")
+            ))
+            .create(),
+            mock(
+                "GET",
+                format!("/p2/workspaces/{}/repositories/12/board", workspace_id).as_str(),
+            )
+            .with_status(200)
+            .with_body(r#"{"pipelines": [{"id": "p1", "name": "Backlog", "issues": []}]}"#)
+            .create(),
+        ]
+    }
+
+    #[test]
+    fn test_move_issue_below_uses_current_pipeline() {
+        let mut mocks = mock_pipeline_boards("move_below", &[(70, None), (71, None)]);
+        mocks.push(
+            mock("GET", "/repos/tommilligan/decadog/issues/71")
+                .with_status(200)
+                .with_body(issue_body("decadog", 71, "open", None))
+                .create(),
+        );
+        mocks.push(
+            mock(
+                "POST",
+                "/p2/workspaces/move_below/repositories/11/issues/72/moves",
+            )
+            .match_body(Matcher::JsonString(
+                r#"{"pipeline_id": "p1", "position": 2}"#.to_owned(),
+            ))
+            .with_status(200)
+            .create(),
+        );
+
+        let workspace = Workspace {
+            id: "move_below".to_owned(),
+            ..Default::default()
+        };
+        let issue = Issue {
+            number: 72,
+            repository_url: "https://api.github.com/repos/tommilligan/decadog".to_owned(),
+            ..Default::default()
+        };
+        // The pipeline passed in is out of date, and does not contain issue 71
+        MOCK_CLIENT
+            .move_issue_to_pipeline(
+                &multi_repositories(),
+                &workspace,
+                &issue,
+                &pipeline(&[]),
+                &Placement::Below("71".to_owned()),
+            )
+            .unwrap();
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+    }
+
+    #[test]
+    fn test_pipeline_position() {
+        // Issue 41 is looked up twice
+        let mocks: Vec<_> = [(40, 1), (41, 2), (42, 1)]
+            .iter()
+            .map(|(number, hits)| {
+                mock(
+                    "GET",
+                    format!("/repos/tommilligan/decadog/issues/{}", number).as_str(),
+                )
+                .with_status(200)
+                .with_body(issue_body("decadog", *number, "open", None))
+                .expect(*hits)
+                .create()
+            })
+            .collect();
+        let repositories = multi_repositories();
+        let pipeline = pipeline(&[(40, None), (41, None), (42, None)]);
+        let issue = |number| Issue {
+            number,
+            repository_url: "https://api.github.com/repos/tommilligan/decadog".to_owned(),
+            ..Default::default()
+        };
+        let position = |number, placement| {
+            MOCK_CLIENT.pipeline_position(&repositories, &pipeline, &issue(number), &placement)
+        };
+
+        assert_eq!(position(42, Placement::Top).unwrap(), Position::Top);
+        assert_eq!(
+            position(42, Placement::Index(3)).unwrap(),
+            Position::Index(3)
+        );
+        assert_eq!(
+            position(42, Placement::Above("41".to_owned())).unwrap(),
+            Position::Index(1)
+        );
+        assert_eq!(
+            position(43, Placement::Below("41".to_owned())).unwrap(),
+            Position::Index(2)
+        );
+        assert_eq!(
+            position(40, Placement::Below("decadog#42".to_owned())).unwrap(),
+            Position::Index(2)
+        );
+        assert_eq!(
+            position(42, Placement::Below("40".to_owned())).unwrap(),
+            Position::Index(1)
+        );
+        for mock in mocks {
+            mock.assert();
+        }
+    }
+
+    #[test]
+    fn test_get_issue_flows() {
+        let mocks = [
+            mock("GET", "/p1/repositories/11/issues/60/events")
+                .with_status(200)
+                .with_body(
+                    r#"[{"type": "transferIssue", "created_at": "2020-01-03T00:00:00Z",
+  "from_pipeline": {"name": "Backlog"}, "to_pipeline": {"name": "In Progress"}}]"#,
+                )
+                .create(),
+            mock("GET", "/p1/repositories/12/issues/61/events")
+                .with_status(200)
+                .with_body("[]")
+                .create(),
+        ];
+        let issue = |repo, number| -> Issue {
+            let mut issue: Issue =
+                serde_json::from_str(&issue_body(repo, number, "closed", None)).unwrap();
+            issue.closed_at = Some(DateTime::parse_from_rfc3339("2020-01-05T00:00:00Z").unwrap());
+            issue
+        };
+
+        let flows = MOCK_CLIENT
+            .get_issue_flows(
+                &multi_repositories(),
+                vec![issue("decadog", 60), issue("frontend", 61)],
+                "In Progress",
+            )
+            .unwrap();
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+        assert_eq!(flows[0].issue.number, 60);
+        assert_eq!(flows[0].cycle_time, Some(Duration::days(2)));
+        assert_eq!(flows[0].lead_time, Some(Duration::days(4)));
+        assert_eq!(flows[1].issue.number, 61);
+        assert_eq!(flows[1].cycle_time, None);
+    }
+
+    #[test]
+    fn test_reorder_pipeline_by_estimate() {
+        let move_mock = |number, index| {
+            mock(
+                "POST",
+                format!(
+                    "/p2/workspaces/reorder/repositories/11/issues/{}/moves",
+                    number
+                )
+                .as_str(),
+            )
+            .match_body(Matcher::JsonString(format!(
+                r#"{{"pipeline_id": "p1", "position": {}}}"#,
+                index
+            )))
+            .with_status(200)
+            .create()
+        };
+        let mut mocks =
+            mock_pipeline_boards("reorder", &[(50, None), (51, Some(2)), (52, Some(5))]);
+        mocks.extend(vec![move_mock(52, 0), move_mock(51, 1)]);
+
+        let workspace = Workspace {
+            id: "reorder".to_owned(),
+            ..Default::default()
+        };
+        // The current issues are read from Zenhub, not the pipeline passed in
+        let moved = MOCK_CLIENT
+            .reorder_pipeline(
+                &multi_repositories(),
+                &workspace,
+                &pipeline(&[]),
+                &PipelineOrder::Estimate,
+            )
+            .unwrap();
+        assert_eq!(moved, 2);
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+    }
+
+    #[test]
+    fn test_reorder_pipeline_zero_estimate() {
+        // An estimate of zero is still an estimate, so goes above unestimated issues
+        let mut mocks = mock_pipeline_boards("reorder_zero", &[(60, None), (61, Some(0))]);
+        mocks.push(
+            mock(
+                "POST",
+                "/p2/workspaces/reorder_zero/repositories/11/issues/61/moves",
+            )
+            .match_body(Matcher::JsonString(
+                r#"{"pipeline_id": "p1", "position": 0}"#.to_owned(),
+            ))
+            .with_status(200)
+            .create(),
+        );
+
+        let workspace = Workspace {
+            id: "reorder_zero".to_owned(),
+            ..Default::default()
+        };
+        let moved = MOCK_CLIENT
+            .reorder_pipeline(
+                &multi_repositories(),
+                &workspace,
+                &pipeline(&[]),
+                &PipelineOrder::Estimate,
+            )
+            .unwrap();
+        assert_eq!(moved, 1);
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+    }
 }
@@ -0,0 +1,364 @@
+/// API rate limit tracking, shared by the Github and Zenhub clients.
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use log::{info, warn};
+use reqwest::blocking::{Request, Response};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{ResponseBuilderExt, StatusCode};
+
+use crate::error::Error;
+
+const HEADER_LIMIT: &str = "x-ratelimit-limit";
+const HEADER_REMAINING: &str = "x-ratelimit-remaining";
+const HEADER_USED: &str = "x-ratelimit-used";
+const HEADER_RESET: &str = "x-ratelimit-reset";
+
+/// Requests Zenhub allows per minute, if it does not say.
+const ZENHUB_DEFAULT_LIMIT: u32 = 100;
+
+/// Wait before retrying a secondary rate limit that gives no other guidance.
+///
+/// Github recommends waiting at least a minute in this case.
+const SECONDARY_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// Text in the body of a 403 for a secondary rate limit without a `Retry-After` header.
+const SECONDARY_RATE_LIMIT_MESSAGE: &str = "secondary rate limit";
+
+/// Once fewer than this fraction of requests remain, spread them out until the reset.
+const PACE_BELOW_FRACTION: u32 = 10;
+
+/// An API that limits the rate of requests.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimitedApi {
+    Github,
+    Zenhub,
+}
+
+impl fmt::Display for RateLimitedApi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Github => write!(f, "Github"),
+            Self::Zenhub => write!(f, "Zenhub"),
+        }
+    }
+}
+
+/// Rate limit state, as reported by an API.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimit {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset_at: DateTime<Utc>,
+}
+
+fn header_value<T: std::str::FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+    headers.get(name)?.to_str().ok()?.parse().ok()
+}
+
+impl RateLimit {
+    /// Read rate limit state from response headers, if present.
+    ///
+    /// Github reports the requests remaining, and Zenhub the requests used.
+    pub fn from_headers(api: RateLimitedApi, headers: &HeaderMap) -> Option<Self> {
+        let reset_timestamp: i64 = header_value(headers, HEADER_RESET)?;
+        let (limit, remaining) = match api {
+            RateLimitedApi::Github => (
+                header_value(headers, HEADER_LIMIT)?,
+                header_value(headers, HEADER_REMAINING)?,
+            ),
+            RateLimitedApi::Zenhub => {
+                let limit = header_value(headers, HEADER_LIMIT).unwrap_or(ZENHUB_DEFAULT_LIMIT);
+                let used: u32 = header_value(headers, HEADER_USED)?;
+                (limit, limit.saturating_sub(used))
+            }
+        };
+        Some(Self {
+            limit,
+            remaining,
+            reset_at: DateTime::from_utc(NaiveDateTime::from_timestamp(reset_timestamp, 0), Utc),
+        })
+    }
+
+    /// Time until this rate limit resets, or `None` if it already has.
+    fn until_reset(&self) -> Option<Duration> {
+        (self.reset_at - Utc::now()).to_std().ok()
+    }
+}
+
+/// Tracks rate limit state across requests, and decides how long to wait
+/// when a limit is near or hit.
+#[derive(Debug)]
+pub struct RateLimiter {
+    api: RateLimitedApi,
+    state: Mutex<Option<RateLimit>>,
+    max_wait: Duration,
+}
+
+impl RateLimiter {
+    /// Create a new rate limiter, which will wait at most `max_wait` for a limit to reset.
+    pub fn new(api: RateLimitedApi, max_wait: Duration) -> Self {
+        Self {
+            api,
+            state: Mutex::new(None),
+            max_wait,
+        }
+    }
+
+    /// The most recent rate limit state seen, if any.
+    pub fn state(&self) -> Option<RateLimit> {
+        self.state
+            .lock()
+            .expect("Rate limit state poisoned")
+            .clone()
+    }
+
+    /// Wait for `wait`, unless this would take us over our maximum wait.
+    ///
+    /// `waited` is the time already spent waiting for this request.
+    pub fn wait(&self, wait: Duration, waited: Duration) -> Result<(), Error> {
+        let reset_at = Utc::now()
+            + chrono::Duration::from_std(wait).unwrap_or_else(|_| chrono::Duration::zero());
+        if waited + wait > self.max_wait {
+            return Err(Error::RateLimited {
+                api: self.api,
+                reset_at,
+            });
+        }
+        warn!(
+            "{} rate limit reached, waiting {} seconds until {}",
+            self.api,
+            wait.as_secs(),
+            reset_at.format("%H:%M:%S UTC")
+        );
+        std::thread::sleep(wait);
+        Ok(())
+    }
+
+    /// If we know the rate limit is exhausted, how long to wait before sending a request.
+    pub fn before_request(&self) -> Option<Duration> {
+        match self.state() {
+            Some(ref state) if state.remaining == 0 => state.until_reset(),
+            _ => None,
+        }
+    }
+
+    /// If few requests remain, how long to wait so the rest are spread out until the reset.
+    pub fn pace(&self) -> Option<Duration> {
+        let state = self.state()?;
+        if state.remaining == 0 || state.remaining > state.limit / PACE_BELOW_FRACTION {
+            return None;
+        }
+        Some(state.until_reset()? / (state.remaining + 1))
+    }
+
+    /// Update our state from a response.
+    ///
+    /// If the response was rate limited, returns how long to wait before retrying.
+    pub fn after_response(&self, response: &Response) -> Option<Duration> {
+        let headers = response.headers();
+        let rate_limit = RateLimit::from_headers(self.api, headers);
+        if let Some(rate_limit) = &rate_limit {
+            info!(
+                "{} rate limit: {}/{} remaining, resets at {}",
+                self.api, rate_limit.remaining, rate_limit.limit, rate_limit.reset_at
+            );
+            *self.state.lock().expect("Rate limit state poisoned") = Some(rate_limit.clone());
+        }
+
+        let status = response.status();
+        if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+            return None;
+        }
+
+        // Secondary rate limits tell us how long to wait directly
+        if let Some(seconds) = header_value::<u64>(headers, RETRY_AFTER.as_str()) {
+            return Some(Duration::from_secs(seconds));
+        }
+        match rate_limit {
+            // Primary rate limit exhausted, wait until it resets
+            Some(rate_limit) if rate_limit.remaining == 0 => {
+                Some(rate_limit.until_reset().unwrap_or_default())
+            }
+            // A 403 with quota remaining is a normal client error
+            _ if status == StatusCode::FORBIDDEN => None,
+            _ => Some(SECONDARY_RATE_LIMIT_WAIT),
+        }
+    }
+
+    /// Whether a response might be a Github secondary rate limit, which can only be
+    /// told apart from other errors by its body.
+    fn is_possible_secondary_limit(&self, response: &Response) -> bool {
+        self.api == RateLimitedApi::Github
+            && response.status() == StatusCode::FORBIDDEN
+            && !response.headers().contains_key(RETRY_AFTER)
+    }
+
+    /// Read the body of a 403 to see if it is a secondary rate limit.
+    ///
+    /// Returns how long to wait if it is, along with an equivalent response.
+    fn check_secondary_limit(
+        &self,
+        response: Response,
+    ) -> Result<(Option<Duration>, Response), Error> {
+        let mut builder = http::Response::builder()
+            .status(response.status())
+            .version(response.version())
+            .url(response.url().clone());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = response.headers().clone();
+        }
+        let body = response.bytes()?;
+        let wait = if String::from_utf8_lossy(&body)
+            .to_lowercase()
+            .contains(SECONDARY_RATE_LIMIT_MESSAGE)
+        {
+            Some(SECONDARY_RATE_LIMIT_WAIT)
+        } else {
+            None
+        };
+        let response = builder
+            .body(body)
+            .expect("Response parts were already valid");
+        Ok((wait, response.into()))
+    }
+
+    /// Send a request with `send`, pacing requests as the limit nears.
+    ///
+    /// If the limit is hit, waits for it to reset and sends the request again.
+    pub fn execute<F>(&self, request: Request, mut send: F) -> Result<Response, Error>
+    where
+        F: FnMut(Request) -> Result<Response, Error>,
+    {
+        let mut request = request;
+        let mut waited = Duration::from_secs(0);
+        loop {
+            if let Some(wait) = self.before_request() {
+                self.wait(wait, waited)?;
+                waited += wait;
+            } else if let Some(wait) = self.pace() {
+                // Pacing slows us down, but never fails a request
+                let wait = wait.min(self.max_wait.checked_sub(waited).unwrap_or_default());
+                info!(
+                    "Few {} requests remaining, waiting {} ms",
+                    self.api,
+                    wait.as_millis()
+                );
+                std::thread::sleep(wait);
+                waited += wait;
+            }
+
+            // Keep a copy of the request, in case we need to send it again
+            let retry = request.try_clone();
+            let response = send(request)?;
+            let (wait, response) = match self.after_response(&response) {
+                None if self.is_possible_secondary_limit(&response) => {
+                    self.check_secondary_limit(response)?
+                }
+                wait => (wait, response),
+            };
+            match (wait, retry) {
+                (Some(wait), Some(retry)) => {
+                    self.wait(wait, waited)?;
+                    waited += wait;
+                    request = retry;
+                }
+                _ => return Ok(response),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            RateLimit::from_headers(RateLimitedApi::Github, &headers),
+            None
+        );
+
+        headers.insert(HEADER_LIMIT, HeaderValue::from_static("5000"));
+        headers.insert(HEADER_REMAINING, HeaderValue::from_static("4999"));
+        headers.insert(HEADER_RESET, HeaderValue::from_static("1372700873"));
+        assert_eq!(
+            RateLimit::from_headers(RateLimitedApi::Github, &headers),
+            Some(RateLimit {
+                limit: 5000,
+                remaining: 4999,
+                reset_at: Utc.timestamp(1_372_700_873, 0),
+            })
+        );
+    }
+
+    #[test]
+    fn test_zenhub_rate_limit_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER_USED, HeaderValue::from_static("60"));
+        headers.insert(HEADER_RESET, HeaderValue::from_static("1372700873"));
+        assert_eq!(
+            RateLimit::from_headers(RateLimitedApi::Zenhub, &headers),
+            Some(RateLimit {
+                limit: 100,
+                remaining: 40,
+                reset_at: Utc.timestamp(1_372_700_873, 0),
+            })
+        );
+
+        headers.insert(HEADER_LIMIT, HeaderValue::from_static("50"));
+        assert_eq!(
+            RateLimit::from_headers(RateLimitedApi::Zenhub, &headers)
+                .unwrap()
+                .remaining,
+            0
+        );
+    }
+
+    #[test]
+    fn test_pace() {
+        let limiter = RateLimiter::new(RateLimitedApi::Zenhub, Duration::from_secs(10));
+        let set_remaining = |remaining| {
+            *limiter.state.lock().unwrap() = Some(RateLimit {
+                limit: 100,
+                remaining,
+                reset_at: Utc::now() + chrono::Duration::seconds(60),
+            });
+        };
+        assert_eq!(limiter.pace(), None);
+
+        set_remaining(50);
+        assert_eq!(limiter.pace(), None);
+
+        set_remaining(5);
+        let wait = limiter.pace().unwrap();
+        assert!(wait > Duration::from_secs(9) && wait <= Duration::from_secs(10));
+
+        set_remaining(0);
+        assert_eq!(limiter.pace(), None);
+        assert!(limiter.before_request().is_some());
+    }
+
+    #[test]
+    fn test_wait_exceeds_maximum() {
+        let limiter = RateLimiter::new(RateLimitedApi::Github, Duration::from_secs(10));
+        assert!(limiter
+            .wait(Duration::from_secs(0), Duration::from_secs(0))
+            .is_ok());
+        match limiter
+            .wait(Duration::from_secs(6), Duration::from_secs(5))
+            .unwrap_err()
+        {
+            Error::RateLimited { api, .. } => assert_eq!(api, RateLimitedApi::Github),
+            _ => panic!("Unexpected error"),
+        }
+    }
+}
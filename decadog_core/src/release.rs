@@ -0,0 +1,171 @@
+/// Progress of Zenhub releases, and when they are likely to be done.
+use chrono::{DateTime, Duration, FixedOffset};
+
+use crate::github::{Issue, State};
+use crate::zenhub::Release;
+
+/// Progress of a release towards done.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReleaseProgress {
+    pub release: Release,
+    pub issues: usize,
+    pub closed_issues: usize,
+    /// Open issues without an estimate, which are not counted in points.
+    pub unestimated_issues: usize,
+    pub total_points: u32,
+    pub done_points: u32,
+    /// Points closed per week, recently.
+    pub velocity: f64,
+    /// When the remaining points will be done at the recent velocity, if they will.
+    pub projected_date: Option<DateTime<FixedOffset>>,
+}
+
+impl ReleaseProgress {
+    /// Work out the progress of `release` from its issues, with their estimates.
+    ///
+    /// Velocity counts points closed in the `window` before `now`, or since the release
+    /// started if that is more recent.
+    pub fn new(
+        release: Release,
+        issues: &[(Issue, Option<u32>)],
+        window: Duration,
+        now: DateTime<FixedOffset>,
+    ) -> Self {
+        let since = (now - window).max(release.start_date);
+        let mut closed_issues = 0;
+        let mut unestimated_issues = 0;
+        let mut total_points = 0;
+        let mut done_points = 0;
+        let mut recent_points = 0;
+        for (issue, estimate) in issues.iter() {
+            let points = estimate.unwrap_or(0);
+            total_points += points;
+            if issue.state == State::Closed {
+                closed_issues += 1;
+                done_points += points;
+                if issue.closed_at.map(|closed_at| closed_at > since) == Some(true) {
+                    recent_points += points;
+                }
+            } else if estimate.is_none() {
+                unestimated_issues += 1;
+            }
+        }
+
+        let weeks = (now - since).num_seconds() as f64 / Duration::weeks(1).num_seconds() as f64;
+        let velocity = if weeks > 0.0 {
+            f64::from(recent_points) / weeks
+        } else {
+            0.0
+        };
+        let remaining_points = total_points - done_points;
+        let projected_date = if remaining_points == 0 {
+            Some(now)
+        } else if velocity > 0.0 {
+            let weeks_left = f64::from(remaining_points) / velocity;
+            let seconds_left = weeks_left * Duration::weeks(1).num_seconds() as f64;
+            Some(now + Duration::seconds(seconds_left.round() as i64))
+        } else {
+            None
+        };
+
+        Self {
+            release,
+            issues: issues.len(),
+            closed_issues,
+            unestimated_issues,
+            total_points,
+            done_points,
+            velocity,
+            projected_date,
+        }
+    }
+
+    pub fn remaining_points(&self) -> u32 {
+        self.total_points - self.done_points
+    }
+
+    /// Whether the release is projected to be done after its desired end date.
+    ///
+    /// Releases without a projected date are late.
+    pub fn is_late(&self) -> bool {
+        match self.projected_date {
+            Some(projected_date) => projected_date > self.release.desired_end_date,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::zenhub::ReleaseState;
+
+    fn date(day: u32) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(&format!("2020-01-{:02}T00:00:00Z", day)).unwrap()
+    }
+
+    fn release(start_day: u32) -> Release {
+        Release {
+            release_id: "r1".to_owned(),
+            title: "1.0".to_owned(),
+            description: None,
+            start_date: date(start_day),
+            desired_end_date: date(31),
+            created_at: date(1),
+            closed_at: None,
+            state: ReleaseState::Open,
+            repositories: vec![],
+        }
+    }
+
+    fn issue(closed_day: Option<u32>, estimate: Option<u32>) -> (Issue, Option<u32>) {
+        let issue = Issue {
+            state: if closed_day.is_some() {
+                State::Closed
+            } else {
+                State::Open
+            },
+            closed_at: closed_day.map(date),
+            ..Default::default()
+        };
+        (issue, estimate)
+    }
+
+    #[test]
+    fn test_release_progress() {
+        let issues = [
+            // Closed before the window, so not in the velocity
+            issue(Some(2), Some(5)),
+            issue(Some(10), Some(3)),
+            issue(Some(14), Some(4)),
+            issue(None, Some(14)),
+            issue(None, None),
+        ];
+        let progress = ReleaseProgress::new(release(1), &issues, Duration::weeks(1), date(15));
+        assert_eq!(progress.issues, 5);
+        assert_eq!(progress.closed_issues, 3);
+        assert_eq!(progress.unestimated_issues, 1);
+        assert_eq!(progress.total_points, 26);
+        assert_eq!(progress.done_points, 12);
+        assert_eq!(progress.remaining_points(), 14);
+        assert_eq!(progress.velocity, 7.0);
+        assert_eq!(progress.projected_date, Some(date(29)));
+        assert!(!progress.is_late());
+
+        // The window starts no earlier than the release
+        let progress = ReleaseProgress::new(release(8), &issues, Duration::weeks(4), date(15));
+        assert_eq!(progress.velocity, 7.0);
+
+        // Without recent progress there is no projection
+        let progress = ReleaseProgress::new(release(1), &issues, Duration::days(1), date(15));
+        assert_eq!(progress.velocity, 0.0);
+        assert_eq!(progress.projected_date, None);
+        assert!(progress.is_late());
+
+        let progress = ReleaseProgress::new(release(1), &issues[..3], Duration::weeks(1), date(15));
+        assert_eq!(progress.remaining_points(), 0);
+        assert_eq!(progress.projected_date, Some(date(15)));
+    }
+}
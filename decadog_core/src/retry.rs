@@ -0,0 +1,193 @@
+/// Retry requests that fail transiently.
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use log::warn;
+use reqwest::blocking::{Request, Response};
+use reqwest::{Method, StatusCode};
+
+use crate::error::Error;
+
+/// Policy for retrying requests that fail transiently, such as on connection
+/// errors, timeouts and server errors.
+///
+/// Delays between attempts grow exponentially from `base_delay`, with up to
+/// `jitter` added at random to avoid many clients retrying in lockstep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts to make, including the first.
+    pub attempts: u32,
+    /// Delay before the first retry.
+    pub base_delay: Duration,
+    /// Maximum random delay added to each retry.
+    pub jitter: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            base_delay: Duration::from_millis(500),
+            jitter: Duration::from_millis(250),
+        }
+    }
+}
+
+/// Whether sending a request with this method more than once has the same effect as sending it once.
+pub fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS | Method::TRACE
+    )
+}
+
+/// Whether a response status indicates a failure that may succeed on retry.
+fn is_transient_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Whether an error sending a request may succeed on retry.
+fn is_transient_error(error: &Error) -> bool {
+    match error {
+        // Connection errors are not exposed directly, so retry anything that is
+        // not a problem with the request itself
+        Error::Reqwest { source } => {
+            source.is_timeout()
+                || !(source.is_builder() || source.is_redirect() || source.is_status())
+        }
+        _ => false,
+    }
+}
+
+/// A random duration between zero and `max`.
+fn random_duration(max: Duration) -> Duration {
+    let max_millis = max.as_millis() as u64;
+    if max_millis == 0 {
+        return Duration::from_millis(0);
+    }
+    let random = RandomState::new().build_hasher().finish();
+    Duration::from_millis(random % (max_millis + 1))
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retrying, after `attempt` attempts have failed.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .checked_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .unwrap_or(self.base_delay);
+        backoff + random_duration(self.jitter)
+    }
+
+    /// Send a request using `send`, retrying if it fails transiently.
+    ///
+    /// Requests are only retried if their method is idempotent, unless `retry_unsafe` is set.
+    pub fn execute<F>(
+        &self,
+        request: Request,
+        retry_unsafe: bool,
+        mut send: F,
+    ) -> Result<Response, Error>
+    where
+        F: FnMut(Request) -> Result<Response, Error>,
+    {
+        let retryable = retry_unsafe || is_idempotent(request.method());
+        let mut request = request;
+        let mut attempt = 1;
+        loop {
+            // Keep a copy of the request, if we are allowed to retry it
+            let retry = if retryable && attempt < self.attempts {
+                request.try_clone()
+            } else {
+                None
+            };
+            let method = request.method().clone();
+            let url = request.url().clone();
+
+            let result = send(request);
+            let retry = match retry {
+                Some(retry) => retry,
+                None => return result,
+            };
+            match &result {
+                Ok(response) if is_transient_status(response.status()) => warn!(
+                    "{} {} failed with status {}",
+                    method,
+                    url,
+                    response.status()
+                ),
+                Err(error) if is_transient_error(error) => {
+                    warn!("{} {} failed: {}", method, url, error)
+                }
+                _ => return result,
+            };
+
+            let delay = self.delay(attempt);
+            warn!(
+                "Retrying in {}ms (attempt {} of {})",
+                delay.as_millis(),
+                attempt + 1,
+                self.attempts
+            );
+            std::thread::sleep(delay);
+            attempt += 1;
+            request = retry;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_idempotent_methods() {
+        assert!(is_idempotent(&Method::GET));
+        assert!(is_idempotent(&Method::PUT));
+        assert!(!is_idempotent(&Method::POST));
+        assert!(!is_idempotent(&Method::PATCH));
+    }
+
+    #[test]
+    fn test_exponential_delay() {
+        let policy = RetryPolicy {
+            attempts: 5,
+            base_delay: Duration::from_millis(100),
+            jitter: Duration::from_millis(0),
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(4), Duration::from_millis(800));
+    }
+
+    #[test]
+    fn test_delay_jitter() {
+        let policy = RetryPolicy {
+            attempts: 5,
+            base_delay: Duration::from_millis(100),
+            jitter: Duration::from_millis(50),
+        };
+        for _ in 0..100 {
+            let delay = policy.delay(1);
+            assert!(delay >= Duration::from_millis(100));
+            assert!(delay <= Duration::from_millis(150));
+        }
+    }
+}
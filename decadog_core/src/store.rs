@@ -0,0 +1,244 @@
+/// Local store of issue state, kept up to date by webhook events.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::github::Issue;
+use crate::webhook::Event;
+
+/// An issue, as of the last event received for it.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct StoredIssue {
+    pub issue: Issue,
+    /// When the last event for the issue was applied.
+    pub received_at: DateTime<Utc>,
+}
+
+/// Stored state of a single repository.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RepositoryState {
+    /// Issues by number.
+    pub issues: BTreeMap<u32, StoredIssue>,
+}
+
+/// Stored state of all repositories, keyed by lowercased full name (`owner/repo`).
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct StoreState {
+    pub repositories: BTreeMap<String, RepositoryState>,
+}
+
+impl StoredIssue {
+    fn new(issue: &Issue) -> Self {
+        Self {
+            issue: issue.clone(),
+            received_at: Utc::now(),
+        }
+    }
+}
+
+/// A store of issue state, saved as JSON at `path`.
+///
+/// Only issues that have had events since the store was created are known.
+#[derive(Debug, Clone)]
+pub struct Store {
+    path: PathBuf,
+    state: StoreState,
+    max_age: Option<Duration>,
+}
+
+impl Store {
+    /// Open the store at `path`, which is empty if it does not exist yet.
+    pub fn open<P: Into<PathBuf>>(path: P) -> Result<Self, Error> {
+        let path = path.into();
+        let state = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|error| Error::Unknown {
+                description: format!("Invalid store {}: {}", path.display(), error),
+            })?,
+            Err(error) if error.kind() == ErrorKind::NotFound => StoreState::default(),
+            Err(error) => return Err(Error::Io { source: error }),
+        };
+        Ok(Self {
+            path,
+            state,
+            max_age: None,
+        })
+    }
+
+    /// Treat a stored issue as stale once no event has been applied to it for `max_age`.
+    ///
+    /// Events can be missed, so this limits how long a missed change goes unnoticed.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Whether an issue received at `received_at` is within the maximum age, if any.
+    fn is_fresh(&self, received_at: DateTime<Utc>) -> bool {
+        match self.max_age.map(chrono::Duration::from_std) {
+            Some(Ok(max_age)) => received_at + max_age > Utc::now(),
+            Some(Err(_)) | None => true,
+        }
+    }
+
+    /// Save the store, replacing the previous file in a single step.
+    pub fn save(&self) -> Result<(), Error> {
+        let contents = serde_json::to_string(&self.state).map_err(|error| Error::Unknown {
+            description: format!("Failed to serialize store: {}", error),
+        })?;
+        let temporary_path = self.path.with_extension("tmp");
+        let result = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent),
+            _ => Ok(()),
+        }
+        .and_then(|_| fs::write(&temporary_path, contents))
+        .and_then(|_| fs::rename(&temporary_path, &self.path));
+        result.map_err(|error| Error::Io { source: error })
+    }
+
+    pub fn state(&self) -> &StoreState {
+        &self.state
+    }
+
+    fn repository_mut(&mut self, full_name: &str) -> &mut RepositoryState {
+        self.state
+            .repositories
+            .entry(full_name.to_lowercase())
+            .or_default()
+    }
+
+    /// Update the store from an event. Returns whether anything changed.
+    pub fn apply(&mut self, event: &Event) -> bool {
+        match event {
+            Event::Issues {
+                action,
+                issue,
+                repository,
+            } => {
+                let issues = &mut self.repository_mut(&repository.full_name).issues;
+                match action.as_ref() {
+                    // Transferred issues are recreated in the new repository
+                    "deleted" | "transferred" => {
+                        issues.remove(&issue.number);
+                    }
+                    _ => {
+                        issues.insert(issue.number, StoredIssue::new(issue));
+                    }
+                }
+            }
+            Event::IssueComment {
+                issue, repository, ..
+            } => {
+                self.repository_mut(&repository.full_name)
+                    .issues
+                    .insert(issue.number, StoredIssue::new(issue));
+            }
+            Event::Ping => return false,
+        };
+        debug!("Applied webhook event to store");
+        true
+    }
+
+    /// Get a stored issue, by repository full name and number, if it is fresh.
+    pub fn issue(&self, full_name: &str, issue_number: u32) -> Option<&Issue> {
+        self.state
+            .repositories
+            .get(&full_name.to_lowercase())?
+            .issues
+            .get(&issue_number)
+            .filter(|stored| self.is_fresh(stored.received_at))
+            .map(|stored| &stored.issue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::github::State;
+    use crate::webhook::tests::ISSUES_PAYLOAD;
+
+    fn temporary_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "decadog-test-store-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_apply_save_open() {
+        let path = temporary_path("apply-save-open");
+        let mut store = Store::open(&path).unwrap();
+        assert_eq!(store.state(), &StoreState::default());
+
+        let event = Event::parse("issues", ISSUES_PAYLOAD.as_bytes())
+            .unwrap()
+            .unwrap();
+        assert!(store.apply(&event));
+        assert!(!store.apply(&Event::Ping));
+        store.save().unwrap();
+
+        let store = Store::open(&path).unwrap();
+        let issue = store.issue("TomMilligan/Decadog", 12).unwrap();
+        assert_eq!(issue.state, State::Closed);
+        assert_eq!(store.issue("tommilligan/decadog", 13), None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_issue_max_age() {
+        let mut store = Store::open(temporary_path("issue-max-age"))
+            .unwrap()
+            .with_max_age(Duration::from_secs(60));
+        let event = Event::parse("issues", ISSUES_PAYLOAD.as_bytes())
+            .unwrap()
+            .unwrap();
+        store.apply(&event);
+        assert!(store.issue("tommilligan/decadog", 12).is_some());
+
+        // An issue with no recent event may have missed one, so is not served
+        store
+            .state
+            .repositories
+            .get_mut("tommilligan/decadog")
+            .unwrap()
+            .issues
+            .get_mut(&12)
+            .unwrap()
+            .received_at = Utc::now() - chrono::Duration::seconds(120);
+        assert_eq!(store.issue("tommilligan/decadog", 12), None);
+    }
+
+    #[test]
+    fn test_apply_deleted() {
+        let mut store = Store::open(temporary_path("apply-deleted")).unwrap();
+        let event = Event::parse("issues", ISSUES_PAYLOAD.as_bytes())
+            .unwrap()
+            .unwrap();
+        store.apply(&event);
+        let (issue, repository) = match event {
+            Event::Issues {
+                issue, repository, ..
+            } => (issue, repository),
+            _ => panic!("Unexpected event"),
+        };
+        assert!(store.issue("tommilligan/decadog", 12).is_some());
+
+        store.apply(&Event::Issues {
+            action: "deleted".to_owned(),
+            issue,
+            repository,
+        });
+        assert_eq!(store.issue("tommilligan/decadog", 12), None);
+    }
+}
@@ -0,0 +1,253 @@
+/// Receiving Github webhook deliveries.
+use ring::hmac;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::Error;
+use crate::github::Issue;
+
+/// Header giving the name of the event delivered.
+pub const EVENT_HEADER: &str = "X-GitHub-Event";
+/// Header giving the unique id of a delivery.
+pub const DELIVERY_HEADER: &str = "X-GitHub-Delivery";
+/// Header giving the HMAC-SHA256 signature of a delivery, as `sha256=<hex digest>`.
+pub const SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => u8::from_str_radix(std::str::from_utf8(&[*high, *low]).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Sign a delivery body with `secret`, giving the value Github sends in the signature header.
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+    let digest = hmac::sign(&key, body);
+    let hex: String = digest
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("sha256={}", hex)
+}
+
+/// Check that a delivery body was signed with `secret`.
+///
+/// The comparison takes constant time, so it does not leak the expected signature.
+pub fn verify_signature(secret: &[u8], body: &[u8], signature: &str) -> Result<(), Error> {
+    let digest = signature
+        .strip_prefix("sha256=")
+        .and_then(decode_hex)
+        .ok_or_else(|| Error::WebhookSignature {
+            description: format!("Invalid signature header {}", signature),
+        })?;
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+    hmac::verify(&key, body, &digest).map_err(|_| Error::WebhookSignature {
+        description: "Signature does not match delivery".to_owned(),
+    })
+}
+
+/// The repository an event happened in.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct EventRepository {
+    pub id: u64,
+    /// Owner and name, as `owner/repo`.
+    pub full_name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct IssuePayload {
+    action: String,
+    issue: Issue,
+    repository: EventRepository,
+}
+
+/// A webhook event that decadog handles.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// An issue was opened, edited, closed, assigned, and so on.
+    Issues {
+        action: String,
+        issue: Issue,
+        repository: EventRepository,
+    },
+    /// A comment on an issue was created, edited or deleted.
+    IssueComment {
+        action: String,
+        issue: Issue,
+        repository: EventRepository,
+    },
+    /// Sent when a webhook is first set up.
+    Ping,
+}
+
+fn invalid_payload(event: &str, error: serde_json::Error) -> Error {
+    Error::WebhookPayload {
+        description: format!("Invalid {} payload: {}", event, error),
+    }
+}
+
+impl Event {
+    /// Parse the body of a delivery of the event named `event`.
+    ///
+    /// Returns `None` for events that are not handled, including comments on pull requests.
+    pub fn parse(event: &str, body: &[u8]) -> Result<Option<Self>, Error> {
+        let payload: Value =
+            serde_json::from_slice(body).map_err(|error| invalid_payload(event, error))?;
+        let event = match event {
+            "issues" => {
+                let payload: IssuePayload = serde_json::from_value(payload)
+                    .map_err(|error| invalid_payload(event, error))?;
+                Self::Issues {
+                    action: payload.action,
+                    issue: payload.issue,
+                    repository: payload.repository,
+                }
+            }
+            "issue_comment" => {
+                if !payload["issue"]["pull_request"].is_null() {
+                    return Ok(None);
+                }
+                let payload: IssuePayload = serde_json::from_value(payload)
+                    .map_err(|error| invalid_payload(event, error))?;
+                Self::IssueComment {
+                    action: payload.action,
+                    issue: payload.issue,
+                    repository: payload.repository,
+                }
+            }
+            "ping" => Self::Ping,
+            _ => return Ok(None),
+        };
+        Ok(Some(event))
+    }
+}
+
+/// Verify and parse a delivery, given the values of its event and signature headers.
+pub fn receive(
+    secret: &[u8],
+    event: Option<&str>,
+    signature: Option<&str>,
+    body: &[u8],
+) -> Result<Option<Event>, Error> {
+    let signature = signature.ok_or_else(|| Error::WebhookSignature {
+        description: format!("Missing {} header", SIGNATURE_HEADER),
+    })?;
+    verify_signature(secret, body, signature)?;
+    let event = event.ok_or_else(|| Error::WebhookPayload {
+        description: format!("Missing {} header", EVENT_HEADER),
+    })?;
+    Event::parse(event, body)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::github::State;
+
+    pub const SECRET: &[u8] = b"It's a Secret to Everybody";
+
+    /// A recorded `issues` delivery, trimmed to the fields decadog reads.
+    pub const ISSUES_PAYLOAD: &str = r#"{
+  "action": "closed",
+  "issue": {
+    "id": 1, "number": 12, "state": "closed", "title": "Fix the thing",
+    "milestone": {
+      "id": 5, "number": 3, "title": "Sprint 3", "description": null, "state": "open",
+      "open_issues": 4, "closed_issues": 8, "created_at": "2020-01-01T00:00:00Z",
+      "due_on": "2020-01-14T12:00:00Z", "closed_at": null,
+      "html_url": "https://github.com/tommilligan/decadog/milestone/3"
+    },
+    "assignees": [{"login": "tommilligan", "id": 2}],
+    "labels": [],
+    "created_at": "2020-01-01T00:00:00Z",
+    "updated_at": "2020-01-02T00:00:00Z",
+    "closed_at": "2020-01-02T00:00:00Z",
+    "html_url": "https://github.com/tommilligan/decadog/issues/12",
+    "repository_url": "https://api.github.com/repos/tommilligan/decadog"
+  },
+  "repository": {"id": 11, "full_name": "tommilligan/decadog"}
+}"#;
+
+    #[test]
+    fn test_signature() {
+        // Example from the Github documentation
+        let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+        assert_eq!(sign(SECRET, b"Hello, World!"), signature);
+        verify_signature(SECRET, b"Hello, World!", signature).unwrap();
+
+        for signature in &[
+            "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e18",
+            "sha256=757107",
+            "sha256=zz",
+            "sha1=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+            "",
+        ] {
+            match verify_signature(SECRET, b"Hello, World!", signature).unwrap_err() {
+                Error::WebhookSignature { .. } => (),
+                error => panic!("Unexpected error {}", error),
+            }
+        }
+    }
+
+    #[test]
+    fn test_receive_issues() {
+        let body = ISSUES_PAYLOAD.as_bytes();
+        let signature = sign(SECRET, body);
+        let event = receive(SECRET, Some("issues"), Some(&signature), body)
+            .unwrap()
+            .unwrap();
+        match event {
+            Event::Issues {
+                action,
+                issue,
+                repository,
+            } => {
+                assert_eq!(action, "closed");
+                assert_eq!(issue.state, State::Closed);
+                assert_eq!(issue.milestone.unwrap().number, 3);
+                assert_eq!(repository.full_name, "tommilligan/decadog");
+            }
+            _ => panic!("Unexpected event"),
+        }
+
+        match receive(b"wrong secret", Some("issues"), Some(&signature), body).unwrap_err() {
+            Error::WebhookSignature { .. } => (),
+            error => panic!("Unexpected error {}", error),
+        }
+        match receive(SECRET, Some("issues"), None, body).unwrap_err() {
+            Error::WebhookSignature { .. } => (),
+            error => panic!("Unexpected error {}", error),
+        }
+    }
+
+    #[test]
+    fn test_parse_events() {
+        assert_eq!(
+            Event::parse("ping", br#"{"zen": "Keep it logically awesome."}"#).unwrap(),
+            Some(Event::Ping)
+        );
+        assert_eq!(Event::parse("push", b"{}").unwrap(), None);
+
+        let pull_request_comment = br#"{"action": "created", "issue": {"pull_request": {}},
+  "repository": {"id": 11, "full_name": "tommilligan/decadog"}}"#;
+        assert_eq!(
+            Event::parse("issue_comment", pull_request_comment).unwrap(),
+            None
+        );
+
+        for (event, body) in &[("issues", &b"not json"[..]), ("issue_comment", &b"{}"[..])] {
+            match Event::parse(event, body).unwrap_err() {
+                Error::WebhookPayload { .. } => (),
+                error => panic!("Unexpected error {}", error),
+            }
+        }
+    }
+}
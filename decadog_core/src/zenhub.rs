@@ -1,87 +1,218 @@
 use std::collections::hash_map::DefaultHasher;
 use std::fmt;
 use std::hash::Hasher;
+use std::time::Duration;
 
 use chrono::{DateTime, FixedOffset};
-use log::debug;
-use reqwest::header::HeaderMap;
+use log::{debug, warn};
+use reqwest::header::{HeaderMap, AUTHORIZATION};
 use reqwest::{
-    blocking::{Client as ReqwestClient, ClientBuilder, RequestBuilder},
-    Method,
+    blocking::{
+        Client as ReqwestClient, ClientBuilder, Request, RequestBuilder as ReqwestRequestBuilder,
+        Response,
+    },
+    Method, StatusCode,
 };
 use serde::de::DeserializeOwned;
+use serde::ser::{Serialize, Serializer};
 use serde_derive::{Deserialize, Serialize};
+use serde_json::json;
 use url::Url;
 
-use crate::error::Error;
+use crate::base_url::BaseUrl;
+use crate::cache::{Cache, CacheEntry};
+use crate::error::{error_body, Error};
+use crate::rate_limit::{RateLimit, RateLimitedApi, RateLimiter};
+use crate::retry::RetryPolicy;
 
 pub struct Client {
     id: u64,
     reqwest_client: ReqwestClient,
-    base_url: Url,
+    base_url: BaseUrl,
+    rate_limiter: RateLimiter,
+    retry_policy: RetryPolicy,
+    cache: Option<(Cache, Duration)>,
+    /// GraphQL endpoint, and a client authorized to it, used for native sprints.
+    graphql: Option<(Url, ReqwestClient)>,
 }
 
+/// Maximum time to wait for the rate limit to reset, by default.
+///
+/// Zenhub limits requests per minute.
+pub const DEFAULT_MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
 impl fmt::Debug for Client {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Zenhub client {}", self.id)
     }
 }
 
-/// Send a HTTP request to an API, and return the resulting struct.
-trait SendApiExt {
-    fn send_api<T>(self) -> Result<T, Error>
-    where
-        Self: Sized,
-        T: DeserializeOwned;
+/// Header giving the id of a request, to quote to Zenhub support.
+const HEADER_REQUEST_ID: &str = "x-request-id";
 
-    fn send_api_no_response(self) -> Result<(), Error>
-    where
-        Self: Sized;
+/// A common cause of Zenhub error responses.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZenhubErrorKind {
+    InvalidToken,
+    RepositoryNotConnected,
+    IssueNotFound,
+    Other,
 }
 
-/// Send a HTTP request to an API, and return the resulting struct.
-impl SendApiExt for RequestBuilder {
-    fn send_api<T>(self) -> Result<T, Error>
-    where
-        Self: Sized,
-        T: DeserializeOwned,
-    {
-        let response = self.send()?;
-        let status = response.status();
-        if status.is_success() {
-            Ok(response.json()?)
-        } else if status.is_client_error() {
-            Err(Error::Api {
-                description: response.text()?,
-                status,
-            })
-        } else {
-            Err(Error::Api {
-                description: "Unexpected response status code.".to_owned(),
-                status,
-            })
+impl ZenhubErrorKind {
+    /// Classify an error response from `endpoint`.
+    ///
+    /// A missing issue can't be told apart from an unconnected repository by status alone,
+    /// so is only reported when the message says which.
+    fn classify(status: StatusCode, endpoint: Endpoint, message: &str) -> Self {
+        let message = message.to_lowercase();
+        match (status, endpoint) {
+            (StatusCode::UNAUTHORIZED, _) => Self::InvalidToken,
+            (StatusCode::FORBIDDEN, _) if message.contains("token") => Self::InvalidToken,
+            (StatusCode::NOT_FOUND, _) if message.contains("repo") => Self::RepositoryNotConnected,
+            (StatusCode::NOT_FOUND, Endpoint::Repository) => Self::RepositoryNotConnected,
+            (StatusCode::NOT_FOUND, Endpoint::Issue) if message.contains("issue") => {
+                Self::IssueNotFound
+            }
+            _ => Self::Other,
+        }
+    }
+}
+
+/// What a Zenhub endpoint is about, used to classify error responses from it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Endpoint {
+    /// A repository as a whole, which is missing only if the repository is not connected.
+    Repository,
+    /// An issue in a repository.
+    Issue,
+    Other,
+}
+
+impl fmt::Display for ZenhubErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidToken => write!(f, "Zenhub rejected the token, check zenhub_token"),
+            Self::RepositoryNotConnected => write!(
+                f,
+                "Repository is not connected to Zenhub, or the token cannot access it"
+            ),
+            Self::IssueNotFound => write!(f, "Issue not found in the Zenhub workspace"),
+            Self::Other => write!(f, "Zenhub request failed"),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorBody {
+    message: String,
+}
+
+/// Check the status of a response to a `method` request, returning an error if unsuccessful.
+fn check_status(method: Method, endpoint: Endpoint, response: Response) -> Result<Response, Error> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let path = response.url().path().to_owned();
+    let request_id = response
+        .headers()
+        .get(HEADER_REQUEST_ID)
+        .and_then(|request_id| request_id.to_str().ok())
+        .map(|request_id| request_id.to_owned());
+
+    let message = match error_body::<ErrorBody>(response)? {
+        Ok(body) => body.message,
+        Err(text) if text.is_empty() => "No response body".to_owned(),
+        Err(text) => text,
+    };
+    Err(Error::Zenhub {
+        kind: ZenhubErrorKind::classify(status, endpoint, &message),
+        method,
+        path,
+        message,
+        request_id,
+        status,
+    })
+}
+
+/// A request to the Zenhub API, which will be sent by the `Client` that built it.
+pub struct RequestBuilder<'a> {
+    client: &'a Client,
+    builder: ReqwestRequestBuilder,
+    retry_unsafe: bool,
+    uncached: bool,
+    endpoint: Endpoint,
+}
+
+impl<'a> RequestBuilder<'a> {
+    fn new(client: &'a Client, builder: ReqwestRequestBuilder) -> Self {
+        Self {
+            client,
+            builder,
+            retry_unsafe: false,
+            uncached: false,
+            endpoint: Endpoint::Other,
+        }
+    }
+
+    /// Send a JSON body.
+    pub fn json<T: Serialize + ?Sized>(self, json: &T) -> Self {
+        Self {
+            builder: self.builder.json(json),
+            ..self
+        }
+    }
+
+    /// Retry this request on transient failure, even if the method is not idempotent.
+    ///
+    /// Only use this where sending the request twice has the same effect as sending it once.
+    pub fn retry(self) -> Self {
+        Self {
+            retry_unsafe: true,
+            ..self
+        }
+    }
+
+    /// Classify errors as coming from `endpoint`.
+    fn endpoint(self, endpoint: Endpoint) -> Self {
+        Self { endpoint, ..self }
+    }
+
+    /// Send this request to the API, even if a response to it is cached.
+    pub fn uncached(self) -> Self {
+        Self {
+            uncached: true,
+            ..self
         }
     }
 
-    fn send_api_no_response(self) -> Result<(), Error>
+    fn send(self) -> Result<Response, Error> {
+        let client = self.client;
+        let retry_unsafe = self.retry_unsafe;
+        let endpoint = self.endpoint;
+        let request = self.builder.build()?;
+        let method = request.method().clone();
+        let response = if self.uncached {
+            client.execute_uncached(request, retry_unsafe)?
+        } else {
+            client.execute_retrying(request, retry_unsafe)?
+        };
+        check_status(method, endpoint, response)
+    }
+
+    /// Send a HTTP request to the API, and return the resulting struct.
+    pub fn send_api<T>(self) -> Result<T, Error>
     where
-        Self: Sized,
+        T: DeserializeOwned,
     {
-        let response = self.send()?;
-        let status = response.status();
-        if status.is_success() {
-            Ok(())
-        } else if status.is_client_error() {
-            Err(Error::Api {
-                description: response.text()?,
-                status,
-            })
-        } else {
-            Err(Error::Api {
-                description: "Unexpected response status code.".to_owned(),
-                status,
-            })
-        }
+        Ok(self.send()?.json()?)
+    }
+
+    /// Send a HTTP request to the API, ignoring any response body.
+    pub fn send_api_no_response(self) -> Result<(), Error> {
+        self.send()?;
+        Ok(())
     }
 }
 
@@ -100,9 +231,7 @@ impl Client {
 
         let reqwest_client = ClientBuilder::new().default_headers(headers).build()?;
 
-        let base_url = Url::parse(url).map_err(|_| Error::Config {
-            description: format!("Invalid Zenhub base url {}", url),
-        })?;
+        let base_url = BaseUrl::parse(url, "Zenhub")?;
 
         let mut hasher = DefaultHasher::new();
         hasher.write(url.as_bytes());
@@ -113,27 +242,126 @@ impl Client {
             id,
             reqwest_client,
             base_url,
+            rate_limiter: RateLimiter::new(RateLimitedApi::Zenhub, DEFAULT_MAX_RATE_LIMIT_WAIT),
+            retry_policy: RetryPolicy::default(),
+            cache: None,
+            graphql: None,
         })
     }
 
+    /// Use the Zenhub GraphQL API at `url`, authorized with the API key `token`.
+    ///
+    /// Native sprints are only available through the GraphQL API.
+    pub fn with_graphql(mut self, url: &str, token: &str) -> Result<Self, Error> {
+        let url = Url::parse(url).map_err(|_| Error::Config {
+            description: format!("Invalid Zenhub GraphQL url {}.", url),
+        })?;
+        // The GraphQL API has its own key, so must not be sent our REST API token
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            format!("Bearer {}", token)
+                .parse()
+                .map_err(|_| Error::Config {
+                    description: "Invalid Zenhub GraphQL token for Authorization header."
+                        .to_owned(),
+                })?,
+        );
+        let reqwest_client = ClientBuilder::new().default_headers(headers).build()?;
+        self.graphql = Some((url, reqwest_client));
+        Ok(self)
+    }
+
+    /// Set the maximum time to wait for the rate limit to reset, before failing.
+    pub fn with_max_rate_limit_wait(mut self, max_wait: Duration) -> Self {
+        self.rate_limiter = RateLimiter::new(RateLimitedApi::Zenhub, max_wait);
+        self
+    }
+
+    /// Set the policy for retrying requests that fail transiently.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Cache responses to GET requests in `cache`, for up to `ttl`.
+    ///
+    /// Any successful change made through this client clears the cache.
+    pub fn with_cache(mut self, cache: Cache, ttl: Duration) -> Self {
+        self.cache = Some((cache, ttl));
+        self
+    }
+
     pub fn id(&self) -> u64 {
         self.id
     }
 
-    /// Returns a `request::RequestBuilder` authorized to the Zenhub API.
-    pub fn request(&self, method: Method, url: Url) -> RequestBuilder {
-        debug!("{} {}", method, url.as_str());
-        self.reqwest_client.request(method, url)
+    /// The most recent rate limit state reported by Zenhub, if any.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limiter.state()
     }
 
-    /// Get the first Zenhub workspace for a repository.
-    pub fn get_first_workspace(&self, repository_id: u64) -> Result<Workspace, Error> {
-        self.get_workspaces(repository_id)?
-            .into_iter()
-            .next()
-            .ok_or_else(|| Error::Unknown {
-                description: "No Zenhub workspace found for repository.".to_owned(),
+    /// Returns a `RequestBuilder` authorized to the Zenhub API.
+    pub fn request(&self, method: Method, url: Url) -> RequestBuilder<'_> {
+        RequestBuilder::new(self, self.reqwest_client.request(method, url))
+    }
+
+    /// Execute a request against the Zenhub API, retrying on transient failure.
+    ///
+    /// Requests with methods that are not idempotent are only retried if `retry_unsafe` is set.
+    pub fn execute_retrying(
+        &self,
+        request: Request,
+        retry_unsafe: bool,
+    ) -> Result<Response, Error> {
+        let (cache, ttl) = match &self.cache {
+            Some((cache, ttl)) => (cache, ttl),
+            None => return self.execute_uncached(request, retry_unsafe),
+        };
+        if request.method() != Method::GET {
+            let response = self.execute_uncached(request, retry_unsafe)?;
+            if response.status().is_success() {
+                self.clear_cache();
+            }
+            return Ok(response);
+        }
+
+        // Responses may differ by credentials, so include our client id in the key
+        let key = format!("{} {}", self.id, request.url());
+        match cache.get(&key) {
+            Some(entry) if entry.is_fresh(*ttl) => {
+                debug!("Using cached response for {}", &key);
+                Ok(entry.to_response())
+            }
+            _ => {
+                let response = self.execute_uncached(request, retry_unsafe)?;
+                if !response.status().is_success() {
+                    return Ok(response);
+                }
+                let entry = CacheEntry::from_response(response)?;
+                cache.put(&key, &entry);
+                Ok(entry.to_response())
+            }
+        }
+    }
+
+    /// Clear any cached responses, after making a change.
+    fn clear_cache(&self) {
+        if let Some((cache, _)) = &self.cache {
+            // The change was made, so failing to clear the cache must not fail it
+            if let Err(error) = cache.clear() {
+                warn!("Failed to clear Zenhub cache: {}", error);
+            }
+        }
+    }
+
+    fn execute_uncached(&self, request: Request, retry_unsafe: bool) -> Result<Response, Error> {
+        self.retry_policy.execute(request, retry_unsafe, |request| {
+            self.rate_limiter.execute(request, |request| {
+                debug!("{} {}", request.method(), request.url().as_str());
+                Ok(self.reqwest_client.execute(request)?)
             })
+        })
     }
 
     /// Get Zenhub workspaces for a repository.
@@ -141,21 +369,44 @@ impl Client {
         self.request(
             Method::GET,
             self.base_url
-                .join(&format!("/p2/repositories/{}/workspaces", repository_id))?,
+                .join(&format!("p2/repositories/{}/workspaces", repository_id))?,
         )
+        .endpoint(Endpoint::Repository)
         .send_api()
     }
 
+    fn board_request(
+        &self,
+        repository_id: u64,
+        workspace_id: &str,
+    ) -> Result<RequestBuilder<'_>, Error> {
+        Ok(self
+            .request(
+                Method::GET,
+                self.base_url.join(&format!(
+                    "p2/workspaces/{}/repositories/{}/board",
+                    workspace_id, repository_id
+                ))?,
+            )
+            .endpoint(Endpoint::Repository))
+    }
+
     /// Get Zenhub board for a repository.
     pub fn get_board(&self, repository_id: u64, workspace_id: &str) -> Result<Board, Error> {
-        self.request(
-            Method::GET,
-            self.base_url.join(&format!(
-                "/p2/workspaces/{}/repositories/{}/board",
-                workspace_id, repository_id
-            ))?,
-        )
-        .send_api()
+        self.board_request(repository_id, workspace_id)?.send_api()
+    }
+
+    /// Get Zenhub board for a repository, bypassing any cache.
+    ///
+    /// Use this to work out positions in a board that is about to be changed.
+    pub fn get_board_uncached(
+        &self,
+        repository_id: u64,
+        workspace_id: &str,
+    ) -> Result<Board, Error> {
+        self.board_request(repository_id, workspace_id)?
+            .uncached()
+            .send_api()
     }
 
     /// Get Zenhub StartDate for a milestone.
@@ -167,7 +418,7 @@ impl Client {
         self.request(
             Method::GET,
             self.base_url.join(&format!(
-                "/p1/repositories/{}/milestones/{}/start_date",
+                "p1/repositories/{}/milestones/{}/start_date",
                 repository_id, milestone_number
             ))?,
         )
@@ -184,11 +435,12 @@ impl Client {
         self.request(
             Method::POST,
             self.base_url.join(&format!(
-                "/p1/repositories/{}/milestones/{}/start_date",
+                "p1/repositories/{}/milestones/{}/start_date",
                 repository_id, milestone_number
             ))?,
         )
         .json(&start_date)
+        .retry()
         .send_api()
     }
 
@@ -197,10 +449,28 @@ impl Client {
         self.request(
             Method::GET,
             self.base_url.join(&format!(
-                "/p1/repositories/{}/issues/{}",
+                "p1/repositories/{}/issues/{}",
+                repository_id, issue_number
+            ))?,
+        )
+        .endpoint(Endpoint::Issue)
+        .send_api()
+    }
+
+    /// Get the pipeline moves and estimate changes of a Zenhub issue, newest first.
+    pub fn get_issue_events(
+        &self,
+        repository_id: u64,
+        issue_number: u32,
+    ) -> Result<Vec<IssueEvent>, Error> {
+        self.request(
+            Method::GET,
+            self.base_url.join(&format!(
+                "p1/repositories/{}/issues/{}/events",
                 repository_id, issue_number
             ))?,
         )
+        .endpoint(Endpoint::Issue)
         .send_api()
     }
 
@@ -214,14 +484,120 @@ impl Client {
         self.request(
             Method::PUT,
             self.base_url.join(&format!(
-                "/p1/repositories/{}/issues/{}/estimate",
+                "p1/repositories/{}/issues/{}/estimate",
                 repository_id, issue_number
             ))?,
         )
+        .endpoint(Endpoint::Issue)
         .json(&SetEstimate::from(estimate))
         .send_api_no_response()
     }
 
+    /// Get the epics in a repository.
+    pub fn get_epics(&self, repository_id: u64) -> Result<Vec<EpicReference>, Error> {
+        let epics: Epics = self
+            .request(
+                Method::GET,
+                self.base_url
+                    .join(&format!("p1/repositories/{}/epics", repository_id))?,
+            )
+            .endpoint(Endpoint::Repository)
+            .send_api()?;
+        Ok(epics.epic_issues)
+    }
+
+    /// Get an epic, including the issues in it.
+    pub fn get_epic(&self, repository_id: u64, epic_number: u32) -> Result<Epic, Error> {
+        self.request(
+            Method::GET,
+            self.base_url.join(&format!(
+                "p1/repositories/{}/epics/{}",
+                repository_id, epic_number
+            ))?,
+        )
+        .send_api()
+    }
+
+    /// Add issues to, and remove issues from, an epic.
+    pub fn update_epic_issues(
+        &self,
+        repository_id: u64,
+        epic_number: u32,
+        update: &UpdateEpicIssues,
+    ) -> Result<(), Error> {
+        self.request(
+            Method::POST,
+            self.base_url.join(&format!(
+                "p1/repositories/{}/epics/{}/update_issues",
+                repository_id, epic_number
+            ))?,
+        )
+        .json(update)
+        .retry()
+        .send_api_no_response()
+    }
+
+    /// Convert an issue to an epic, containing `issues`.
+    pub fn convert_to_epic(
+        &self,
+        repository_id: u64,
+        issue_number: u32,
+        issues: &[IssueReference],
+    ) -> Result<(), Error> {
+        self.request(
+            Method::POST,
+            self.base_url.join(&format!(
+                "p1/repositories/{}/issues/{}/convert_to_epic",
+                repository_id, issue_number
+            ))?,
+        )
+        .endpoint(Endpoint::Issue)
+        .json(&ConvertToEpic {
+            issues: issues.to_vec(),
+        })
+        .send_api_no_response()
+    }
+
+    /// Convert an epic back to an issue.
+    pub fn convert_to_issue(&self, repository_id: u64, epic_number: u32) -> Result<(), Error> {
+        self.request(
+            Method::POST,
+            self.base_url.join(&format!(
+                "p1/repositories/{}/epics/{}/convert_to_issue",
+                repository_id, epic_number
+            ))?,
+        )
+        .send_api_no_response()
+    }
+
+    /// Get the dependencies between issues in a repository, including those with
+    /// issues in other repositories.
+    pub fn get_dependencies(&self, repository_id: u64) -> Result<Vec<Dependency>, Error> {
+        let dependencies: Dependencies = self
+            .request(
+                Method::GET,
+                self.base_url
+                    .join(&format!("p1/repositories/{}/dependencies", repository_id))?,
+            )
+            .endpoint(Endpoint::Repository)
+            .send_api()?;
+        Ok(dependencies.dependencies)
+    }
+
+    /// Create a dependency between two issues.
+    pub fn create_dependency(&self, dependency: &Dependency) -> Result<Dependency, Error> {
+        self.request(Method::POST, self.base_url.join("p1/dependencies")?)
+            .json(dependency)
+            .send_api()
+    }
+
+    /// Delete a dependency between two issues.
+    pub fn delete_dependency(&self, dependency: &Dependency) -> Result<(), Error> {
+        self.request(Method::DELETE, self.base_url.join("p1/dependencies")?)
+            .json(dependency)
+            .send_api_no_response()
+    }
+
     /// Move issue to a Zenhub pipeline.
     pub fn move_issue(
         &self,
@@ -233,60 +609,610 @@ impl Client {
         self.request(
             Method::POST,
             self.base_url.join(&format!(
-                "/p2/workspaces/{}/repositories/{}/issues/{}/moves",
+                "p2/workspaces/{}/repositories/{}/issues/{}/moves",
                 workspace_id, repository_id, issue_number
             ))?,
         )
+        .endpoint(Endpoint::Issue)
         .json(position)
+        .retry()
         .send_api_no_response()
     }
-}
 
-/// Zenhub Workspace.
-#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
-pub struct Workspace {
-    pub name: Option<String>,
-    pub description: Option<String>,
-    pub id: String,
-    pub repositories: Vec<u64>,
-}
+    /// Get the release reports of a repository.
+    pub fn get_releases(&self, repository_id: u64) -> Result<Vec<Release>, Error> {
+        self.request(
+            Method::GET,
+            self.base_url.join(&format!(
+                "p1/repositories/{}/reports/releases",
+                repository_id
+            ))?,
+        )
+        .endpoint(Endpoint::Repository)
+        .send_api()
+    }
 
-/// Zenhub issue data.
-#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
-pub struct Issue {
-    pub estimate: Option<Estimate>,
-    pub is_epic: bool,
-}
+    /// Get a release report.
+    pub fn get_release(&self, release_id: &str) -> Result<Release, Error> {
+        self.request(
+            Method::GET,
+            self.base_url
+                .join(&format!("p1/reports/release/{}", release_id))?,
+        )
+        .send_api()
+    }
 
-/// A Zenhub estimate.
-#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
-pub struct Estimate {
-    pub value: u32,
-}
+    /// Create a release report in a repository.
+    pub fn create_release(
+        &self,
+        repository_id: u64,
+        release: &CreateRelease,
+    ) -> Result<Release, Error> {
+        self.request(
+            Method::POST,
+            self.base_url.join(&format!(
+                "p1/repositories/{}/reports/release",
+                repository_id
+            ))?,
+        )
+        .endpoint(Endpoint::Repository)
+        .json(release)
+        .send_api()
+    }
 
-impl From<&u32> for Estimate {
-    fn from(value: &u32) -> Self {
-        Estimate { value: *value }
+    /// Get the issues in a release report.
+    pub fn get_release_issues(&self, release_id: &str) -> Result<Vec<IssueReference>, Error> {
+        self.request(
+            Method::GET,
+            self.base_url
+                .join(&format!("p1/reports/release/{}/issues", release_id))?,
+        )
+        .send_api()
     }
-}
 
-impl fmt::Display for Estimate {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.value.to_string())
+    /// Add and remove issues in a release report.
+    pub fn update_release_issues(
+        &self,
+        release_id: &str,
+        update: &UpdateReleaseIssues,
+    ) -> Result<(), Error> {
+        self.request(
+            Method::PATCH,
+            self.base_url
+                .join(&format!("p1/reports/release/{}/issues", release_id))?,
+        )
+        .json(update)
+        .retry()
+        .send_api_no_response()
     }
-}
 
-/// Body to set a Zenhub estimate.
-#[derive(Deserialize, Serialize, Debug, Clone, Default)]
-pub struct SetEstimate {
-    pub estimate: u32,
-}
+    /// Add a repository to a release report.
+    pub fn add_release_repository(
+        &self,
+        release_id: &str,
+        repository_id: u64,
+    ) -> Result<(), Error> {
+        self.request(
+            Method::POST,
+            self.base_url.join(&format!(
+                "p1/reports/release/{}/repository/{}",
+                release_id, repository_id
+            ))?,
+        )
+        .retry()
+        .send_api_no_response()
+    }
 
-impl From<u32> for SetEstimate {
-    fn from(estimate: u32) -> Self {
-        SetEstimate { estimate }
+    /// Remove a repository from a release report.
+    pub fn remove_release_repository(
+        &self,
+        release_id: &str,
+        repository_id: u64,
+    ) -> Result<(), Error> {
+        self.request(
+            Method::DELETE,
+            self.base_url.join(&format!(
+                "p1/reports/release/{}/repository/{}",
+                release_id, repository_id
+            ))?,
+        )
+        .send_api_no_response()
     }
-}
+
+    /// Send a GraphQL `query` named `operation`, and return the resulting data.
+    fn graphql<T: DeserializeOwned>(
+        &self,
+        operation: &str,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> Result<T, Error> {
+        let (url, reqwest_client) = self.graphql.as_ref().ok_or_else(|| Error::Config {
+            description: "Zenhub GraphQL API key required for native sprints.".to_owned(),
+        })?;
+        let request = reqwest_client
+            .request(Method::POST, url.clone())
+            .json(&GraphqlRequest { query, variables })
+            .build()?;
+        // GraphQL is neither cached nor limited like the REST API. Queries are safe
+        // to repeat, and so are the sprint mutations we send.
+        let response = self.retry_policy.execute(request, true, |request| {
+            debug!(
+                "{} {} {}",
+                request.method(),
+                request.url().as_str(),
+                operation
+            );
+            Ok(reqwest_client.execute(request)?)
+        })?;
+        let response: GraphqlResponse<T> =
+            check_status(Method::POST, Endpoint::Other, response)?.json()?;
+
+        if !response.errors.is_empty() {
+            let messages: Vec<String> = response
+                .errors
+                .into_iter()
+                .map(|error| error.message)
+                .collect();
+            return Err(Error::ZenhubGraphql {
+                operation: operation.to_owned(),
+                message: messages.join("; "),
+            });
+        }
+        response.data.ok_or_else(|| Error::ZenhubGraphql {
+            operation: operation.to_owned(),
+            message: "No data in response".to_owned(),
+        })
+    }
+
+    /// Get every page of a GraphQL connection, fetching a page after a cursor with `page`.
+    fn graphql_pages<T, F>(&self, mut page: F) -> Result<Vec<T>, Error>
+    where
+        F: FnMut(Option<String>) -> Result<Connection<T>, Error>,
+    {
+        let mut nodes = Vec::new();
+        let mut after = None;
+        loop {
+            let connection = page(after)?;
+            nodes.extend(connection.nodes);
+            match connection.page_info.end_cursor {
+                Some(cursor) if connection.page_info.has_next_page => after = Some(cursor),
+                _ => return Ok(nodes),
+            }
+        }
+    }
+
+    /// Get the native sprints in a workspace, earliest first.
+    pub fn get_sprints(
+        &self,
+        workspace_id: &str,
+        state: SprintState,
+    ) -> Result<Vec<NativeSprint>, Error> {
+        self.graphql_pages(|after| {
+            let data: WorkspaceData<SprintsData> = self.graphql(
+                "WorkspaceSprints",
+                QUERY_WORKSPACE_SPRINTS,
+                json!({ "workspaceId": workspace_id, "state": state, "after": after }),
+            )?;
+            Ok(data.workspace.sprints)
+        })
+    }
+
+    /// Get the issues in a native sprint.
+    pub fn get_sprint_issues(&self, sprint_id: &str) -> Result<Vec<IssueReference>, Error> {
+        let issues = self.graphql_pages(|after| {
+            let data: NodeData<SprintIssuesData> = self.graphql(
+                "SprintIssues",
+                QUERY_SPRINT_ISSUES,
+                json!({ "sprintId": sprint_id, "after": after }),
+            )?;
+            Ok(data.node.issues)
+        })?;
+        Ok(issues
+            .into_iter()
+            .map(|issue| IssueReference {
+                repo_id: issue.repository.gh_id,
+                issue_number: issue.number,
+            })
+            .collect())
+    }
+
+    /// Get the native sprints an issue is in, open or closed.
+    pub fn get_issue_sprints(
+        &self,
+        repository_id: u64,
+        issue_number: u32,
+    ) -> Result<Vec<NativeSprint>, Error> {
+        self.graphql_pages(|after| {
+            let data: IssueSprintsData = self.graphql(
+                "IssueSprints",
+                QUERY_ISSUE_SPRINTS,
+                json!({
+                    "repositoryGhId": repository_id,
+                    "issueNumber": issue_number,
+                    "after": after
+                }),
+            )?;
+            Ok(data.issue_by_info.sprints)
+        })
+    }
+
+    /// Get the GraphQL id of an issue.
+    fn get_issue_id(&self, repository_id: u64, issue_number: u32) -> Result<String, Error> {
+        let data: IssueByInfoData = self.graphql(
+            "IssueId",
+            QUERY_ISSUE_ID,
+            json!({ "repositoryGhId": repository_id, "issueNumber": issue_number }),
+        )?;
+        Ok(data.issue_by_info.id)
+    }
+
+    /// Add an issue to a native sprint.
+    pub fn add_issue_to_sprint(
+        &self,
+        sprint_id: &str,
+        repository_id: u64,
+        issue_number: u32,
+    ) -> Result<(), Error> {
+        let issue_id = self.get_issue_id(repository_id, issue_number)?;
+        let _: serde_json::Value = self.graphql(
+            "AddIssuesToSprints",
+            MUTATION_ADD_ISSUES_TO_SPRINTS,
+            json!({ "issueIds": [issue_id], "sprintIds": [sprint_id] }),
+        )?;
+        self.clear_cache();
+        Ok(())
+    }
+
+    /// Remove an issue from a native sprint.
+    pub fn remove_issue_from_sprint(
+        &self,
+        sprint_id: &str,
+        repository_id: u64,
+        issue_number: u32,
+    ) -> Result<(), Error> {
+        let issue_id = self.get_issue_id(repository_id, issue_number)?;
+        let _: serde_json::Value = self.graphql(
+            "RemoveIssuesFromSprints",
+            MUTATION_REMOVE_ISSUES_FROM_SPRINTS,
+            json!({ "issueIds": [issue_id], "sprintIds": [sprint_id] }),
+        )?;
+        self.clear_cache();
+        Ok(())
+    }
+}
+
+const QUERY_WORKSPACE_SPRINTS: &str = r#"query WorkspaceSprints($workspaceId: ID!, $state: SprintState!, $after: String) {
+  workspace(id: $workspaceId) {
+    sprints(first: 50, after: $after, filters: { state: { eq: $state } }, orderBy: { field: START_AT, direction: ASC }) {
+      nodes { id name generatedName state startAt endAt }
+      pageInfo { hasNextPage endCursor }
+    }
+  }
+}"#;
+
+const QUERY_SPRINT_ISSUES: &str = r#"query SprintIssues($sprintId: ID!, $after: String) {
+  node(id: $sprintId) {
+    ... on Sprint {
+      issues(first: 100, after: $after) {
+        nodes { number repository { ghId } }
+        pageInfo { hasNextPage endCursor }
+      }
+    }
+  }
+}"#;
+
+const QUERY_ISSUE_SPRINTS: &str = r#"query IssueSprints($repositoryGhId: Int!, $issueNumber: Int!, $after: String) {
+  issueByInfo(repositoryGhId: $repositoryGhId, issueNumber: $issueNumber) {
+    sprints(first: 50, after: $after) {
+      nodes { id name generatedName state startAt endAt }
+      pageInfo { hasNextPage endCursor }
+    }
+  }
+}"#;
+
+const QUERY_ISSUE_ID: &str = r#"query IssueId($repositoryGhId: Int!, $issueNumber: Int!) {
+  issueByInfo(repositoryGhId: $repositoryGhId, issueNumber: $issueNumber) { id }
+}"#;
+
+const MUTATION_ADD_ISSUES_TO_SPRINTS: &str = r#"mutation AddIssuesToSprints($issueIds: [ID!]!, $sprintIds: [ID!]!) {
+  addIssuesToSprints(input: { issueIds: $issueIds, sprintIds: $sprintIds }) { clientMutationId }
+}"#;
+
+const MUTATION_REMOVE_ISSUES_FROM_SPRINTS: &str = r#"mutation RemoveIssuesFromSprints($issueIds: [ID!]!, $sprintIds: [ID!]!) {
+  removeIssuesFromSprints(input: { issueIds: $issueIds, sprintIds: $sprintIds }) { clientMutationId }
+}"#;
+
+#[derive(Serialize, Debug)]
+struct GraphqlRequest<'a> {
+    query: &'a str,
+    variables: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug)]
+struct GraphqlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<ErrorBody>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+/// A page of a GraphQL connection.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Connection<T> {
+    nodes: Vec<T>,
+    page_info: PageInfo,
+}
+
+#[derive(Deserialize, Debug)]
+struct WorkspaceData<T> {
+    workspace: T,
+}
+
+#[derive(Deserialize, Debug)]
+struct NodeData<T> {
+    node: T,
+}
+
+#[derive(Deserialize, Debug)]
+struct SprintsData {
+    sprints: Connection<NativeSprint>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SprintIssuesData {
+    issues: Connection<SprintIssue>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SprintIssueRepository {
+    gh_id: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct SprintIssue {
+    number: u32,
+    repository: SprintIssueRepository,
+}
+
+#[derive(Deserialize, Debug)]
+struct IssueId {
+    id: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct IssueByInfoData {
+    issue_by_info: IssueId,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct IssueSprintsData {
+    issue_by_info: SprintsData,
+}
+
+/// Zenhub Workspace.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Workspace {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub id: String,
+    pub repositories: Vec<u64>,
+}
+
+impl Workspace {
+    /// Whether this workspace has the id `name_or_id`, or the name, ignoring case.
+    pub fn matches(&self, name_or_id: &str) -> bool {
+        self.id == name_or_id
+            || self
+                .name
+                .as_ref()
+                .map(|name| name.eq_ignore_ascii_case(name_or_id))
+                .unwrap_or(false)
+    }
+}
+
+impl fmt::Display for Workspace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{} ({})", name, self.id),
+            None => write!(f, "{}", self.id),
+        }
+    }
+}
+
+/// Zenhub issue data.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Issue {
+    pub estimate: Option<Estimate>,
+    pub is_epic: bool,
+}
+
+/// A Zenhub estimate.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Estimate {
+    pub value: u32,
+}
+
+impl From<&u32> for Estimate {
+    fn from(value: &u32) -> Self {
+        Estimate { value: *value }
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value.to_string())
+    }
+}
+
+/// Body to set a Zenhub estimate.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct SetEstimate {
+    pub estimate: u32,
+}
+
+impl From<u32> for SetEstimate {
+    fn from(estimate: u32) -> Self {
+        SetEstimate { estimate }
+    }
+}
+
+/// A reference to an issue in any repository.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct IssueReference {
+    pub repo_id: u64,
+    pub issue_number: u32,
+}
+
+/// A pipeline named in an issue event.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct EventPipeline {
+    pub name: String,
+}
+
+/// A change to a Zenhub issue.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum IssueEvent {
+    /// The issue moved between pipelines. New issues have no `from_pipeline`.
+    #[serde(rename = "transferIssue")]
+    TransferIssue {
+        created_at: DateTime<FixedOffset>,
+        from_pipeline: Option<EventPipeline>,
+        to_pipeline: EventPipeline,
+    },
+    /// The estimate of the issue was set, changed or removed.
+    #[serde(rename = "estimateIssue")]
+    EstimateIssue {
+        created_at: DateTime<FixedOffset>,
+        from_estimate: Option<Estimate>,
+        to_estimate: Option<Estimate>,
+    },
+    /// Any other event.
+    #[serde(other)]
+    Other,
+}
+
+/// A dependency between two issues, where `blocking` must be done before `blocked`.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Dependency {
+    pub blocking: IssueReference,
+    pub blocked: IssueReference,
+}
+
+#[derive(Deserialize, Debug)]
+struct Dependencies {
+    dependencies: Vec<Dependency>,
+}
+
+/// A reference to an epic.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct EpicReference {
+    pub repo_id: u64,
+    pub issue_number: u32,
+    pub issue_url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Epics {
+    epic_issues: Vec<EpicReference>,
+}
+
+/// An issue in a Zenhub epic.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct EpicIssue {
+    pub repo_id: u64,
+    pub issue_number: u32,
+    pub estimate: Option<Estimate>,
+    #[serde(default)]
+    pub is_epic: bool,
+}
+
+/// A Zenhub epic.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Epic {
+    /// Sum of the estimates of the epic and all issues in it.
+    pub total_epic_estimates: Option<Estimate>,
+    /// Estimate of the epic itself.
+    pub estimate: Option<Estimate>,
+    pub issues: Vec<EpicIssue>,
+}
+
+/// Body to change the issues in a Zenhub epic.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct UpdateEpicIssues {
+    pub add_issues: Vec<IssueReference>,
+    pub remove_issues: Vec<IssueReference>,
+}
+
+/// Whether a release report is open or closed.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseState {
+    Open,
+    Closed,
+}
+
+/// A Zenhub release report.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Release {
+    pub release_id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub start_date: DateTime<FixedOffset>,
+    pub desired_end_date: DateTime<FixedOffset>,
+    pub created_at: DateTime<FixedOffset>,
+    pub closed_at: Option<DateTime<FixedOffset>>,
+    pub state: ReleaseState,
+    pub repositories: Vec<u64>,
+}
+
+impl Release {
+    /// Whether this release has the id `title_or_id`, or the title, ignoring case.
+    pub fn matches(&self, title_or_id: &str) -> bool {
+        self.release_id == title_or_id || self.title.eq_ignore_ascii_case(title_or_id)
+    }
+}
+
+impl fmt::Display for Release {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.title)
+    }
+}
+
+/// Body to create a Zenhub release report.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CreateRelease {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub start_date: DateTime<FixedOffset>,
+    pub desired_end_date: DateTime<FixedOffset>,
+    /// Other repositories in the release, as well as the one it is created in.
+    pub repositories: Vec<u64>,
+}
+
+/// Body to change the issues in a Zenhub release report.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct UpdateReleaseIssues {
+    pub add_issues: Vec<IssueReference>,
+    pub remove_issues: Vec<IssueReference>,
+}
+
+/// Body to convert an issue to a Zenhub epic.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+struct ConvertToEpic {
+    issues: Vec<IssueReference>,
+}
 
 /// A Zenhub reference to an issue.
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
@@ -294,6 +1220,9 @@ pub struct PipelineIssue {
     pub issue_number: u32,
     pub estimate: Option<Estimate>,
     pub is_epic: bool,
+    /// Repository the issue belongs to, if the board spans several.
+    #[serde(default)]
+    pub repo_id: Option<u64>,
 }
 
 /// A Zenhub pipeline.
@@ -304,28 +1233,73 @@ pub struct Pipeline {
     pub issues: Vec<PipelineIssue>,
 }
 
-/// A position of an issue in a Zenhub pipeline.
-#[derive(Deserialize, Serialize, Debug, Clone)]
-pub struct PipelinePosition {
-    pub pipeline_id: String,
-    pub position: String,
+/// A position in a Zenhub pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Position {
+    #[default]
+    Top,
+    Bottom,
+    /// Index from the top of the pipeline, starting at 0.
+    Index(u32),
 }
 
-impl Default for PipelinePosition {
-    fn default() -> Self {
-        Self {
-            pipeline_id: Default::default(),
-            position: "top".to_owned(),
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Top => serializer.serialize_str("top"),
+            Self::Bottom => serializer.serialize_str("bottom"),
+            Self::Index(index) => serializer.serialize_u32(*index),
         }
     }
 }
 
+/// A position of an issue in a Zenhub pipeline.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct PipelinePosition {
+    pub pipeline_id: String,
+    pub position: Position,
+}
+
 /// A Zenhub board.
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct Board {
     pub pipelines: Vec<Pipeline>,
 }
 
+/// Whether a native sprint is open or closed.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SprintState {
+    Open,
+    Closed,
+}
+
+/// A Zenhub native sprint, which spans a workspace.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NativeSprint {
+    pub id: String,
+    /// Name given to the sprint, if it has been renamed.
+    pub name: Option<String>,
+    /// Name generated by Zenhub from the sprint settings.
+    pub generated_name: String,
+    pub state: SprintState,
+    pub start_at: DateTime<FixedOffset>,
+    pub end_at: DateTime<FixedOffset>,
+}
+
+impl NativeSprint {
+    pub fn title(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.generated_name)
+    }
+}
+
+impl fmt::Display for NativeSprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.title())
+    }
+}
+
 /// A Zenhub milestone StartDate.
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct StartDate {
@@ -342,16 +1316,25 @@ impl From<DateTime<FixedOffset>> for StartDate {
 
 #[cfg(test)]
 pub mod tests {
+    use std::time::Duration;
+
     use lazy_static::lazy_static;
     use mockito::mock;
     use pretty_assertions::assert_eq;
+    use serde_json::json;
 
     use super::*;
+    use crate::cache::tests::temporary_cache;
 
     const MOCK_ZENHUB_TOKEN: &str = "mock_token";
+    const MOCK_ZENHUB_GRAPHQL_TOKEN: &str = "mock_graphql_token";
     lazy_static! {
         pub static ref MOCK_ZENHUB_CLIENT: Client =
             Client::new(&mockito::server_url(), MOCK_ZENHUB_TOKEN)
+                .and_then(|client| client.with_graphql(
+                    &format!("{}/public/graphql", mockito::server_url()),
+                    MOCK_ZENHUB_GRAPHQL_TOKEN
+                ))
                 .expect("Couldn't create mock zenhub client");
     }
 
@@ -393,4 +1376,715 @@ pub mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_path_prefix() {
+        let client = Client::new(
+            &format!("{}/zenhub/", mockito::server_url()),
+            MOCK_ZENHUB_TOKEN,
+        )
+        .unwrap();
+        let mock = mock("GET", "/zenhub/p1/repositories/4321/issues/1")
+            .match_header("x-authentication-token", "mock_token")
+            .with_status(200)
+            .with_body(r#"{"estimate": null, "is_epic": false}"#)
+            .create();
+
+        client.get_issue(4321, 1).unwrap();
+        mock.assert();
+    }
+
+    fn fast_retry_client() -> Client {
+        Client::new(&mockito::server_url(), MOCK_ZENHUB_TOKEN)
+            .unwrap()
+            .with_retry_policy(RetryPolicy {
+                attempts: 3,
+                base_delay: Duration::from_millis(0),
+                jitter: Duration::from_millis(0),
+            })
+    }
+
+    #[test]
+    fn test_get_retried_on_server_error() {
+        let client = fast_retry_client();
+        let mock_error = mock("GET", "/p1/repositories/5678/issues/1")
+            .with_status(500)
+            .create();
+        let mock_ok = mock("GET", "/p1/repositories/5678/issues/1")
+            .with_status(200)
+            .with_body(r#"{"estimate": null, "is_epic": true}"#)
+            .create();
+
+        let issue = client.get_issue(5678, 1).unwrap();
+        mock_error.assert();
+        mock_ok.assert();
+        assert_eq!(
+            issue,
+            Issue {
+                estimate: None,
+                is_epic: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_move_retried_with_opt_in() {
+        let client = fast_retry_client();
+        let mock_error = mock(
+            "POST",
+            "/p2/workspaces/abc/repositories/5678/issues/1/moves",
+        )
+        .with_status(503)
+        .create();
+        let mock_ok = mock(
+            "POST",
+            "/p2/workspaces/abc/repositories/5678/issues/1/moves",
+        )
+        .with_status(200)
+        .create();
+
+        client
+            .move_issue(5678, "abc", 1, &PipelinePosition::default())
+            .unwrap();
+        mock_error.assert();
+        mock_ok.assert();
+    }
+
+    #[test]
+    fn test_error_responses() {
+        let mocks = [
+            mock("GET", "/p1/repositories/8000/issues/1")
+                .with_status(401)
+                .with_header("x-request-id", "abc123")
+                .with_body(r#"{"message": "Invalid Token"}"#)
+                .create(),
+            mock("GET", "/p1/repositories/8001/epics")
+                .with_status(404)
+                .with_body(r#"{"message": "Repo not found"}"#)
+                .create(),
+            mock("GET", "/p1/repositories/8002/issues/1")
+                .with_status(404)
+                .with_body(r#"{"message": "Issue not found"}"#)
+                .create(),
+            // An unconnected repository can't be told apart from a missing issue
+            mock("GET", "/p1/repositories/8004/issues/1")
+                .with_status(404)
+                .with_body(r#"{"message": "Not found"}"#)
+                .create(),
+            // A missing epic is not a missing repository
+            mock("GET", "/p1/repositories/8005/epics/3")
+                .with_status(404)
+                .with_body(r#"{"message": "Not found"}"#)
+                .create(),
+            mock("GET", "/p1/repositories/8003/issues/1")
+                .with_status(500)
+                .with_body("")
+                .create(),
+        ];
+        let client = Client::new(&mockito::server_url(), MOCK_ZENHUB_TOKEN)
+            .unwrap()
+            .with_retry_policy(RetryPolicy::none());
+
+        let error = client.get_issue(8000, 1).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Zenhub rejected the token, check zenhub_token. Zenhub error [401 Unauthorized] \
+             for GET /p1/repositories/8000/issues/1: Invalid Token (request id abc123)"
+        );
+        let kind = |error| match error {
+            Error::Zenhub { kind, .. } => kind,
+            error => panic!("Unexpected error {}", error),
+        };
+        assert_eq!(
+            kind(client.get_epics(8001).unwrap_err()),
+            ZenhubErrorKind::RepositoryNotConnected
+        );
+        assert_eq!(
+            kind(client.get_issue(8002, 1).unwrap_err()),
+            ZenhubErrorKind::IssueNotFound
+        );
+        assert_eq!(
+            kind(client.get_issue(8004, 1).unwrap_err()),
+            ZenhubErrorKind::Other
+        );
+        assert_eq!(
+            kind(client.get_epic(8005, 3).unwrap_err()),
+            ZenhubErrorKind::Other
+        );
+        match client.get_issue(8003, 1).unwrap_err() {
+            Error::Zenhub {
+                kind,
+                message,
+                status,
+                ..
+            } => {
+                assert_eq!(kind, ZenhubErrorKind::Other);
+                assert_eq!(message, "No response body");
+                assert_eq!(status, 500);
+            }
+            error => panic!("Unexpected error {}", error),
+        }
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+    }
+
+    #[test]
+    fn test_rate_limited() {
+        let client = Client::new(&mockito::server_url(), MOCK_ZENHUB_TOKEN)
+            .unwrap()
+            .with_max_rate_limit_wait(Duration::from_secs(0));
+        let mock = mock("GET", "/p1/repositories/9876/issues/1")
+            .with_status(403)
+            .with_header("x-ratelimit-limit", "100")
+            .with_header("x-ratelimit-used", "100")
+            .with_header("x-ratelimit-reset", "4102444800")
+            .create();
+
+        match client.get_issue(9876, 1).unwrap_err() {
+            Error::RateLimited { api, reset_at } => {
+                assert_eq!(api, RateLimitedApi::Zenhub);
+                assert!(reset_at > chrono::Utc::now());
+            }
+            error => panic!("Unexpected error {}", error),
+        }
+        assert_eq!(client.rate_limit().unwrap().remaining, 0);
+
+        // Once we know the limit is exhausted, we should not send further requests
+        match client.get_issue(9876, 1).unwrap_err() {
+            Error::RateLimited { .. } => (),
+            error => panic!("Unexpected error {}", error),
+        }
+        mock.assert();
+    }
+
+    #[test]
+    fn test_too_many_requests_retried() {
+        let mock_limited = mock("GET", "/p1/repositories/9877/issues/1")
+            .with_status(429)
+            .with_header("retry-after", "0")
+            .create();
+        let mock_ok = mock("GET", "/p1/repositories/9877/issues/1")
+            .with_status(200)
+            .with_body(r#"{"estimate": null, "is_epic": false}"#)
+            .create();
+
+        MOCK_ZENHUB_CLIENT.get_issue(9877, 1).unwrap();
+        mock_limited.assert();
+        mock_ok.assert();
+    }
+
+    #[test]
+    fn test_pipeline_position() {
+        let position = |position| {
+            serde_json::to_value(PipelinePosition {
+                pipeline_id: "p1".to_owned(),
+                position,
+            })
+            .unwrap()
+        };
+        assert_eq!(
+            position(Position::Top),
+            json!({"pipeline_id": "p1", "position": "top"})
+        );
+        assert_eq!(
+            position(Position::Bottom),
+            json!({"pipeline_id": "p1", "position": "bottom"})
+        );
+        assert_eq!(
+            position(Position::Index(3)),
+            json!({"pipeline_id": "p1", "position": 3})
+        );
+    }
+
+    #[test]
+    fn test_cached_get_until_changed() {
+        let client = Client::new(&mockito::server_url(), MOCK_ZENHUB_TOKEN)
+            .unwrap()
+            .with_cache(
+                temporary_cache("zenhub-until-changed"),
+                Duration::from_secs(300),
+            );
+        let mock_get = mock("GET", "/p1/repositories/9012/issues/1")
+            .with_status(200)
+            .with_body(r#"{"estimate": null, "is_epic": false}"#)
+            .expect(2)
+            .create();
+        let mock_set = mock("PUT", "/p1/repositories/9012/issues/1/estimate")
+            .with_status(200)
+            .create();
+
+        // Second fetch should use the cache
+        client.get_issue(9012, 1).unwrap();
+        client.get_issue(9012, 1).unwrap();
+
+        // Making a change should clear the cache
+        client.set_estimate(9012, 1, 3).unwrap();
+        client.get_issue(9012, 1).unwrap();
+
+        mock_get.assert();
+        mock_set.assert();
+    }
+
+    #[test]
+    fn test_uncached_board_bypasses_cache() {
+        let client = Client::new(&mockito::server_url(), MOCK_ZENHUB_TOKEN)
+            .unwrap()
+            .with_cache(
+                temporary_cache("zenhub-uncached-board"),
+                Duration::from_secs(300),
+            );
+        let mock_board = mock("GET", "/p2/workspaces/uncached/repositories/9014/board")
+            .with_status(200)
+            .with_body(r#"{"pipelines": []}"#)
+            .expect(2)
+            .create();
+
+        // Second fetch should use the cache, but the uncached fetch should not
+        client.get_board(9014, "uncached").unwrap();
+        client.get_board(9014, "uncached").unwrap();
+        client.get_board_uncached(9014, "uncached").unwrap();
+
+        mock_board.assert();
+    }
+
+    #[test]
+    fn test_get_epics() {
+        let mock_epics = mock("GET", "/p1/repositories/2345/epics")
+            .with_status(200)
+            .with_body(
+                r#"{"epic_issues": [{"issue_number": 3, "repo_id": 2345,
+  "issue_url": "https://github.com/tommilligan/decadog/issues/3"}]}"#,
+            )
+            .create();
+        let mock_epic = mock("GET", "/p1/repositories/2345/epics/3")
+            .with_status(200)
+            .with_body(
+                r#"{"total_epic_estimates": {"value": 8}, "estimate": {"value": 3},
+  "pipeline": {"name": "In Progress", "pipeline_id": "p1"},
+  "issues": [{"issue_number": 4, "repo_id": 2345, "estimate": {"value": 5},
+    "is_epic": false, "pipeline": {"name": "Done", "pipeline_id": "p2"}},
+    {"issue_number": 5, "repo_id": 6789}]}"#,
+            )
+            .create();
+
+        let epics = MOCK_ZENHUB_CLIENT.get_epics(2345).unwrap();
+        assert_eq!(
+            epics,
+            vec![EpicReference {
+                repo_id: 2345,
+                issue_number: 3,
+                issue_url: "https://github.com/tommilligan/decadog/issues/3".to_owned(),
+            }]
+        );
+        let epic = MOCK_ZENHUB_CLIENT.get_epic(2345, 3).unwrap();
+        mock_epics.assert();
+        mock_epic.assert();
+        assert_eq!(epic.total_epic_estimates, Some(Estimate { value: 8 }));
+        assert_eq!(
+            epic.issues,
+            vec![
+                EpicIssue {
+                    repo_id: 2345,
+                    issue_number: 4,
+                    estimate: Some(Estimate { value: 5 }),
+                    is_epic: false,
+                },
+                EpicIssue {
+                    repo_id: 6789,
+                    issue_number: 5,
+                    estimate: None,
+                    is_epic: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_change_epics() {
+        let issue = IssueReference {
+            repo_id: 2345,
+            issue_number: 4,
+        };
+        let mock_update = mock("POST", "/p1/repositories/2345/epics/3/update_issues")
+            .match_body(mockito::Matcher::Json(serde_json::json!({
+                "add_issues": [{"repo_id": 2345, "issue_number": 4}],
+                "remove_issues": []
+            })))
+            .with_status(200)
+            .with_body(r#"{"added_issues": [], "removed_issues": []}"#)
+            .create();
+        let mock_convert = mock("POST", "/p1/repositories/2345/issues/6/convert_to_epic")
+            .match_body(mockito::Matcher::Json(serde_json::json!({
+                "issues": [{"repo_id": 2345, "issue_number": 4}]
+            })))
+            .with_status(200)
+            .create();
+        let mock_revert = mock("POST", "/p1/repositories/2345/epics/6/convert_to_issue")
+            .with_status(200)
+            .create();
+
+        MOCK_ZENHUB_CLIENT
+            .update_epic_issues(
+                2345,
+                3,
+                &UpdateEpicIssues {
+                    add_issues: vec![issue.clone()],
+                    remove_issues: vec![],
+                },
+            )
+            .unwrap();
+        MOCK_ZENHUB_CLIENT
+            .convert_to_epic(2345, 6, &[issue])
+            .unwrap();
+        MOCK_ZENHUB_CLIENT.convert_to_issue(2345, 6).unwrap();
+        mock_update.assert();
+        mock_convert.assert();
+        mock_revert.assert();
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let dependency = Dependency {
+            blocking: IssueReference {
+                repo_id: 3456,
+                issue_number: 1,
+            },
+            blocked: IssueReference {
+                repo_id: 3457,
+                issue_number: 2,
+            },
+        };
+        let body = r#"{"blocking": {"repo_id": 3456, "issue_number": 1},
+  "blocked": {"repo_id": 3457, "issue_number": 2}}"#;
+        let mock_get = mock("GET", "/p1/repositories/3456/dependencies")
+            .with_status(200)
+            .with_body(format!(r#"{{"dependencies": [{}]}}"#, body))
+            .create();
+        let mock_create = mock("POST", "/p1/dependencies")
+            .match_body(mockito::Matcher::JsonString(body.to_owned()))
+            .with_status(200)
+            .with_body(body)
+            .create();
+        let mock_delete = mock("DELETE", "/p1/dependencies")
+            .match_body(mockito::Matcher::JsonString(body.to_owned()))
+            .with_status(204)
+            .create();
+
+        assert_eq!(
+            MOCK_ZENHUB_CLIENT.get_dependencies(3456).unwrap(),
+            vec![dependency.clone()]
+        );
+        assert_eq!(
+            MOCK_ZENHUB_CLIENT.create_dependency(&dependency).unwrap(),
+            dependency
+        );
+        MOCK_ZENHUB_CLIENT.delete_dependency(&dependency).unwrap();
+        mock_get.assert();
+        mock_create.assert();
+        mock_delete.assert();
+    }
+
+    #[test]
+    fn test_get_issue_events() {
+        let mock = mock("GET", "/p1/repositories/3456/issues/7/events")
+            .with_status(200)
+            .with_body(
+                r#"[
+  {"user_id": 1, "type": "transferIssue", "created_at": "2020-01-03T00:00:00.000Z",
+   "from_pipeline": {"name": "In Progress"}, "to_pipeline": {"name": "Done"},
+   "workspace_id": "abc"},
+  {"user_id": 1, "type": "estimateIssue", "created_at": "2020-01-02T00:00:00.000Z",
+   "to_estimate": {"value": 3}},
+  {"user_id": 1, "type": "convertIssueToEpic", "created_at": "2020-01-01T00:00:00.000Z"}
+]"#,
+            )
+            .create();
+
+        let events = MOCK_ZENHUB_CLIENT.get_issue_events(3456, 7).unwrap();
+        mock.assert();
+        let date = |value| DateTime::parse_from_rfc3339(value).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                IssueEvent::TransferIssue {
+                    created_at: date("2020-01-03T00:00:00Z"),
+                    from_pipeline: Some(EventPipeline {
+                        name: "In Progress".to_owned()
+                    }),
+                    to_pipeline: EventPipeline {
+                        name: "Done".to_owned()
+                    },
+                },
+                IssueEvent::EstimateIssue {
+                    created_at: date("2020-01-02T00:00:00Z"),
+                    from_estimate: None,
+                    to_estimate: Some(Estimate { value: 3 }),
+                },
+                IssueEvent::Other,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_releases() {
+        let mock = mock("GET", "/p1/repositories/9200/reports/releases")
+            .match_header("x-authentication-token", "mock_token")
+            .with_status(200)
+            .with_body(
+                r#"[{"release_id": "59d3cd520a430a6344fd3bdb", "title": "Test release",
+  "description": "", "start_date": "2017-10-01T19:00:00.000Z",
+  "desired_end_date": "2017-10-03T19:00:00.000Z", "created_at": "2017-10-03T17:48:02.701Z",
+  "closed_at": null, "state": "open", "repositories": [9200, 9201]}]"#,
+            )
+            .create();
+
+        let releases = MOCK_ZENHUB_CLIENT.get_releases(9200).unwrap();
+        mock.assert();
+
+        assert_eq!(releases.len(), 1);
+        let release = &releases[0];
+        assert!(release.matches("59d3cd520a430a6344fd3bdb"));
+        assert!(release.matches("test RELEASE"));
+        assert_eq!(release.state, ReleaseState::Open);
+        assert_eq!(release.repositories, vec![9200, 9201]);
+        assert_eq!(
+            release.desired_end_date,
+            DateTime::parse_from_rfc3339("2017-10-03T19:00:00Z").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_update_release_issues() {
+        let mock = mock("PATCH", "/p1/reports/release/release_update/issues")
+            .match_body(mockito::Matcher::Json(json!({
+                "add_issues": [{"repo_id": 9300, "issue_number": 3}],
+                "remove_issues": [{"repo_id": 9301, "issue_number": 4}]
+            })))
+            .with_status(200)
+            .with_body(r#"{"added": [{"repo_id": 9300, "issue_number": 3}], "removed": []}"#)
+            .create();
+
+        let update = UpdateReleaseIssues {
+            add_issues: vec![IssueReference {
+                repo_id: 9300,
+                issue_number: 3,
+            }],
+            remove_issues: vec![IssueReference {
+                repo_id: 9301,
+                issue_number: 4,
+            }],
+        };
+        MOCK_ZENHUB_CLIENT
+            .update_release_issues("release_update", &update)
+            .unwrap();
+        mock.assert();
+    }
+
+    #[test]
+    fn test_get_sprints() {
+        let sprint = |id: &str, name: Option<&str>| {
+            json!({
+                "id": id,
+                "name": name,
+                "generatedName": "Sprint: Jan 6 - Jan 20, 2020",
+                "state": "OPEN",
+                "startAt": "2020-01-06T12:00:00Z",
+                "endAt": "2020-01-20T12:00:00Z"
+            })
+        };
+        let page = |variables, nodes, end_cursor: Option<&str>| {
+            mock("POST", "/public/graphql")
+                .match_header("authorization", "Bearer mock_graphql_token")
+                .match_header("x-authentication-token", mockito::Matcher::Missing)
+                .match_body(mockito::Matcher::PartialJson(
+                    json!({ "variables": variables }),
+                ))
+                .with_status(200)
+                .with_body(
+                    json!({ "data": { "workspace": { "sprints": {
+                        "nodes": nodes,
+                        "pageInfo": {
+                            "hasNextPage": end_cursor.is_some(),
+                            "endCursor": end_cursor
+                        }
+                    }}}})
+                    .to_string(),
+                )
+                .create()
+        };
+        let mocks = [
+            page(
+                json!({ "workspaceId": "sprints_workspace", "state": "OPEN", "after": null }),
+                json!([sprint("sprint_1", None)]),
+                Some("cursor_1"),
+            ),
+            page(
+                json!({ "workspaceId": "sprints_workspace", "after": "cursor_1" }),
+                json!([sprint("sprint_2", Some("Sprint 2"))]),
+                None,
+            ),
+        ];
+
+        let sprints = MOCK_ZENHUB_CLIENT
+            .get_sprints("sprints_workspace", SprintState::Open)
+            .unwrap();
+        for mock in mocks.iter() {
+            mock.assert();
+        }
+
+        assert_eq!(sprints.len(), 2);
+        assert_eq!(sprints[0].id, "sprint_1");
+        assert_eq!(sprints[0].title(), "Sprint: Jan 6 - Jan 20, 2020");
+        assert_eq!(sprints[1].title(), "Sprint 2");
+        assert_eq!(sprints[1].state, SprintState::Open);
+        assert_eq!(
+            sprints[1].end_at,
+            DateTime::parse_from_rfc3339("2020-01-20T12:00:00Z").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_issue_sprints() {
+        let mock = mock("POST", "/public/graphql")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "variables": { "repositoryGhId": 9101, "issueNumber": 8, "after": null }
+            })))
+            .with_status(200)
+            .with_body(
+                json!({ "data": { "issueByInfo": { "sprints": {
+                    "nodes": [{
+                        "id": "sprint_closed",
+                        "name": null,
+                        "generatedName": "Sprint: Jan 6 - Jan 20, 2020",
+                        "state": "CLOSED",
+                        "startAt": "2020-01-06T12:00:00Z",
+                        "endAt": "2020-01-20T12:00:00Z"
+                    }],
+                    "pageInfo": { "hasNextPage": false, "endCursor": null }
+                }}}})
+                .to_string(),
+            )
+            .create();
+
+        let sprints = MOCK_ZENHUB_CLIENT.get_issue_sprints(9101, 8).unwrap();
+        mock.assert();
+        assert_eq!(sprints.len(), 1);
+        assert_eq!(sprints[0].id, "sprint_closed");
+        assert_eq!(sprints[0].state, SprintState::Closed);
+    }
+
+    #[test]
+    fn test_add_issue_to_sprint() {
+        let issue_id = mock("POST", "/public/graphql")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "variables": { "repositoryGhId": 9100, "issueNumber": 7 }
+            })))
+            .with_status(200)
+            .with_body(r#"{"data": {"issueByInfo": {"id": "issue_9100_7"}}}"#)
+            .create();
+        let add = mock("POST", "/public/graphql")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "variables": { "issueIds": ["issue_9100_7"], "sprintIds": ["sprint_add"] }
+            })))
+            .with_status(200)
+            .with_body(r#"{"data": {"addIssuesToSprints": {"clientMutationId": null}}}"#)
+            .create();
+
+        MOCK_ZENHUB_CLIENT
+            .add_issue_to_sprint("sprint_add", 9100, 7)
+            .unwrap();
+        issue_id.assert();
+        add.assert();
+    }
+
+    #[test]
+    fn test_sprint_changes_clear_cache() {
+        let client = Client::new(&mockito::server_url(), MOCK_ZENHUB_TOKEN)
+            .and_then(|client| {
+                client.with_graphql(
+                    &format!("{}/public/graphql", mockito::server_url()),
+                    MOCK_ZENHUB_GRAPHQL_TOKEN,
+                )
+            })
+            .unwrap()
+            .with_cache(
+                temporary_cache("zenhub-sprint-changes"),
+                Duration::from_secs(300),
+            );
+        let mock_get = mock("GET", "/p1/repositories/9013/issues/1")
+            .with_status(200)
+            .with_body(r#"{"estimate": null, "is_epic": false}"#)
+            .expect(2)
+            .create();
+        let mock_issues = mock("POST", "/public/graphql")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "variables": { "sprintId": "sprint_cached" }
+            })))
+            .with_status(200)
+            .with_body(
+                json!({ "data": { "node": { "issues": {
+                    "nodes": [],
+                    "pageInfo": { "hasNextPage": false, "endCursor": null }
+                }}}})
+                .to_string(),
+            )
+            .create();
+        let mock_issue_id = mock("POST", "/public/graphql")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "variables": { "repositoryGhId": 9013, "issueNumber": 1 }
+            })))
+            .with_status(200)
+            .with_body(r#"{"data": {"issueByInfo": {"id": "issue_9013_1"}}}"#)
+            .create();
+        let mock_remove = mock("POST", "/public/graphql")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "variables": { "issueIds": ["issue_9013_1"], "sprintIds": ["sprint_cached"] }
+            })))
+            .with_status(200)
+            .with_body(r#"{"data": {"removeIssuesFromSprints": {"clientMutationId": null}}}"#)
+            .create();
+
+        // Reading from GraphQL should keep the cache
+        client.get_issue(9013, 1).unwrap();
+        client.get_sprint_issues("sprint_cached").unwrap();
+        client.get_issue(9013, 1).unwrap();
+
+        // Changing a sprint should clear it
+        client
+            .remove_issue_from_sprint("sprint_cached", 9013, 1)
+            .unwrap();
+        client.get_issue(9013, 1).unwrap();
+
+        mock_get.assert();
+        mock_issues.assert();
+        mock_issue_id.assert();
+        mock_remove.assert();
+    }
+
+    #[test]
+    fn test_graphql_errors() {
+        let mock = mock("POST", "/public/graphql")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "variables": { "sprintId": "sprint_missing" }
+            })))
+            .with_status(200)
+            .with_body(r#"{"data": null, "errors": [{"message": "Sprint not found"}]}"#)
+            .create();
+
+        let error = MOCK_ZENHUB_CLIENT
+            .get_sprint_issues("sprint_missing")
+            .unwrap_err();
+        mock.assert();
+        assert_eq!(
+            error.to_string(),
+            "Zenhub GraphQL error in SprintIssues: Sprint not found"
+        );
+
+        let client = Client::new(&mockito::server_url(), MOCK_ZENHUB_TOKEN).unwrap();
+        match client.get_sprint_issues("sprint_missing").unwrap_err() {
+            Error::Config { .. } => (),
+            error => panic!("Unexpected error {}", error),
+        }
+    }
 }